@@ -0,0 +1,347 @@
+pub mod ac;
+pub mod dc;
+pub mod newton;
+pub mod symbolic;
+pub mod transfer_function;
+pub mod transient;
+
+use num::complex::Complex64;
+use std::collections::BTreeMap;
+use std::collections::HashMap;
+
+use crate::analysis::newton::{Homotopy, Strategy};
+use crate::analysis::transient::Integration;
+use crate::device::SmallSignal;
+use crate::graph::component::{ComponentId, ComponentKind};
+use crate::graph::node::Node;
+use crate::mna::MnaSystem;
+use crate::Circuit;
+
+// selects the model each component stamps
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Analysis {
+    Dc,
+    // small signal at the angular frequency omega (rad/s)
+    Ac { omega: f64 },
+    // operating point at t = 0 with the initial conditions of capacitors and inductors forced
+    Initial,
+    // one time step with the companion models of capacitors and inductors
+    Transient { step: f64, method: Integration },
+}
+
+// where a nonlinear component is linearized: the junction voltage of a diode, (vbe, vbc) of a
+// BJT or (vgs, vds, vbs) of a MOSFET
+pub type Bias = [f64; 3];
+
+// what a component needs to know beyond its value
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct ComponentState {
+    pub initial_condition: Option<Complex64>,
+    // voltage across and current into the component at the previous time point
+    pub voltage: Complex64,
+    pub current: Complex64,
+    // branch of the controlling source of a current controlled source
+    pub controlling_branch: Option<usize>,
+    pub bias: Bias,
+}
+
+// node voltages and component currents of one solve of the MNA system
+pub struct Solution {
+    // indexed by node id, the ground node is 0 V
+    node_voltages: Vec<Complex64>,
+    // current flowing into the positive (first) terminal and through the component
+    currents: BTreeMap<ComponentId, Complex64>,
+    // the bias nonlinear components were linearized at
+    biases: BTreeMap<ComponentId, Bias>,
+    // gm, gds and rπ of the transistors at their bias
+    small_signals: BTreeMap<ComponentId, SmallSignal>,
+    strategy: Strategy,
+}
+
+impl Solution {
+    pub fn node_voltage(&self, node_id: usize) -> Option<Complex64> {
+        self.node_voltages.get(node_id).copied()
+    }
+
+    pub fn node_voltages(&self) -> &[Complex64] {
+        &self.node_voltages
+    }
+
+    pub fn current(&self, component_id: ComponentId) -> Option<Complex64> {
+        self.currents.get(&component_id).copied()
+    }
+
+    pub fn currents(&self) -> &BTreeMap<ComponentId, Complex64> {
+        &self.currents
+    }
+
+    pub fn small_signal(&self, component_id: ComponentId) -> Option<SmallSignal> {
+        self.small_signals.get(&component_id).copied()
+    }
+
+    // the convergence aid that found the operating point
+    pub fn strategy(&self) -> Strategy {
+        self.strategy
+    }
+
+    pub(crate) fn biases(&self) -> &BTreeMap<ComponentId, Bias> {
+        &self.biases
+    }
+}
+
+// maps the terminals of a circuit onto the node voltage unknowns of the MNA system
+pub struct Topology {
+    nodes: Vec<Node>,
+    terminal_nodes: HashMap<usize, usize>,
+    ground: usize,
+    node_count: usize,
+}
+
+impl Topology {
+    pub fn nodes(&self) -> &[Node] {
+        &self.nodes
+    }
+
+    pub fn ground(&self) -> usize {
+        self.ground
+    }
+
+    pub fn node_count(&self) -> usize {
+        self.node_count
+    }
+
+    // number of unknown node voltages (every node except ground)
+    pub fn unknown_count(&self) -> usize {
+        self.node_count - 1
+    }
+
+    pub fn node_of(&self, terminal_id: &usize) -> Option<usize> {
+        self.terminal_nodes.get(terminal_id).copied()
+    }
+
+    // index of the node voltage in the MNA system, None for the ground node
+    pub fn unknown_of_node(&self, node_id: usize) -> Option<usize> {
+        match node_id.cmp(&self.ground) {
+            std::cmp::Ordering::Less => Some(node_id),
+            std::cmp::Ordering::Equal => None,
+            std::cmp::Ordering::Greater => Some(node_id - 1),
+        }
+    }
+
+    pub fn unknown(&self, terminal_id: &usize) -> Option<usize> {
+        self.node_of(terminal_id)
+            .and_then(|node_id| self.unknown_of_node(node_id))
+    }
+}
+
+impl Circuit {
+    // components with a branch current unknown and the index of their branch
+    fn branches(&self, analysis: Analysis) -> HashMap<ComponentId, usize> {
+        let mut branches = HashMap::<ComponentId, usize>::new();
+        for (id, component) in self.components.iter() {
+            if component.has_branch(analysis) {
+                branches.insert(*id, branches.len());
+            }
+        }
+        branches
+    }
+
+    // branch index of the controlling source, None for components that are not current controlled
+    pub(crate) fn controlling_branch(
+        &self,
+        id: &ComponentId,
+        branches: &HashMap<ComponentId, usize>,
+    ) -> Result<Option<usize>, String> {
+        let component = &self.components[id];
+        if !component.kind().is_current_controlled() {
+            return Ok(None);
+        }
+        let source = component
+            .controlling_source()
+            .ok_or(format!("{} has no controlling source", component.name()))?;
+        branches.get(&source).copied().map(Some).ok_or(format!(
+            "{}: controlling source {} does not exist",
+            component.name(),
+            source
+        ))
+    }
+
+    // state of a component, the previous solution is the last time point of a transient analysis
+    fn component_state(
+        &self,
+        id: &ComponentId,
+        topology: &Topology,
+        branches: &HashMap<ComponentId, usize>,
+        previous: Option<&Solution>,
+        biases: &BTreeMap<ComponentId, Bias>,
+    ) -> Result<ComponentState, String> {
+        let component = &self.components[id];
+        let mut state = ComponentState {
+            initial_condition: component.initial_condition(),
+            controlling_branch: self.controlling_branch(id, branches)?,
+            bias: biases.get(id).copied().unwrap_or_default(),
+            ..Default::default()
+        };
+        if let Some(previous) = previous {
+            let voltage = |terminal_id: &usize| {
+                topology
+                    .node_of(terminal_id)
+                    .and_then(|node_id| previous.node_voltage(node_id))
+                    .unwrap_or_default()
+            };
+            state.voltage = voltage(&component.ids()[0]) - voltage(&component.ids()[1]);
+            state.current = previous.current(*id).unwrap_or_default();
+        }
+        Ok(state)
+    }
+
+    // independent sources only drive the AC analysis with their phasor, the other analyses
+    // with their DC value scaled by the homotopy
+    fn stamped_value(&self, id: &ComponentId, analysis: Analysis, homotopy: Homotopy) -> Complex64 {
+        let component = &self.components[id];
+        match (component.kind(), analysis) {
+            (ComponentKind::VoltageSource | ComponentKind::CurrentSource, Analysis::Ac { .. }) => {
+                component.ac()
+            }
+            (ComponentKind::VoltageSource | ComponentKind::CurrentSource, _) => {
+                component.value() * homotopy.source_factor
+            }
+            _ => component.value(),
+        }
+    }
+
+    // stamps every component with the model of the analysis, nonlinear ones linearized at
+    // their bias, and solves the linear system, the homotopy scales the independent sources and
+    // shunts every node to ground
+    pub(crate) fn solve_linear(
+        &self,
+        topology: &Topology,
+        analysis: Analysis,
+        previous: Option<&Solution>,
+        biases: &BTreeMap<ComponentId, Bias>,
+        homotopy: Homotopy,
+    ) -> Result<Solution, String> {
+        let branches = self.branches(analysis);
+        let states = self
+            .components
+            .keys()
+            .map(|id| {
+                let state = self.component_state(id, topology, &branches, previous, biases)?;
+                Ok((*id, state))
+            })
+            .collect::<Result<BTreeMap<ComponentId, ComponentState>, String>>()?;
+        let mut system = MnaSystem::new(topology.unknown_count(), branches.len());
+        for (id, component) in self.components.iter() {
+            let terminals = component
+                .ids()
+                .iter()
+                .map(|id| topology.unknown(id))
+                .collect::<Vec<Option<usize>>>();
+            let branch = branches.get(id).copied();
+            let value = self.stamped_value(id, analysis, homotopy);
+            component
+                .kind()
+                .stamp(
+                    &mut system,
+                    &terminals,
+                    branch,
+                    value,
+                    analysis,
+                    &states[id],
+                )
+                .map_err(|error| format!("{}: {}", component.name(), error))?;
+        }
+        if homotopy.gmin > 0.0 {
+            for unknown in 0..topology.unknown_count() {
+                system.stamp_admittance(Some(unknown), None, homotopy.gmin.into());
+            }
+        }
+
+        let (voltages, branch_currents) = system.solve()?;
+
+        let node_voltages = (0..topology.node_count())
+            .map(|node_id| {
+                topology
+                    .unknown_of_node(node_id)
+                    .map(|unknown| voltages[unknown])
+                    .unwrap_or(Complex64::new(0.0, 0.0))
+            })
+            .collect::<Vec<Complex64>>();
+        let currents = self
+            .components
+            .iter()
+            .map(|(id, component)| {
+                let voltages = component
+                    .ids()
+                    .iter()
+                    .map(|terminal_id| node_voltages[topology.node_of(terminal_id).unwrap_or(0)])
+                    .collect::<Vec<Complex64>>();
+                let control = states[id]
+                    .controlling_branch
+                    .map(|branch| branch_currents[branch]);
+                let branch_current = branches.get(id).map(|branch| branch_currents[*branch]);
+                let current = component.kind().current(
+                    &voltages,
+                    control,
+                    branch_current,
+                    self.stamped_value(id, analysis, homotopy),
+                    analysis,
+                    &states[id],
+                );
+                (*id, current)
+            })
+            .collect();
+        let small_signals = self
+            .components
+            .iter()
+            .filter_map(|(id, component)| {
+                let small_signal = component.kind().small_signal(states[id].bias)?;
+                Some((*id, small_signal))
+            })
+            .collect();
+        Ok(Solution {
+            node_voltages,
+            currents,
+            biases: biases.clone(),
+            small_signals,
+            strategy: Strategy::Newton,
+        })
+    }
+
+    // builds the nodes of the circuit and chooses the ground node
+    pub(crate) fn topology(&mut self) -> Result<Topology, String> {
+        let nodes = self.terminal_graph.create_nodes();
+        let mut terminal_nodes = HashMap::<usize, usize>::new();
+        for node in nodes.iter() {
+            for terminal_id in node.terminal_ids() {
+                terminal_nodes.insert(*terminal_id, node.id());
+            }
+        }
+        if nodes.is_empty() {
+            return Err("Circuit has no nodes".to_string());
+        }
+        let ground = match self.ground {
+            Some(terminal_id) => *terminal_nodes
+                .get(&terminal_id)
+                .ok_or(format!("Ground terminal {} does not exist", terminal_id))?,
+            // without an explicit ground the node with the most terminals is chosen
+            None => nodes
+                .iter()
+                .max_by(|a, b| {
+                    a.terminal_ids()
+                        .len()
+                        .cmp(&b.terminal_ids().len())
+                        .then(b.id().cmp(&a.id()))
+                })
+                .map(|node| node.id())
+                .unwrap_or(0),
+        };
+        let node_count = nodes.len();
+        Ok(Topology {
+            nodes,
+            terminal_nodes,
+            ground,
+            node_count,
+        })
+    }
+}
@@ -0,0 +1,190 @@
+use num::complex::Complex64;
+use std::collections::BTreeMap;
+use std::f64::consts::PI;
+
+use crate::analysis::newton::{Homotopy, NewtonOptions};
+use crate::analysis::{Analysis, Solution};
+use crate::graph::component::ComponentId;
+use crate::Circuit;
+
+// spacing of the sweep points, like the .ac card of SPICE
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum SweepKind {
+    // points in total
+    Linear,
+    // points per decade
+    Decade,
+    // points per octave
+    Octave,
+}
+
+impl SweepKind {
+    pub fn frequencies(
+        &self,
+        start_hz: f64,
+        stop_hz: f64,
+        points: usize,
+    ) -> Result<Vec<f64>, String> {
+        if points == 0 {
+            return Err("Sweep needs at least one point".to_string());
+        }
+        if !(start_hz.is_finite() && stop_hz.is_finite()) || stop_hz < start_hz {
+            return Err(format!(
+                "Invalid sweep range {} Hz to {} Hz",
+                start_hz, stop_hz
+            ));
+        }
+        let base = match self {
+            SweepKind::Linear => {
+                if points == 1 {
+                    return Ok(vec![start_hz]);
+                }
+                let step = (stop_hz - start_hz) / (points - 1) as f64;
+                return Ok((0..points)
+                    .map(|index| start_hz + step * index as f64)
+                    .collect());
+            }
+            SweepKind::Decade => 10.0_f64,
+            SweepKind::Octave => 2.0_f64,
+        };
+        if start_hz <= 0.0 {
+            return Err("Logarithmic sweeps have to start above 0 Hz".to_string());
+        }
+        // the small tolerance keeps the stop frequency despite rounding
+        let steps = ((stop_hz / start_hz).log(base) * points as f64 + 1e-9).floor() as usize;
+        Ok((0..=steps)
+            .map(|index| start_hz * base.powf(index as f64 / points as f64))
+            .collect())
+    }
+}
+
+pub fn magnitude_db(value: Complex64) -> f64 {
+    20.0 * value.norm().log10()
+}
+
+// in degrees, between -180 and 180
+pub fn phase_degrees(value: Complex64) -> f64 {
+    value.arg().to_degrees()
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct BodePoint {
+    pub frequency: f64,
+    pub magnitude_db: f64,
+    pub phase_degrees: f64,
+}
+
+impl BodePoint {
+    pub fn new(frequency: f64, value: Complex64) -> Self {
+        Self {
+            frequency,
+            magnitude_db: magnitude_db(value),
+            phase_degrees: phase_degrees(value),
+        }
+    }
+}
+
+// one solution per frequency
+pub struct AcSweep {
+    frequencies: Vec<f64>,
+    solutions: Vec<Solution>,
+}
+
+impl AcSweep {
+    pub fn frequencies(&self) -> &[f64] {
+        &self.frequencies
+    }
+
+    pub fn solutions(&self) -> &[Solution] {
+        &self.solutions
+    }
+
+    pub fn node_voltages(&self, node_id: usize) -> Vec<Complex64> {
+        self.solutions
+            .iter()
+            .filter_map(|solution| solution.node_voltage(node_id))
+            .collect()
+    }
+
+    pub fn currents(&self, component_id: ComponentId) -> Vec<Complex64> {
+        self.solutions
+            .iter()
+            .filter_map(|solution| solution.current(component_id))
+            .collect()
+    }
+
+    // magnitude and phase of a node voltage over the sweep
+    pub fn bode(&self, node_id: usize) -> Vec<BodePoint> {
+        self.frequencies
+            .iter()
+            .zip(self.node_voltages(node_id))
+            .map(|(frequency, voltage)| BodePoint::new(*frequency, voltage))
+            .collect()
+    }
+
+    // magnitude and phase of v(output) / v(input)
+    pub fn bode_transfer(&self, input_node: usize, output_node: usize) -> Vec<BodePoint> {
+        self.frequencies
+            .iter()
+            .zip(self.node_voltages(input_node))
+            .zip(self.node_voltages(output_node))
+            .map(|((frequency, input), output)| BodePoint::new(*frequency, output / input))
+            .collect()
+    }
+}
+
+impl Circuit {
+    // small signal phasor of an independent source, the phase in degrees
+    pub fn set_ac(
+        &mut self,
+        id: ComponentId,
+        magnitude: f64,
+        phase_degrees: f64,
+    ) -> Result<(), String> {
+        self.components
+            .get_mut(&id)
+            .ok_or(format!("Component {} does not exist", id))?
+            .set_ac(Complex64::from_polar(magnitude, phase_degrees.to_radians()))
+    }
+
+    // small signal sweep driven by the AC phasors of the independent sources, their DC values
+    // only set the operating point
+    pub fn ac_sweep(
+        &mut self,
+        start_hz: f64,
+        stop_hz: f64,
+        points: usize,
+        kind: SweepKind,
+    ) -> Result<AcSweep, String> {
+        let frequencies = kind.frequencies(start_hz, stop_hz, points)?;
+        let topology = self.topology()?;
+        // nonlinear components are linearized at the operating point
+        let biases = if self.is_nonlinear() {
+            self.solve_operating_point(&topology, Analysis::Dc, &NewtonOptions::default())
+                .map_err(|error| format!("Operating point: {}", error))?
+                .biases()
+                .clone()
+        } else {
+            BTreeMap::new()
+        };
+        let solutions = frequencies
+            .iter()
+            .map(|frequency| {
+                let omega = 2.0 * PI * frequency;
+                self.solve_linear(
+                    &topology,
+                    Analysis::Ac { omega },
+                    None,
+                    &biases,
+                    Homotopy::default(),
+                )
+                .map_err(|error| format!("{} Hz: {}", frequency, error))
+            })
+            .collect::<Result<Vec<Solution>, String>>()?;
+        self.nodes = topology.nodes().to_vec();
+        Ok(AcSweep {
+            frequencies,
+            solutions,
+        })
+    }
+}
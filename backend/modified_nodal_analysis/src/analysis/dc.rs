@@ -0,0 +1,124 @@
+use std::collections::BTreeMap;
+
+use crate::analysis::newton::{ConvergenceError, Homotopy, NewtonOptions, Strategy};
+use crate::analysis::{Analysis, Solution, Topology};
+use crate::device::diode::GMIN;
+use crate::Circuit;
+
+// the shunt conductance gmin stepping starts with (S)
+const START_GMIN: f64 = 1e-2;
+// source stepping gives up below this fraction of the sources
+const MIN_SOURCE_STEP: f64 = 1e-3;
+
+impl Circuit {
+    // operating point, the node voltages are written back into the nodes of the circuit
+    pub fn solve_dc(&mut self) -> Result<Solution, String> {
+        self.solve_dc_with(&NewtonOptions::default())
+            .map_err(|error| error.to_string())
+    }
+
+    pub fn solve_dc_with(&mut self, options: &NewtonOptions) -> Result<Solution, ConvergenceError> {
+        let topology = self
+            .topology()
+            .map_err(|message| ConvergenceError::new(0, message))?;
+        let solution = self.solve_operating_point(&topology, Analysis::Dc, options)?;
+        self.nodes = topology.nodes().to_vec();
+        for node in self.nodes.iter_mut() {
+            node.set_voltage(solution.node_voltages()[node.id()]);
+        }
+        Ok(solution)
+    }
+
+    // Newton-Raphson from zero, if it fails gmin stepping and then source stepping
+    pub(crate) fn solve_operating_point(
+        &self,
+        topology: &Topology,
+        analysis: Analysis,
+        options: &NewtonOptions,
+    ) -> Result<Solution, ConvergenceError> {
+        let error = match self.solve_newton(topology, analysis, None, options) {
+            Ok(solution) => return Ok(solution),
+            Err(error) => error,
+        };
+        // a linear circuit that does not solve has no better starting point
+        if !self.is_nonlinear() {
+            return Err(error);
+        }
+        if options.gmin_steps > 0 {
+            if let Ok(mut solution) = self.step_gmin(topology, analysis, options) {
+                solution.strategy = Strategy::GminStepping;
+                return Ok(solution);
+            }
+        }
+        if options.source_steps > 0 {
+            if let Ok(mut solution) = self.step_sources(topology, analysis, options) {
+                solution.strategy = Strategy::SourceStepping;
+                return Ok(solution);
+            }
+        }
+        // the failure of plain Newton says the most about the circuit
+        Err(error)
+    }
+
+    // every step starts at the bias of the previous one with a geometrically smaller conductance
+    // from each node to ground, from START_GMIN down to GMIN and finally without it
+    fn step_gmin(
+        &self,
+        topology: &Topology,
+        analysis: Analysis,
+        options: &NewtonOptions,
+    ) -> Result<Solution, ConvergenceError> {
+        let ratio = (GMIN / START_GMIN).powf(1.0 / options.gmin_steps as f64);
+        let mut biases = BTreeMap::new();
+        for step in 0..=options.gmin_steps {
+            let homotopy = Homotopy {
+                gmin: START_GMIN * ratio.powi(step as i32),
+                ..Default::default()
+            };
+            biases = self
+                .iterate(topology, analysis, None, biases, homotopy, options)?
+                .biases()
+                .clone();
+        }
+        self.iterate(
+            topology,
+            analysis,
+            None,
+            biases,
+            Homotopy::default(),
+            options,
+        )
+    }
+
+    // ramps the independent sources from zero to their values, a step that does not converge
+    // is halved and retried from the last converged point
+    fn step_sources(
+        &self,
+        topology: &Topology,
+        analysis: Analysis,
+        options: &NewtonOptions,
+    ) -> Result<Solution, ConvergenceError> {
+        let mut biases = BTreeMap::new();
+        let (mut factor, mut step) = (0.0, 1.0 / options.source_steps as f64);
+        loop {
+            let next = (factor + step).min(1.0);
+            let homotopy = Homotopy {
+                source_factor: next,
+                ..Default::default()
+            };
+            match self.iterate(topology, analysis, None, biases.clone(), homotopy, options) {
+                Ok(solution) if next == 1.0 => return Ok(solution),
+                Ok(solution) => {
+                    factor = next;
+                    biases = solution.biases().clone();
+                }
+                Err(error) => {
+                    step /= 2.0;
+                    if step < MIN_SOURCE_STEP {
+                        return Err(error);
+                    }
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,573 @@
+use std::collections::BTreeMap;
+use std::fmt;
+
+use crate::analysis::{Analysis, Bias, Solution, Topology};
+use crate::graph::component::ComponentId;
+use crate::Circuit;
+
+// convergence criteria of the Newton-Raphson iteration, the defaults are the ones of SPICE
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct NewtonOptions {
+    // absolute current tolerance (A)
+    pub abstol: f64,
+    // relative tolerance of voltages and currents
+    pub reltol: f64,
+    // absolute voltage tolerance (V)
+    pub vntol: f64,
+    pub max_iterations: usize,
+    // steps of the convergence aids of the operating point, 0 disables them
+    pub gmin_steps: usize,
+    pub source_steps: usize,
+}
+
+impl Default for NewtonOptions {
+    fn default() -> Self {
+        Self {
+            abstol: 1e-12,
+            reltol: 1e-3,
+            vntol: 1e-6,
+            max_iterations: 100,
+            gmin_steps: 10,
+            source_steps: 10,
+        }
+    }
+}
+
+impl NewtonOptions {
+    // the current the linear model predicts matches the one of the device at the new bias
+    pub fn current_converged(&self, predicted: f64, exact: f64) -> bool {
+        (predicted - exact).abs() <= self.reltol * predicted.abs().max(exact.abs()) + self.abstol
+    }
+}
+
+// how the operating point was found
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Strategy {
+    // Newton-Raphson from zero, also every linear solve
+    #[default]
+    Newton,
+    // a conductance from every node to ground stepped down to zero
+    GminStepping,
+    // every independent source ramped up from zero
+    SourceStepping,
+}
+
+// continuation parameters of the convergence aids
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub(crate) struct Homotopy {
+    // conductance from every node to ground (S)
+    pub gmin: f64,
+    // scales the values of the independent sources
+    pub source_factor: f64,
+}
+
+impl Default for Homotopy {
+    fn default() -> Self {
+        Self {
+            gmin: 0.0,
+            source_factor: 1.0,
+        }
+    }
+}
+
+// the bias of a nonlinear component for the next iteration
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct BiasUpdate {
+    pub bias: Bias,
+    // the step was cut short to keep the exponentials in range
+    pub limited: bool,
+    // the linear model predicts the current of the device at the new bias
+    pub converged: bool,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct ConvergenceError {
+    pub message: String,
+    pub iterations: usize,
+    // the node whose voltage changed the most relative to its tolerance in the last iteration
+    pub worst_node: Option<usize>,
+    pub voltage_change: f64,
+}
+
+impl ConvergenceError {
+    pub(crate) fn new(iterations: usize, message: String) -> Self {
+        Self {
+            message,
+            iterations,
+            worst_node: None,
+            voltage_change: 0.0,
+        }
+    }
+}
+
+impl fmt::Display for ConvergenceError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.worst_node {
+            Some(node) => write!(
+                f,
+                "{}, node {} still changed by {} V",
+                self.message, node, self.voltage_change
+            ),
+            None => write!(f, "{}", self.message),
+        }
+    }
+}
+
+impl Circuit {
+    pub fn is_nonlinear(&self) -> bool {
+        self.components
+            .values()
+            .any(|component| component.kind().is_nonlinear())
+    }
+
+    // a time step starts at the bias of the previous time point
+    pub(crate) fn solve_newton(
+        &self,
+        topology: &Topology,
+        analysis: Analysis,
+        previous: Option<&Solution>,
+        options: &NewtonOptions,
+    ) -> Result<Solution, ConvergenceError> {
+        let biases = previous
+            .map(|previous| previous.biases().clone())
+            .unwrap_or_default();
+        self.iterate(
+            topology,
+            analysis,
+            previous,
+            biases,
+            Homotopy::default(),
+            options,
+        )
+    }
+
+    // repeats the linear solve with the nonlinear components linearized at the result of the
+    // previous one until neither the node voltages nor the device currents change
+    pub(crate) fn iterate(
+        &self,
+        topology: &Topology,
+        analysis: Analysis,
+        previous: Option<&Solution>,
+        mut biases: BTreeMap<ComponentId, Bias>,
+        homotopy: Homotopy,
+        options: &NewtonOptions,
+    ) -> Result<Solution, ConvergenceError> {
+        if !self.is_nonlinear() {
+            return self
+                .solve_linear(topology, analysis, previous, &biases, homotopy)
+                .map_err(|message| ConvergenceError::new(1, message));
+        }
+        let mut last: Option<Solution> = None;
+        let mut worst = None;
+        for iteration in 1..=options.max_iterations {
+            let solution = self
+                .solve_linear(topology, analysis, previous, &biases, homotopy)
+                .map_err(|message| ConvergenceError::new(iteration, message))?;
+            let mut converged = false;
+            if let Some(last) = &last {
+                // |Δv| <= reltol * max(|v|, |v_last|) + vntol for every node
+                let (node, change, ratio) = solution
+                    .node_voltages()
+                    .iter()
+                    .zip(last.node_voltages())
+                    .enumerate()
+                    .map(|(node, (voltage, last_voltage))| {
+                        let change = (voltage - last_voltage).norm();
+                        let tolerance = options.reltol * voltage.norm().max(last_voltage.norm())
+                            + options.vntol;
+                        (node, change, change / tolerance)
+                    })
+                    .max_by(|a, b| a.2.total_cmp(&b.2))
+                    .unwrap_or_default();
+                worst = Some((node, change));
+                converged = ratio <= 1.0;
+            }
+            let mut next = BTreeMap::new();
+            for (id, component) in self.components.iter() {
+                let voltages = component
+                    .ids()
+                    .iter()
+                    .map(|terminal_id| {
+                        let node = topology.node_of(terminal_id).unwrap_or(0);
+                        solution.node_voltage(node).unwrap_or_default().re
+                    })
+                    .collect::<Vec<f64>>();
+                let bias = biases.get(id).copied().unwrap_or_default();
+                if let Some(update) = component.kind().next_bias(&voltages, bias, options) {
+                    converged &= update.converged && !update.limited;
+                    next.insert(*id, update.bias);
+                }
+            }
+            if converged {
+                // the operating point is where the devices are linearized for the small signal
+                // analysis, not where the last solve linearized them
+                let mut solution = solution;
+                solution.small_signals = next
+                    .iter()
+                    .filter_map(|(id, bias)| {
+                        let small_signal = self.components[id].kind().small_signal(*bias)?;
+                        Some((*id, small_signal))
+                    })
+                    .collect();
+                solution.biases = next;
+                return Ok(solution);
+            }
+            biases = next;
+            last = Some(solution);
+        }
+        let mut error = ConvergenceError::new(
+            options.max_iterations,
+            format!("No convergence after {} iterations", options.max_iterations),
+        );
+        if let Some((node, change)) = worst {
+            error.worst_node = Some(node);
+            error.voltage_change = change;
+        }
+        Err(error)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::analysis::ac::SweepKind;
+    use crate::device::bjt::BjtModel;
+    use crate::device::diode::DiodeModel;
+    use crate::device::mosfet::MosfetModel;
+    use crate::device::Polarity;
+    use crate::graph::component::{ComponentId, ComponentKind};
+    use num::complex::Complex64;
+
+    // V1 (1, 2) drives D1 (5 anode, 6 cathode) to ground through R1 (3, 4)
+    fn diode_circuit(voltage: f64, model: DiodeModel) -> Circuit {
+        let mut circuit = Circuit::new();
+        circuit.add_component(ComponentKind::VoltageSource, Complex64::new(voltage, 0.0));
+        circuit.add_component(ComponentKind::Resistor, Complex64::new(1e3, 0.0));
+        circuit.add_component(ComponentKind::Diode(model), Complex64::new(0.0, 0.0));
+        for (a, b) in [(1, 3), (4, 5), (6, 2)] {
+            circuit.connect(&a, &b).unwrap();
+        }
+        circuit.set_ground(2);
+        circuit
+    }
+
+    #[test]
+    fn test_forward_bias() {
+        let model = DiodeModel::default();
+        let mut circuit = diode_circuit(5.0, model);
+        let solution = circuit.solve_dc().unwrap();
+        let voltage = circuit.voltage(&5).unwrap().re;
+        let current = solution.current(ComponentId(2)).unwrap().re;
+        assert!(voltage > 0.6 && voltage < 0.8, "{}", voltage);
+        // the resistor and the Shockley equation agree on the current
+        assert!((current - (5.0 - voltage) / 1e3).abs() < 1e-9);
+        let (exact, _) = model.junction(voltage);
+        assert!((current - exact).abs() < 1e-3 * exact);
+    }
+
+    #[test]
+    fn test_series_resistance() {
+        let model = DiodeModel {
+            series_resistance: 100.0,
+            ..Default::default()
+        };
+        let mut circuit = diode_circuit(5.0, model);
+        let solution = circuit.solve_dc().unwrap();
+        let voltage = circuit.voltage(&5).unwrap().re;
+        let current = solution.current(ComponentId(2)).unwrap().re;
+        assert!((current - (5.0 - voltage) / 1e3).abs() < 1e-9);
+        // the junction only sees the voltage behind the series resistance
+        let (exact, _) = model.junction(voltage - 100.0 * current);
+        assert!((current - exact).abs() < 1e-3 * exact);
+    }
+
+    #[test]
+    fn test_reverse_bias() {
+        let mut circuit = diode_circuit(-5.0, DiodeModel::default());
+        let solution = circuit.solve_dc().unwrap();
+        // Is and GMIN leak
+        let current = solution.current(ComponentId(2)).unwrap().re;
+        assert!(current < 0.0 && current > -1e-11, "{}", current);
+        assert!((circuit.voltage(&5).unwrap().re + 5.0).abs() < 1e-6);
+    }
+
+    #[test]
+    fn test_convergence_error() {
+        let mut circuit = diode_circuit(5.0, DiodeModel::default());
+        // without the convergence aids that would rescue it
+        let options = NewtonOptions {
+            max_iterations: 3,
+            gmin_steps: 0,
+            source_steps: 0,
+            ..Default::default()
+        };
+        let error = circuit.solve_dc_with(&options).err().unwrap();
+        assert_eq!(error.iterations, 3);
+        // the anode is the only node that moves
+        let anode = circuit.topology().unwrap().node_of(&5).unwrap();
+        assert_eq!(error.worst_node, Some(anode));
+        assert!(error.voltage_change > 0.0);
+        assert!(error
+            .to_string()
+            .starts_with("No convergence after 3 iterations"));
+
+        let invalid = DiodeModel {
+            saturation_current: 0.0,
+            ..Default::default()
+        };
+        let error = diode_circuit(5.0, invalid).solve_dc_with(&options);
+        assert_eq!(error.err().unwrap().worst_node, None);
+    }
+
+    #[test]
+    fn test_convergence_aids() {
+        let mut circuit = diode_circuit(5.0, DiodeModel::default());
+        let newton = circuit.solve_dc().unwrap();
+        assert_eq!(newton.strategy(), Strategy::Newton);
+        let anode = circuit.node_of(&5).unwrap();
+        let voltage = newton.node_voltage(anode).unwrap().re;
+        // too few iterations for plain Newton from zero, but enough for the small steps of gmin
+        // stepping, and with fewer still for the ones of source stepping
+        for (max_iterations, strategy) in
+            [(6, Strategy::GminStepping), (4, Strategy::SourceStepping)]
+        {
+            let options = NewtonOptions {
+                max_iterations,
+                ..Default::default()
+            };
+            let solution = circuit.solve_dc_with(&options).unwrap();
+            assert_eq!(solution.strategy(), strategy);
+            let stepped = solution.node_voltage(anode).unwrap().re;
+            assert!((stepped - voltage).abs() < 1e-4, "{} {}", stepped, voltage);
+        }
+        // only the aids that are enabled are tried
+        let options = NewtonOptions {
+            max_iterations: 6,
+            gmin_steps: 0,
+            ..Default::default()
+        };
+        let solution = circuit.solve_dc_with(&options).unwrap();
+        assert_eq!(solution.strategy(), Strategy::SourceStepping);
+    }
+
+    #[test]
+    fn test_blocking_diode() {
+        // peak detector: V1 charges C1 (5, 6) through D1 with 10 Ω series resistance, R1 (7, 8)
+        // is the load
+        let mut circuit = Circuit::new();
+        circuit.add_component(ComponentKind::VoltageSource, Complex64::new(5.0, 0.0)); // 1, 2
+        let model = DiodeModel {
+            series_resistance: 10.0,
+            ..Default::default()
+        };
+        let d1 = circuit.add_component(ComponentKind::Diode(model), Complex64::new(0.0, 0.0)); // 3, 4
+        let c1 = circuit.add_component(ComponentKind::Capacitor, Complex64::new(1e-6, 0.0)); // 5, 6
+        circuit.add_component(ComponentKind::Resistor, Complex64::new(1e3, 0.0)); // 7, 8
+        for (a, b) in [(1, 3), (4, 5), (5, 7), (6, 2), (8, 2)] {
+            circuit.connect(&a, &b).unwrap();
+        }
+        circuit.set_ground(2);
+
+        // charged above the source the diode blocks and the load discharges the capacitor
+        circuit.set_initial_condition(c1, Some(10.0)).unwrap();
+        let tau = 1e-3;
+        let waveforms = circuit.transient(0.5 * tau, tau / 100.0).unwrap();
+        let output = circuit.node_of(&5).unwrap();
+        for (time, voltage) in waveforms
+            .times()
+            .iter()
+            .zip(waveforms.node_voltages(output))
+        {
+            let expected = 10.0 * (-time / tau).exp();
+            assert!(
+                (voltage - expected).abs() < 1e-3,
+                "{} at {} s",
+                voltage,
+                time
+            );
+        }
+        assert!(waveforms
+            .currents(d1)
+            .iter()
+            .all(|current| current.abs() < 1e-9));
+
+        // without the initial charge the capacitor follows the source one diode drop below
+        circuit.set_initial_condition(c1, Some(0.0)).unwrap();
+        let waveforms = circuit.transient(tau, tau / 100.0).unwrap();
+        let operating_point = circuit.solve_dc().unwrap();
+        let last = *waveforms.node_voltages(output).last().unwrap();
+        // within the Newton tolerances
+        let expected = operating_point.node_voltage(output).unwrap().re;
+        assert!((last - expected).abs() < 1e-4);
+        assert!(last > 4.2 && last < 4.4, "{}", last);
+    }
+
+    #[test]
+    fn test_small_signal() {
+        // the AC analysis sees the diode as its conductance at the operating point
+        let model = DiodeModel::default();
+        let mut circuit = diode_circuit(1.0, model);
+        let operating_point = circuit.solve_dc().unwrap();
+        let bias = operating_point.biases()[&ComponentId(2)][0];
+        let conductance = model.linearize(bias).conductance;
+        circuit.set_ac(ComponentId(0), 1.0, 0.0).unwrap();
+        let sweep = circuit.ac_sweep(1e3, 1e3, 1, SweepKind::Linear).unwrap();
+        let anode = circuit.node_of(&5).unwrap();
+        let expected = 1.0 / (1.0 + 1e3 * conductance);
+        assert!((sweep.node_voltages(anode)[0] - expected).norm() < 1e-12);
+    }
+
+    // V1 (1, 2) biases the base of Q1 (7 collector, 8 base, 9 emitter) through RB (3, 4) and
+    // feeds the collector through RC (5, 6), the input V2 (10, 11) without a DC value sits
+    // between the supply and RB
+    fn common_emitter(model: BjtModel) -> Circuit {
+        let mut circuit = Circuit::new();
+        let supply = 5.0 * model.polarity.sign();
+        circuit.add_component(ComponentKind::VoltageSource, Complex64::new(supply, 0.0));
+        circuit.add_component(ComponentKind::Resistor, Complex64::new(430e3, 0.0));
+        circuit.add_component(ComponentKind::Resistor, Complex64::new(1e3, 0.0));
+        circuit.add_component(ComponentKind::Bjt(model), Complex64::new(0.0, 0.0));
+        circuit.add_component(ComponentKind::VoltageSource, Complex64::new(0.0, 0.0));
+        for (a, b) in [(1, 11), (10, 3), (1, 5), (4, 8), (6, 7), (9, 2)] {
+            circuit.connect(&a, &b).unwrap();
+        }
+        circuit.set_ground(2);
+        circuit
+    }
+
+    #[test]
+    fn test_common_emitter() {
+        let model = BjtModel::default();
+        let mut circuit = common_emitter(model);
+        let solution = circuit.solve_dc().unwrap();
+        let (collector, base) = (
+            circuit.voltage(&7).unwrap().re,
+            circuit.voltage(&8).unwrap().re,
+        );
+        assert!(base > 0.6 && base < 0.8, "{}", base);
+        // forward active: the collector current is βF times the base current
+        let collector_current = solution.current(ComponentId(3)).unwrap().re;
+        let base_current = solution.current(ComponentId(1)).unwrap().re;
+        assert!((collector_current - (5.0 - collector) / 1e3).abs() < 1e-9);
+        assert!((collector_current / base_current - 100.0).abs() < 0.1);
+
+        let small_signal = solution.small_signal(ComponentId(3)).unwrap();
+        let gm = collector_current / model.thermal_voltage;
+        assert!((small_signal.gm - gm).abs() < 1e-3 * gm);
+        assert!((small_signal.r_pi - 100.0 / gm).abs() < 1e-3 * 100.0 / gm);
+        assert!(small_signal.gds < 1e-9);
+        assert_eq!(solution.small_signal(ComponentId(2)), None);
+
+        // the AC analysis sees the hybrid-π model at the operating point, driven by the input
+        // alone while the supply is an AC ground
+        circuit.set_ac(ComponentId(4), 1.0, 0.0).unwrap();
+        let sweep = circuit.ac_sweep(1e3, 1e3, 1, SweepKind::Linear).unwrap();
+        let node = |terminal| circuit.node_of(&terminal).unwrap();
+        assert_eq!(sweep.node_voltages(node(1))[0], Complex64::new(0.0, 0.0));
+        let base = small_signal.r_pi / (430e3 + small_signal.r_pi);
+        let collector = -1e3 * small_signal.gm * base / (1.0 + 1e3 * small_signal.gds);
+        assert!((sweep.node_voltages(node(8))[0] - base).norm() < 1e-6 * base);
+        assert!((sweep.node_voltages(node(7))[0] - collector).norm() < 1e-6 * collector.abs());
+    }
+
+    #[test]
+    fn test_pnp() {
+        // the PNP circuit with a negative supply mirrors the NPN one
+        let mut npn = common_emitter(BjtModel::default());
+        let mut pnp = common_emitter(BjtModel {
+            polarity: Polarity::P,
+            ..Default::default()
+        });
+        npn.solve_dc().unwrap();
+        let solution = pnp.solve_dc().unwrap();
+        for terminal in [7, 8] {
+            let (voltage, mirrored) = (
+                npn.voltage(&terminal).unwrap().re,
+                pnp.voltage(&terminal).unwrap().re,
+            );
+            assert!(
+                (voltage + mirrored).abs() < 1e-6,
+                "{} {}",
+                voltage,
+                mirrored
+            );
+        }
+        assert!(solution.small_signal(ComponentId(3)).unwrap().gm > 0.0);
+    }
+
+    // VDD (1, 2) feeds the drain of M1 (7 drain, 8 gate, 9 source, 10 bulk) through RD (5, 6),
+    // VG (3, 4) drives the gate
+    fn common_source(model: MosfetModel) -> Circuit {
+        let mut circuit = Circuit::new();
+        let sign = model.polarity.sign();
+        circuit.add_component(
+            ComponentKind::VoltageSource,
+            Complex64::new(5.0 * sign, 0.0),
+        );
+        circuit.add_component(
+            ComponentKind::VoltageSource,
+            Complex64::new(2.0 * sign, 0.0),
+        );
+        circuit.add_component(ComponentKind::Resistor, Complex64::new(10e3, 0.0));
+        circuit.add_component(ComponentKind::Mosfet(model), Complex64::new(0.0, 0.0));
+        for (a, b) in [(1, 5), (6, 7), (3, 8), (9, 2), (10, 2), (4, 2)] {
+            circuit.connect(&a, &b).unwrap();
+        }
+        circuit.set_ground(2);
+        circuit
+    }
+
+    #[test]
+    fn test_common_source() {
+        // β = KP * W / L = 2e-4 A/V², saturated at 1 V overdrive
+        let model = MosfetModel {
+            threshold_voltage: 1.0,
+            width: 10e-4,
+            ..Default::default()
+        };
+        let mut circuit = common_source(model);
+        let solution = circuit.solve_dc().unwrap();
+        let drain_current = solution.current(ComponentId(3)).unwrap().re;
+        assert!((drain_current - 1e-4).abs() < 1e-9, "{}", drain_current);
+        assert!((circuit.voltage(&7).unwrap().re - 4.0).abs() < 1e-5);
+        let small_signal = solution.small_signal(ComponentId(3)).unwrap();
+        assert!((small_signal.gm - 2e-4).abs() < 1e-9);
+        assert!(small_signal.gds < 1e-9);
+        assert_eq!(small_signal.r_pi, f64::INFINITY);
+
+        // only VG drives the AC analysis, VDD is an AC ground: vd = -RD * gm / (1 + RD * gds)
+        circuit.set_ac(ComponentId(1), 1.0, 0.0).unwrap();
+        let sweep = circuit.ac_sweep(1e3, 1e3, 1, SweepKind::Linear).unwrap();
+        let drain = circuit.node_of(&7).unwrap();
+        let expected = -1e4 * small_signal.gm / (1.0 + 1e4 * small_signal.gds);
+        assert!((sweep.node_voltages(drain)[0] - expected).norm() < 1e-6);
+
+        // the PMOS with negated voltages mirrors it
+        let mut pmos = common_source(MosfetModel {
+            polarity: Polarity::P,
+            threshold_voltage: -1.0,
+            ..model
+        });
+        let solution = pmos.solve_dc().unwrap();
+        assert!((solution.current(ComponentId(3)).unwrap().re + drain_current).abs() < 1e-9);
+        assert!((pmos.voltage(&7).unwrap().re + 4.0).abs() < 1e-5);
+    }
+
+    #[test]
+    fn test_triode() {
+        // ten times as wide the drain drops below the overdrive into the linear region
+        let model = MosfetModel {
+            threshold_voltage: 1.0,
+            width: 100e-4,
+            ..Default::default()
+        };
+        let mut circuit = common_source(model);
+        let solution = circuit.solve_dc().unwrap();
+        let drain = circuit.voltage(&7).unwrap().re;
+        assert!(drain > 0.0 && drain < 1.0, "{}", drain);
+        // β (vov - vds / 2) vds = (5 - vds) / RD
+        let current = solution.current(ComponentId(3)).unwrap().re;
+        assert!((current - 2e-3 * (1.0 - drain / 2.0) * drain).abs() < 1e-3 * current);
+        assert!((current - (5.0 - drain) / 1e4).abs() < 1e-9);
+    }
+}
@@ -0,0 +1,738 @@
+use std::collections::{BTreeMap, HashMap};
+
+use symbolic_manipulation::polynomial::{self, Monomial, Polynomial};
+use symbolic_manipulation::rational::Rational;
+use symbolic_manipulation::{Expression, Operator, OperatorExpression, Symbol};
+
+use crate::analysis::Topology;
+use crate::graph::component::{ComponentId, ComponentKind};
+use crate::Circuit;
+
+// Cramer's rule expands determinants over all column subsets, which grows as 2^n
+const MAX_UNKNOWNS: usize = 16;
+
+fn exponent_error() -> String {
+    format!(
+        "The symbolic solution has exponents beyond {}",
+        polynomial::MAX_EXPONENT
+    )
+}
+
+// the variable of the Laplace domain
+pub fn laplace_variable() -> Symbol {
+    Symbol::new("s".to_string())
+}
+
+// selects the model each component stamps, the values are the symbols of the components
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Domain {
+    // capacitors are open and inductors short circuits
+    Dc,
+    // capacitors are admittances s*C and inductors impedances s*L
+    Laplace,
+}
+
+// the MNA system [G B; C D] [v; j] = [i; e] as one matrix of polynomials in the component symbols
+pub struct SymbolicSystem {
+    node_count: usize,
+    a: Vec<Vec<Polynomial>>,
+    z: Vec<Polynomial>,
+}
+
+impl SymbolicSystem {
+    pub fn new(node_count: usize, branch_count: usize) -> Self {
+        let size = node_count + branch_count;
+        Self {
+            node_count,
+            a: vec![vec![Polynomial::zero(); size]; size],
+            z: vec![Polynomial::zero(); size],
+        }
+    }
+
+    fn add(entry: &mut Polynomial, value: &Polynomial) {
+        *entry = &*entry + value;
+    }
+
+    // admittance between two nodes, None is the ground node
+    pub fn stamp_admittance(
+        &mut self,
+        node_1: Option<usize>,
+        node_2: Option<usize>,
+        admittance: &Polynomial,
+    ) {
+        if let Some(n1) = node_1 {
+            Self::add(&mut self.a[n1][n1], admittance);
+        }
+        if let Some(n2) = node_2 {
+            Self::add(&mut self.a[n2][n2], admittance);
+        }
+        if let (Some(n1), Some(n2)) = (node_1, node_2) {
+            Self::add(&mut self.a[n1][n2], &-admittance);
+            Self::add(&mut self.a[n2][n1], &-admittance);
+        }
+    }
+
+    // current flows from the positive node through the source to the negative node (SPICE convention)
+    pub fn stamp_current_source(
+        &mut self,
+        positive: Option<usize>,
+        negative: Option<usize>,
+        current: &Polynomial,
+    ) {
+        if let Some(p) = positive {
+            Self::add(&mut self.z[p], &-current);
+        }
+        if let Some(n) = negative {
+            Self::add(&mut self.z[n], current);
+        }
+    }
+
+    // v(positive) - v(negative) = voltage, the branch current flows into the positive terminal
+    pub fn stamp_voltage_source(
+        &mut self,
+        branch: usize,
+        positive: Option<usize>,
+        negative: Option<usize>,
+        voltage: &Polynomial,
+    ) {
+        let row = self.node_count + branch;
+        let one = Polynomial::one();
+        if let Some(p) = positive {
+            Self::add(&mut self.a[p][row], &one);
+            Self::add(&mut self.a[row][p], &one);
+        }
+        if let Some(n) = negative {
+            Self::add(&mut self.a[n][row], &-&one);
+            Self::add(&mut self.a[row][n], &-&one);
+        }
+        Self::add(&mut self.z[row], voltage);
+    }
+
+    // adds -z to the branch equation: v(positive) - v(negative) - z * j = e
+    pub fn stamp_branch_impedance(&mut self, branch: usize, impedance: &Polynomial) {
+        let row = self.node_count + branch;
+        Self::add(&mut self.a[row][row], &-impedance);
+    }
+
+    // current gain * v(control+ - control-) from the positive through the source to the negative node
+    pub fn stamp_transadmittance(
+        &mut self,
+        positive: Option<usize>,
+        negative: Option<usize>,
+        control_positive: Option<usize>,
+        control_negative: Option<usize>,
+        gain: &Polynomial,
+    ) {
+        for (node, gain) in [(positive, gain.clone()), (negative, -gain)] {
+            let Some(row) = node else { continue };
+            if let Some(column) = control_positive {
+                Self::add(&mut self.a[row][column], &gain);
+            }
+            if let Some(column) = control_negative {
+                Self::add(&mut self.a[row][column], &-&gain);
+            }
+        }
+    }
+
+    // adds -gain * v(control+ - control-) to the branch equation of a voltage source
+    pub fn stamp_voltage_gain(
+        &mut self,
+        branch: usize,
+        control_positive: Option<usize>,
+        control_negative: Option<usize>,
+        gain: &Polynomial,
+    ) {
+        let row = self.node_count + branch;
+        if let Some(column) = control_positive {
+            Self::add(&mut self.a[row][column], &-gain);
+        }
+        if let Some(column) = control_negative {
+            Self::add(&mut self.a[row][column], gain);
+        }
+    }
+
+    // current gain * j(control) from the positive through the source to the negative node
+    pub fn stamp_current_gain(
+        &mut self,
+        positive: Option<usize>,
+        negative: Option<usize>,
+        control: usize,
+        gain: &Polynomial,
+    ) {
+        let column = self.node_count + control;
+        if let Some(p) = positive {
+            Self::add(&mut self.a[p][column], gain);
+        }
+        if let Some(n) = negative {
+            Self::add(&mut self.a[n][column], &-gain);
+        }
+    }
+
+    // adds -z * j(control) to the branch equation of a voltage source
+    pub fn stamp_transimpedance(&mut self, branch: usize, control: usize, impedance: &Polynomial) {
+        let row = self.node_count + branch;
+        Self::add(&mut self.a[row][self.node_count + control], &-impedance);
+    }
+
+    // op-amp whose output current j into the output node holds
+    // inverse_gain * v(output) = v(input+) - v(input-), the ideal nullor has no inverse gain
+    pub fn stamp_op_amp(
+        &mut self,
+        branch: usize,
+        output: Option<usize>,
+        input_positive: Option<usize>,
+        input_negative: Option<usize>,
+        inverse_gain: &Polynomial,
+    ) {
+        let row = self.node_count + branch;
+        let one = Polynomial::one();
+        if let Some(o) = output {
+            Self::add(&mut self.a[o][row], &one);
+            Self::add(&mut self.a[row][o], inverse_gain);
+        }
+        if let Some(p) = input_positive {
+            Self::add(&mut self.a[row][p], &-&one);
+        }
+        if let Some(n) = input_negative {
+            Self::add(&mut self.a[row][n], &one);
+        }
+    }
+
+    // Cramer's rule: x_k = det(A_k) / det(A), with A_k as A whose column k is replaced by z
+    // returns the numerators of all unknowns and the common denominator det(A)
+    pub fn solve(&self) -> Result<(Vec<Polynomial>, Polynomial), String> {
+        let size = self.z.len();
+        if size > MAX_UNKNOWNS {
+            return Err(format!(
+                "Symbolic analysis supports up to {} unknowns, the circuit has {}",
+                MAX_UNKNOWNS, size
+            ));
+        }
+        // every row is divided by the monomial all of its entries share, which clears 1/R terms
+        let mut a = self.a.clone();
+        let mut z = self.z.clone();
+        for (row, right) in a.iter_mut().zip(z.iter_mut()) {
+            let gcd = row
+                .iter()
+                .chain(std::iter::once(&*right))
+                .filter_map(|entry| entry.monomial_gcd())
+                .reduce(|gcd, monomial| gcd.gcd(&monomial));
+            if let Some(gcd) = gcd {
+                let inverse = Monomial::one().divide(&gcd).ok_or_else(exponent_error)?;
+                for entry in row.iter_mut().chain(std::iter::once(right)) {
+                    *entry = entry
+                        .multiply_monomial(&inverse)
+                        .ok_or_else(exponent_error)?;
+                }
+            }
+        }
+        let denominator = determinant(&a).ok_or_else(exponent_error)?;
+        if denominator.is_zero() {
+            return Err("The circuit has no unique solution".to_string());
+        }
+        let numerators = (0..size)
+            .map(|column| {
+                let mut replaced = a.clone();
+                for (row, right) in replaced.iter_mut().zip(z.iter()) {
+                    row[column] = right.clone();
+                }
+                determinant(&replaced).ok_or_else(exponent_error)
+            })
+            .collect::<Result<Vec<Polynomial>, String>>()?;
+        Ok((numerators, denominator))
+    }
+}
+
+// Laplace expansion along the rows, the minors are shared through the set of remaining columns
+// None if an exponent exceeds MAX_EXPONENT
+fn determinant(matrix: &[Vec<Polynomial>]) -> Option<Polynomial> {
+    fn minor(
+        matrix: &[Vec<Polynomial>],
+        columns: u32,
+        minors: &mut HashMap<u32, Polynomial>,
+    ) -> Option<Polynomial> {
+        let row = matrix.len() - columns.count_ones() as usize;
+        if row == matrix.len() {
+            return Some(Polynomial::one());
+        }
+        if let Some(minor) = minors.get(&columns) {
+            return Some(minor.clone());
+        }
+        let mut sum = Polynomial::zero();
+        let mut sign = false;
+        for column in 0..matrix.len() {
+            if columns & (1 << column) == 0 {
+                continue;
+            }
+            let entry = &matrix[row][column];
+            if !entry.is_zero() {
+                let product =
+                    entry.checked_mul(&minor(matrix, columns & !(1 << column), minors)?)?;
+                sum = if sign {
+                    &sum - &product
+                } else {
+                    &sum + &product
+                };
+            }
+            sign = !sign;
+        }
+        minors.insert(columns, sum.clone());
+        Some(sum)
+    }
+    minor(matrix, (1u32 << matrix.len()) - 1, &mut HashMap::new())
+}
+
+// numerator / denominator without common factors, with a positive leading coefficient and
+// integer coefficients in the denominator
+pub fn quotient(numerator: &Polynomial, denominator: &Polynomial) -> Result<Expression, String> {
+    let (numerator, denominator) = reduce(numerator, denominator)?;
+    Ok(reduced_quotient(&numerator, &denominator))
+}
+
+// numerator / denominator of a reduced pair, constant denominators divide the coefficients
+pub(crate) fn reduced_quotient(numerator: &Polynomial, denominator: &Polynomial) -> Expression {
+    match denominator.as_constant() {
+        Some(constant) => numerator.scale(&constant.recip()).to_expression(),
+        None => Expression::OperatorExpression(
+            OperatorExpression::new(
+                Operator::Division,
+                vec![
+                    Ok(numerator.to_expression()),
+                    Ok(denominator.to_expression()),
+                ],
+            )
+            .expect("division of two operands"),
+        ),
+    }
+}
+
+pub(crate) fn reduce(
+    numerator: &Polynomial,
+    denominator: &Polynomial,
+) -> Result<(Polynomial, Polynomial), String> {
+    if numerator.is_zero() {
+        return Ok((Polynomial::zero(), Polynomial::one()));
+    }
+    // cancels common factors like R1 + R2
+    let gcd = numerator.gcd(denominator).ok_or_else(exponent_error)?;
+    // the gcd divides both, so only exponents out of range fail
+    let divide = |polynomial: &Polynomial| polynomial.divide_exact(&gcd).ok_or_else(exponent_error);
+    let (numerator, denominator) = (divide(numerator)?, divide(denominator)?);
+    let mut factor = denominator.content();
+    if denominator
+        .leading_coefficient()
+        .is_some_and(|coefficient| *coefficient < Rational::from_integer(0.into()))
+    {
+        factor = -factor;
+    }
+    Ok((
+        numerator.scale(&factor.recip()),
+        denominator.scale(&factor.recip()),
+    ))
+}
+
+// closed form node voltages and component currents
+pub struct SymbolicSolution {
+    // indexed by node id, the ground node is 0 V
+    node_voltages: Vec<Expression>,
+    currents: BTreeMap<ComponentId, Expression>,
+}
+
+impl SymbolicSolution {
+    pub fn node_voltage(&self, node_id: usize) -> Option<&Expression> {
+        self.node_voltages.get(node_id)
+    }
+
+    pub fn node_voltages(&self) -> &[Expression] {
+        &self.node_voltages
+    }
+
+    pub fn current(&self, component_id: ComponentId) -> Option<&Expression> {
+        self.currents.get(&component_id)
+    }
+
+    pub fn currents(&self) -> &BTreeMap<ComponentId, Expression> {
+        &self.currents
+    }
+}
+
+pub(crate) struct SymbolicNumerators {
+    pub(crate) topology: Topology,
+    branches: HashMap<ComponentId, usize>,
+    // indexed by node id, the ground node is 0
+    pub(crate) node_numerators: Vec<Polynomial>,
+    branch_numerators: Vec<Polynomial>,
+    pub(crate) denominator: Polynomial,
+}
+
+impl Circuit {
+    // the symbol of a component is its name, e.g. R1 or C2
+    pub fn component_symbol(&self, id: ComponentId) -> Option<Symbol> {
+        self.components
+            .get(&id)
+            .map(|component| Symbol::new(component.name().to_string()))
+    }
+
+    // numerators of all unknowns over the common denominator, before any cancellation
+    pub(crate) fn symbolic_numerators(
+        &mut self,
+        domain: Domain,
+    ) -> Result<SymbolicNumerators, String> {
+        let topology = self.topology()?;
+        let mut branches = HashMap::<ComponentId, usize>::new();
+        for (id, component) in self.components.iter() {
+            if component.kind().has_branch() {
+                branches.insert(*id, branches.len());
+            }
+        }
+        let mut system = SymbolicSystem::new(topology.unknown_count(), branches.len());
+        for (id, component) in self.components.iter() {
+            let terminals = component
+                .ids()
+                .iter()
+                .map(|id| topology.unknown(id))
+                .collect::<Vec<Option<usize>>>();
+            let symbol = Symbol::new(component.name().to_string());
+            component
+                .kind()
+                .stamp_symbolic(
+                    &mut system,
+                    &terminals,
+                    branches.get(id).copied(),
+                    self.controlling_branch(id, &branches)?,
+                    &symbol,
+                    domain,
+                )
+                .map_err(|error| format!("{}: {}", component.name(), error))?;
+        }
+
+        let (mut numerators, denominator) = system.solve()?;
+        let branch_numerators = numerators.split_off(topology.unknown_count());
+        let node_numerators = (0..topology.node_count())
+            .map(|node_id| {
+                topology
+                    .unknown_of_node(node_id)
+                    .map(|unknown| numerators[unknown].clone())
+                    .unwrap_or_default()
+            })
+            .collect::<Vec<Polynomial>>();
+        self.nodes = topology.nodes().to_vec();
+        Ok(SymbolicNumerators {
+            topology,
+            branches,
+            node_numerators,
+            branch_numerators,
+            denominator,
+        })
+    }
+
+    // node voltages and currents as closed form expressions of the component symbols
+    pub fn solve_symbolic(&mut self, domain: Domain) -> Result<SymbolicSolution, String> {
+        let SymbolicNumerators {
+            topology,
+            branches,
+            node_numerators,
+            branch_numerators,
+            denominator,
+        } = self.symbolic_numerators(domain)?;
+        let node_voltages = node_numerators
+            .iter()
+            .map(|numerator| quotient(numerator, &denominator))
+            .collect::<Result<Vec<Expression>, String>>()?;
+        let currents = self
+            .components
+            .iter()
+            .map(|(id, component)| {
+                let voltages = component
+                    .ids()
+                    .iter()
+                    .map(|terminal_id| &node_numerators[topology.node_of(terminal_id).unwrap_or(0)])
+                    .collect::<Vec<&Polynomial>>();
+                let voltage = voltages[0] - voltages[1];
+                let symbol = Polynomial::symbol(Symbol::new(component.name().to_string()));
+                let current = match (component.kind(), domain) {
+                    (ComponentKind::Resistor, _) => {
+                        let denominator = denominator
+                            .checked_mul(&symbol)
+                            .ok_or_else(exponent_error)?;
+                        quotient(&voltage, &denominator)?
+                    }
+                    (ComponentKind::Capacitor, Domain::Dc) => Expression::integer(0),
+                    (ComponentKind::Capacitor, Domain::Laplace) => {
+                        let numerator = voltage
+                            .checked_mul(&symbol)
+                            .and_then(|product| {
+                                product.checked_mul(&Polynomial::symbol(laplace_variable()))
+                            })
+                            .ok_or_else(exponent_error)?;
+                        quotient(&numerator, &denominator)?
+                    }
+                    (ComponentKind::CurrentSource, _) => symbol.to_expression(),
+                    (
+                        ComponentKind::Inductor
+                        | ComponentKind::VoltageSource
+                        | ComponentKind::Vcvs
+                        | ComponentKind::Ccvs
+                        | ComponentKind::OpAmp
+                        | ComponentKind::FiniteOpAmp { .. },
+                        _,
+                    ) => quotient(&branch_numerators[branches[id]], &denominator)?,
+                    (ComponentKind::Vccs, _) => {
+                        let numerator = (voltages[2] - voltages[3])
+                            .checked_mul(&symbol)
+                            .ok_or_else(exponent_error)?;
+                        quotient(&numerator, &denominator)?
+                    }
+                    (ComponentKind::Cccs, _) => {
+                        let control = self
+                            .controlling_branch(id, &branches)?
+                            .ok_or("Controlling source has no branch")?;
+                        let numerator = branch_numerators[control]
+                            .checked_mul(&symbol)
+                            .ok_or_else(exponent_error)?;
+                        quotient(&numerator, &denominator)?
+                    }
+                    (ComponentKind::Diode(_), _) => {
+                        return Err("Diodes have no symbolic model".to_string())
+                    }
+                    (ComponentKind::Bjt(_) | ComponentKind::Mosfet(_), _) => {
+                        return Err("Transistors have no symbolic model".to_string())
+                    }
+                };
+                Ok((*id, current))
+            })
+            .collect::<Result<BTreeMap<ComponentId, Expression>, String>>()?;
+        Ok(SymbolicSolution {
+            node_voltages,
+            currents,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use num::complex::Complex64;
+
+    // V1 from terminal 1 (+) to 2 (-), R1 between 1 and 4, R2 between 4 and ground
+    fn divider() -> Circuit {
+        let mut circuit = Circuit::new();
+        circuit.add_component(ComponentKind::VoltageSource, Complex64::new(10.0, 0.0));
+        circuit.add_component(ComponentKind::Resistor, Complex64::new(1e3, 0.0));
+        circuit.add_component(ComponentKind::Resistor, Complex64::new(4e3, 0.0));
+        circuit.connect(&1, &3).unwrap();
+        circuit.connect(&4, &5).unwrap();
+        circuit.connect(&6, &2).unwrap();
+        circuit.set_ground(2);
+        circuit
+    }
+
+    #[test]
+    fn test_voltage_divider() {
+        let mut circuit = divider();
+        let solution = circuit.solve_symbolic(Domain::Dc).unwrap();
+        let output = circuit.node_of(&4).unwrap();
+        assert_eq!(
+            solution.node_voltage(output).unwrap().to_infix(),
+            "R2 * V1 / (R1 + R2)"
+        );
+        assert_eq!(
+            solution
+                .node_voltage(circuit.node_of(&1).unwrap())
+                .unwrap()
+                .to_infix(),
+            "V1"
+        );
+        // the source current flows into its positive terminal
+        assert_eq!(
+            solution.current(ComponentId(0)).unwrap().to_infix(),
+            "-V1 / (R1 + R2)"
+        );
+        assert_eq!(
+            solution.current(ComponentId(2)).unwrap().to_infix(),
+            "V1 / (R1 + R2)"
+        );
+    }
+
+    #[test]
+    fn test_rc_lowpass() {
+        // V1 - R1 - node - C1 - ground, L1 in series with the source does not change anything
+        let mut circuit = Circuit::new();
+        circuit.add_component(ComponentKind::VoltageSource, Complex64::new(1.0, 0.0)); // 1, 2
+        circuit.add_component(ComponentKind::Resistor, Complex64::new(1e3, 0.0)); // 3, 4
+        circuit.add_component(ComponentKind::Capacitor, Complex64::new(1e-6, 0.0)); // 5, 6
+        circuit.add_component(ComponentKind::Inductor, Complex64::new(1e-3, 0.0)); // 7, 8
+        circuit.connect(&1, &7).unwrap();
+        circuit.connect(&8, &3).unwrap();
+        circuit.connect(&4, &5).unwrap();
+        circuit.connect(&6, &2).unwrap();
+        circuit.set_ground(2);
+
+        let solution = circuit.solve_symbolic(Domain::Laplace).unwrap();
+        let output = solution.node_voltage(circuit.node_of(&4).unwrap()).unwrap();
+        assert_eq!(output.to_infix(), "V1 / (C1 * L1 * s^2 + C1 * R1 * s + 1)");
+        let dc = circuit.solve_symbolic(Domain::Dc).unwrap();
+        assert_eq!(
+            dc.node_voltage(circuit.node_of(&4).unwrap())
+                .unwrap()
+                .to_infix(),
+            "V1"
+        );
+        assert_eq!(dc.current(ComponentId(2)).unwrap().to_infix(), "0");
+    }
+
+    // V1 (1, 2) loaded by R1 (3, 4) drives one amplifier per controlled source:
+    // E1 (5, 6, 7, 8) with the feedback divider R2 (9, 10), R3 (11, 12), G1 (13, 14, 15, 16) into
+    // R4 (17, 18), H1 (19, 20) and F1 (21, 22) into R5 (23, 24) sense the current of V1
+    fn controlled_sources() -> Circuit {
+        let mut circuit = Circuit::new();
+        let source = circuit.add_component(ComponentKind::VoltageSource, Complex64::new(1.0, 0.0));
+        circuit.add_component(ComponentKind::Resistor, Complex64::new(1e3, 0.0));
+        circuit.add_component(ComponentKind::Vcvs, Complex64::new(100.0, 0.0));
+        circuit.add_component(ComponentKind::Resistor, Complex64::new(9e3, 0.0));
+        circuit.add_component(ComponentKind::Resistor, Complex64::new(1e3, 0.0));
+        circuit.add_component(ComponentKind::Vccs, Complex64::new(2e-3, 0.0));
+        circuit.add_component(ComponentKind::Resistor, Complex64::new(5e3, 0.0));
+        let h1 = circuit.add_component(ComponentKind::Ccvs, Complex64::new(50.0, 0.0));
+        let f1 = circuit.add_component(ComponentKind::Cccs, Complex64::new(4.0, 0.0));
+        circuit.add_component(ComponentKind::Resistor, Complex64::new(2e3, 0.0));
+        circuit.set_controlling_source(h1, source).unwrap();
+        circuit.set_controlling_source(f1, source).unwrap();
+        for (a, b) in [
+            // input
+            (1, 3),
+            (1, 7),
+            (1, 15),
+            // E1 output and feedback
+            (5, 9),
+            (8, 10),
+            (10, 11),
+            // G1 output
+            (13, 17),
+            // F1 output
+            (22, 23),
+            // ground
+            (2, 4),
+            (2, 6),
+            (2, 12),
+            (2, 14),
+            (2, 16),
+            (2, 18),
+            (2, 20),
+            (2, 21),
+            (2, 24),
+        ] {
+            circuit.connect(&a, &b).unwrap();
+        }
+        circuit.set_ground(2);
+        circuit
+    }
+
+    #[test]
+    fn test_controlled_sources() {
+        let mut circuit = controlled_sources();
+        let solution = circuit.solve_symbolic(Domain::Dc).unwrap();
+        let voltage = |terminal_id: usize| {
+            solution
+                .node_voltage(circuit.node_of(&terminal_id).unwrap())
+                .unwrap()
+                .to_infix()
+        };
+        // non-inverting amplifier with the loop gain E1 * R3 / (R2 + R3)
+        assert_eq!(
+            voltage(5),
+            "(E1 * R2 * V1 + E1 * R3 * V1) / (E1 * R3 + R2 + R3)"
+        );
+        // transconductance amplifier
+        assert_eq!(voltage(13), "-(G1 * R4 * V1)");
+        // transresistance and current amplifier, the current of V1 is -V1 / R1
+        assert_eq!(voltage(19), "-(H1 * V1) / R1");
+        assert_eq!(voltage(22), "-(F1 * R5 * V1) / R1");
+        assert_eq!(
+            solution.current(ComponentId(8)).unwrap().to_infix(),
+            "-(F1 * V1) / R1"
+        );
+
+        let values = circuit.component_values();
+        let numeric = circuit.solve_dc().unwrap();
+        for (node_id, voltage) in numeric.node_voltages().iter().enumerate() {
+            let evaluated = solution
+                .node_voltage(node_id)
+                .unwrap()
+                .evaluate(&values)
+                .unwrap();
+            assert!((evaluated - voltage).norm() < 1e-9, "node {}", node_id);
+        }
+        for (id, current) in numeric.currents() {
+            let evaluated = solution.current(*id).unwrap().evaluate(&values).unwrap();
+            assert!((evaluated - current).norm() < 1e-12, "component {}", id);
+        }
+    }
+
+    #[test]
+    fn test_op_amp() {
+        // inverting amplifier: V1 (1, 2), U1 (3 output, 4 +, 5 -), R1 (6, 7) and R2 (8, 9)
+        for (kind, expected) in [
+            (ComponentKind::OpAmp, "-(R2 * V1) / R1"),
+            // the open-loop gain U1 without the gain bandwidth product
+            (
+                ComponentKind::FiniteOpAmp {
+                    gain_bandwidth: Some(1e6),
+                },
+                "-(R2 * U1 * V1) / (R1 * U1 + R1 + R2)",
+            ),
+        ] {
+            let mut circuit = Circuit::new();
+            circuit.add_component(ComponentKind::VoltageSource, Complex64::new(1.0, 0.0));
+            circuit.add_component(kind, Complex64::new(1e5, 0.0));
+            circuit.add_component(ComponentKind::Resistor, Complex64::new(1e3, 0.0));
+            circuit.add_component(ComponentKind::Resistor, Complex64::new(1e4, 0.0));
+            for (a, b) in [(1, 6), (7, 5), (5, 8), (9, 3), (4, 2)] {
+                circuit.connect(&a, &b).unwrap();
+            }
+            circuit.set_ground(2);
+            let solution = circuit.solve_symbolic(Domain::Laplace).unwrap();
+            let output = solution.node_voltage(circuit.node_of(&3).unwrap()).unwrap();
+            assert_eq!(output.to_infix(), expected);
+        }
+    }
+
+    #[test]
+    fn test_matches_numeric_solution() {
+        // bridge of five resistors fed by a current source
+        let mut circuit = Circuit::new();
+        circuit.add_component(ComponentKind::CurrentSource, Complex64::new(2e-3, 0.0)); // 1, 2
+        for value in [1e3, 2e3, 3e3, 4e3, 5e3] {
+            circuit.add_component(ComponentKind::Resistor, Complex64::new(value, 0.0));
+        }
+        // top (2): R1 (3, 4), R2 (5, 6); left (4): R3 (7, 8), middle R5 (11, 12); right (6): R4 (9, 10)
+        for (a, b) in [
+            (2, 3),
+            (3, 5),
+            (4, 7),
+            (4, 11),
+            (6, 12),
+            (6, 9),
+            (8, 10),
+            (10, 1),
+        ] {
+            circuit.connect(&a, &b).unwrap();
+        }
+        circuit.set_ground(1);
+        let symbolic = circuit.solve_symbolic(Domain::Dc).unwrap();
+        let values = circuit.component_values();
+        let numeric = circuit.solve_dc().unwrap();
+        for (node_id, voltage) in numeric.node_voltages().iter().enumerate() {
+            let evaluated = symbolic
+                .node_voltage(node_id)
+                .unwrap()
+                .evaluate(&values)
+                .unwrap();
+            assert!((evaluated - voltage).norm() < 1e-9, "node {}", node_id);
+        }
+        for (id, current) in numeric.currents() {
+            let evaluated = symbolic.current(*id).unwrap().evaluate(&values).unwrap();
+            assert!((evaluated - current).norm() < 1e-12, "component {}", id);
+        }
+    }
+}
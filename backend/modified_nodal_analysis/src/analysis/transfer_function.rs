@@ -0,0 +1,341 @@
+use num::complex::Complex64;
+use num::Zero;
+use std::collections::HashMap;
+use symbolic_manipulation::polynomial::Polynomial;
+use symbolic_manipulation::{Expression, Symbol};
+
+use crate::analysis::symbolic::{laplace_variable, quotient, reduce, reduced_quotient, Domain};
+use crate::graph::component::{ComponentId, ComponentKind};
+use crate::Circuit;
+
+// Durand-Kerner iterations before the roots are accepted as they are
+const MAX_ITERATIONS: usize = 500;
+
+// H(s) = numerator / denominator, both polynomials in s and the component symbols
+#[derive(Clone, Debug, PartialEq)]
+pub struct RationalFunction {
+    numerator: Polynomial,
+    denominator: Polynomial,
+}
+
+impl RationalFunction {
+    // cancels common factors and normalizes the denominator
+    pub fn new(numerator: &Polynomial, denominator: &Polynomial) -> Result<Self, String> {
+        if denominator.is_zero() {
+            return Err("Transfer function with a zero denominator".to_string());
+        }
+        let (numerator, denominator) = reduce(numerator, denominator)?;
+        Ok(Self {
+            numerator,
+            denominator,
+        })
+    }
+
+    pub fn numerator(&self) -> Expression {
+        self.numerator.to_expression()
+    }
+
+    pub fn denominator(&self) -> Expression {
+        self.denominator.to_expression()
+    }
+
+    pub fn to_expression(&self) -> Expression {
+        reduced_quotient(&self.numerator, &self.denominator)
+    }
+
+    // the degree of the denominator in s
+    pub fn order(&self) -> usize {
+        self.denominator
+            .degree_in(&laplace_variable())
+            .unwrap_or(0)
+            .max(0) as usize
+    }
+
+    // H(0), an error if the function has a pole at s = 0
+    pub fn dc_gain(&self) -> Result<Expression, String> {
+        let s = laplace_variable();
+        let denominator = self.denominator.coefficient(&s, 0);
+        if denominator.is_zero() {
+            return Err("The transfer function has a pole at s = 0".to_string());
+        }
+        quotient(&self.numerator.coefficient(&s, 0), &denominator)
+    }
+
+    // H(s) with the component symbols bound to values
+    pub fn evaluate(
+        &self,
+        s: Complex64,
+        values: &HashMap<Symbol, Complex64>,
+    ) -> Result<Complex64, String> {
+        let mut values = values.clone();
+        values.insert(laplace_variable(), s);
+        let numerator = evaluate(&self.numerator, &values)?;
+        let denominator = evaluate(&self.denominator, &values)?;
+        if denominator.is_zero() {
+            return Err(format!("The transfer function has a pole at s = {}", s));
+        }
+        Ok(numerator / denominator)
+    }
+
+    // the frequency response H(jω), omega in rad/s
+    pub fn at_frequency(
+        &self,
+        omega: f64,
+        values: &HashMap<Symbol, Complex64>,
+    ) -> Result<Complex64, String> {
+        self.evaluate(Complex64::new(0.0, omega), values)
+    }
+
+    pub fn zeros(&self, values: &HashMap<Symbol, Complex64>) -> Result<Vec<Complex64>, String> {
+        Ok(roots(&coefficients(&self.numerator, values)?))
+    }
+
+    pub fn poles(&self, values: &HashMap<Symbol, Complex64>) -> Result<Vec<Complex64>, String> {
+        Ok(roots(&coefficients(&self.denominator, values)?))
+    }
+}
+
+fn evaluate(
+    polynomial: &Polynomial,
+    values: &HashMap<Symbol, Complex64>,
+) -> Result<Complex64, String> {
+    polynomial
+        .to_expression()
+        .evaluate(values)
+        .map_err(|error| error.to_string())
+}
+
+// numeric coefficients of the powers of s, the constant first
+fn coefficients(
+    polynomial: &Polynomial,
+    values: &HashMap<Symbol, Complex64>,
+) -> Result<Vec<Complex64>, String> {
+    let s = laplace_variable();
+    let degree = polynomial.degree_in(&s).unwrap_or(0).max(0);
+    (0..=degree)
+        .map(|exponent| evaluate(&polynomial.coefficient(&s, exponent), values))
+        .collect()
+}
+
+// roots of the polynomial with the coefficients (constant first) by Durand-Kerner iteration,
+// sorted by their real and then imaginary parts
+fn roots(coefficients: &[Complex64]) -> Vec<Complex64> {
+    let mut coefficients = coefficients.to_vec();
+    while coefficients
+        .last()
+        .is_some_and(|coefficient| coefficient.is_zero())
+    {
+        coefficients.pop();
+    }
+    // a vanishing constant is a root at 0
+    let mut roots = Vec::new();
+    while coefficients.len() > 1 && coefficients[0].is_zero() {
+        coefficients.remove(0);
+        roots.push(Complex64::zero());
+    }
+    let degree = coefficients.len().saturating_sub(1);
+    if degree > 0 {
+        let leading = coefficients[degree];
+        let monic = coefficients
+            .iter()
+            .map(|coefficient| coefficient / leading)
+            .collect::<Vec<Complex64>>();
+        let value = |z: Complex64| {
+            monic
+                .iter()
+                .rev()
+                .fold(Complex64::zero(), |sum, c| sum * z + c)
+        };
+        // the starting points lie on a circle that encloses all roots (Cauchy bound)
+        let radius = 1.0
+            + monic[..degree]
+                .iter()
+                .map(|coefficient| coefficient.norm())
+                .fold(0.0, f64::max);
+        let seed = Complex64::new(0.4, 0.9);
+        let mut estimates = (0..degree)
+            .map(|index| radius * (seed / seed.norm()).powu(index as u32 + 1))
+            .collect::<Vec<Complex64>>();
+        for _ in 0..MAX_ITERATIONS {
+            let mut change: f64 = 0.0;
+            for index in 0..degree {
+                let z = estimates[index];
+                let divisor = (0..degree)
+                    .filter(|other| *other != index)
+                    .fold(Complex64::new(1.0, 0.0), |product, other| {
+                        product * (z - estimates[other])
+                    });
+                if divisor.is_zero() {
+                    continue;
+                }
+                let step = value(z) / divisor;
+                estimates[index] = z - step;
+                change = change.max(step.norm() / z.norm().max(1e-300));
+            }
+            if change < 1e-14 {
+                break;
+            }
+        }
+        roots.extend(estimates);
+    }
+    roots.sort_by(|a, b| a.re.total_cmp(&b.re).then(a.im.total_cmp(&b.im)));
+    roots
+}
+
+impl Circuit {
+    // the symbols of all components bound to their values
+    pub fn component_values(&self) -> HashMap<Symbol, Complex64> {
+        self.components
+            .values()
+            .map(|component| (Symbol::new(component.name().to_string()), component.value()))
+            .collect()
+    }
+
+    // H(s) from an independent source to the voltage of the node of the output terminal, all
+    // other sources are set to zero
+    pub fn transfer_function(
+        &mut self,
+        input_source: ComponentId,
+        output_terminal: usize,
+    ) -> Result<RationalFunction, String> {
+        let source = self
+            .components
+            .get(&input_source)
+            .ok_or(format!("Component {} does not exist", input_source))?;
+        if !matches!(
+            source.kind(),
+            ComponentKind::VoltageSource | ComponentKind::CurrentSource
+        ) {
+            return Err(format!("{} is not an independent source", source.name()));
+        }
+        let symbol = Symbol::new(source.name().to_string());
+        let numerators = self.symbolic_numerators(Domain::Laplace)?;
+        let numerator = numerators
+            .topology
+            .node_of(&output_terminal)
+            .map(|node_id| &numerators.node_numerators[node_id])
+            .ok_or(format!("Terminal {} does not exist", output_terminal))?;
+        // the node voltage is linear in the sources, the input contributes its coefficient
+        RationalFunction::new(&numerator.coefficient(&symbol, 1), &numerators.denominator)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn assert_close(actual: Complex64, expected: Complex64) {
+        assert!(
+            (actual - expected).norm() < 1e-6 * expected.norm().max(1.0),
+            "{} != {}",
+            actual,
+            expected
+        );
+    }
+
+    #[test]
+    fn test_rc_lowpass() {
+        // V1 (1, 2), R1 (3, 4) and C1 (5, 6) to ground, with a load current source I1 (7, 8)
+        let mut circuit = Circuit::new();
+        let source = circuit.add_component(ComponentKind::VoltageSource, Complex64::new(1.0, 0.0));
+        circuit.add_component(ComponentKind::Resistor, Complex64::new(1e3, 0.0));
+        circuit.add_component(ComponentKind::Capacitor, Complex64::new(1e-6, 0.0));
+        circuit.add_component(ComponentKind::CurrentSource, Complex64::new(1e-3, 0.0));
+        for (a, b) in [(1, 3), (4, 5), (5, 7), (6, 2), (8, 2)] {
+            circuit.connect(&a, &b).unwrap();
+        }
+        circuit.set_ground(2);
+        let h = circuit.transfer_function(source, 4).unwrap();
+        assert_eq!(h.to_expression().to_infix(), "1 / (C1 * R1 * s + 1)");
+        assert_eq!(h.numerator().to_infix(), "1");
+        assert_eq!(h.order(), 1);
+        assert_eq!(h.dc_gain().unwrap().to_infix(), "1");
+
+        let values = circuit.component_values();
+        assert_eq!(h.zeros(&values).unwrap(), vec![]);
+        let poles = h.poles(&values).unwrap();
+        assert_eq!(poles.len(), 1);
+        assert_close(poles[0], Complex64::new(-1e3, 0.0));
+        // -3 dB at the corner frequency
+        let corner = h.at_frequency(1e3, &values).unwrap();
+        assert_close(corner, Complex64::new(0.5, -0.5));
+
+        assert!(circuit.transfer_function(ComponentId(1), 4).is_err());
+        assert!(circuit.transfer_function(source, 9).is_err());
+    }
+
+    #[test]
+    fn test_inverting_amplifier() {
+        // V1 (1, 2), U1 (3 output, 4 +, 5 -), R1 (6, 7) from the input, R2 (8, 9) as feedback
+        // and C1 (10, 11) in parallel to R2
+        let mut circuit = Circuit::new();
+        let source = circuit.add_component(ComponentKind::VoltageSource, Complex64::new(1.0, 0.0));
+        circuit.add_component(ComponentKind::OpAmp, Complex64::zero());
+        circuit.add_component(ComponentKind::Resistor, Complex64::new(1e3, 0.0));
+        circuit.add_component(ComponentKind::Resistor, Complex64::new(1e4, 0.0));
+        for (a, b) in [(1, 6), (7, 5), (5, 8), (9, 3), (4, 2)] {
+            circuit.connect(&a, &b).unwrap();
+        }
+        circuit.set_ground(2);
+        let h = circuit.transfer_function(source, 3).unwrap();
+        assert_eq!(h.to_expression().to_infix(), "-R2 / R1");
+
+        circuit.add_component(ComponentKind::Capacitor, Complex64::new(1e-9, 0.0));
+        circuit.connect(&10, &8).unwrap();
+        circuit.connect(&11, &9).unwrap();
+        let h = circuit.transfer_function(source, 3).unwrap();
+        assert_eq!(
+            h.to_expression().to_infix(),
+            "-R2 / (C1 * R1 * R2 * s + R1)"
+        );
+        assert_eq!(h.dc_gain().unwrap().to_infix(), "-R2 / R1");
+        let poles = h.poles(&circuit.component_values()).unwrap();
+        assert_close(poles[0], Complex64::new(-1e5, 0.0));
+    }
+
+    #[test]
+    fn test_rlc_bandpass() {
+        // V1 (1, 2) - L1 (3, 4) - C1 (5, 6) - R1 (7, 8) to ground, output across R1
+        let mut circuit = Circuit::new();
+        let source = circuit.add_component(ComponentKind::VoltageSource, Complex64::new(1.0, 0.0));
+        circuit.add_component(ComponentKind::Inductor, Complex64::new(1e-3, 0.0));
+        circuit.add_component(ComponentKind::Capacitor, Complex64::new(1e-6, 0.0));
+        circuit.add_component(ComponentKind::Resistor, Complex64::new(10.0, 0.0));
+        for (a, b) in [(1, 3), (4, 5), (6, 7), (8, 2)] {
+            circuit.connect(&a, &b).unwrap();
+        }
+        circuit.set_ground(2);
+        let h = circuit.transfer_function(source, 7).unwrap();
+        assert_eq!(
+            h.to_expression().to_infix(),
+            "C1 * R1 * s / (C1 * L1 * s^2 + C1 * R1 * s + 1)"
+        );
+        assert_eq!(h.order(), 2);
+        assert_eq!(h.dc_gain().unwrap().to_infix(), "0");
+
+        let values = circuit.component_values();
+        assert_eq!(h.zeros(&values).unwrap(), vec![Complex64::zero()]);
+        // s^2 + R/L s + 1/(LC) = 0
+        let poles = h.poles(&values).unwrap();
+        let (real, imaginary) = (-5e3, (1e9_f64 - 25e6).sqrt());
+        assert_close(poles[0], Complex64::new(real, -imaginary));
+        assert_close(poles[1], Complex64::new(real, imaginary));
+
+        // matches the numeric AC analysis at resonance
+        circuit.set_ac(source, 1.0, 0.0).unwrap();
+        let omega = 1e9_f64.sqrt();
+        let sweep = circuit
+            .ac_sweep(
+                omega / (2.0 * std::f64::consts::PI),
+                omega / (2.0 * std::f64::consts::PI),
+                1,
+                crate::analysis::ac::SweepKind::Linear,
+            )
+            .unwrap();
+        let output = circuit.node_of(&7).unwrap();
+        assert_close(
+            h.at_frequency(omega, &values).unwrap(),
+            sweep.node_voltages(output)[0],
+        );
+    }
+}
@@ -0,0 +1,99 @@
+use crate::analysis::newton::NewtonOptions;
+use crate::analysis::{Analysis, Solution};
+use crate::graph::component::ComponentId;
+use crate::Circuit;
+
+// integration rule of the companion models of capacitors and inductors
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Integration {
+    BackwardEuler,
+    Trapezoidal,
+}
+
+// one solution per time point, starting with the operating point at t = 0
+pub struct Waveforms {
+    times: Vec<f64>,
+    solutions: Vec<Solution>,
+}
+
+impl Waveforms {
+    pub fn times(&self) -> &[f64] {
+        &self.times
+    }
+
+    pub fn solutions(&self) -> &[Solution] {
+        &self.solutions
+    }
+
+    pub fn node_voltages(&self, node_id: usize) -> Vec<f64> {
+        self.solutions
+            .iter()
+            .filter_map(|solution| solution.node_voltage(node_id))
+            .map(|voltage| voltage.re)
+            .collect()
+    }
+
+    pub fn currents(&self, component_id: ComponentId) -> Vec<f64> {
+        self.solutions
+            .iter()
+            .filter_map(|solution| solution.current(component_id))
+            .map(|current| current.re)
+            .collect()
+    }
+}
+
+impl Circuit {
+    // capacitor voltage or inductor current at t = 0, without one the operating point is used
+    pub fn set_initial_condition(
+        &mut self,
+        id: ComponentId,
+        value: Option<f64>,
+    ) -> Result<(), String> {
+        self.components
+            .get_mut(&id)
+            .ok_or(format!("Component {} does not exist", id))?
+            .set_initial_condition(value.map(|value| value.into()))
+    }
+
+    pub fn transient(&mut self, t_stop: f64, t_step: f64) -> Result<Waveforms, String> {
+        self.transient_with(t_stop, t_step, Integration::Trapezoidal)
+    }
+
+    pub fn transient_with(
+        &mut self,
+        t_stop: f64,
+        t_step: f64,
+        method: Integration,
+    ) -> Result<Waveforms, String> {
+        if !(t_step > 0.0 && t_step.is_finite()) {
+            return Err(format!("Invalid time step {} s", t_step));
+        }
+        if !(t_stop > 0.0 && t_stop.is_finite()) {
+            return Err(format!("Invalid stop time {} s", t_stop));
+        }
+        let topology = self.topology()?;
+        let options = NewtonOptions::default();
+        let mut times = vec![0.0];
+        let mut solutions = vec![self
+            .solve_operating_point(&topology, Analysis::Initial, &options)
+            .map_err(|error| format!("t = 0 s: {}", error))?];
+        let steps = (t_stop / t_step - 1e-9).ceil().max(1.0) as usize;
+        for index in 1..=steps {
+            // the last step is shortened to end exactly at the stop time
+            let time = (index as f64 * t_step).min(t_stop);
+            let step = time - times[index - 1];
+            let solution = self
+                .solve_newton(
+                    &topology,
+                    Analysis::Transient { step, method },
+                    solutions.last(),
+                    &options,
+                )
+                .map_err(|error| format!("t = {} s: {}", time, error))?;
+            times.push(time);
+            solutions.push(solution);
+        }
+        self.nodes = topology.nodes().to_vec();
+        Ok(Waveforms { times, solutions })
+    }
+}
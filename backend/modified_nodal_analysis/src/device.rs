@@ -0,0 +1,99 @@
+use num::complex::Complex64;
+
+pub mod bjt;
+pub mod diode;
+pub mod mosfet;
+
+// NPN or NMOS, PNP or PMOS
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Polarity {
+    N,
+    P,
+}
+
+impl Polarity {
+    // the P devices are the N ones with every voltage and current negated
+    pub fn sign(&self) -> f64 {
+        match self {
+            Polarity::N => 1.0,
+            Polarity::P => -1.0,
+        }
+    }
+}
+
+// a terminal current of a transistor linearized at its bias as a function of the controlling
+// terminal voltages: i = conductances · v + current
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Companion<const N: usize> {
+    pub conductances: [f64; N],
+    pub current: f64,
+}
+
+impl<const N: usize> Companion<N> {
+    // the small signal current only depends on the conductances
+    pub fn current_at(&self, voltages: [Complex64; N], small_signal: bool) -> Complex64 {
+        let current = if small_signal { 0.0 } else { self.current };
+        self.conductances.iter().zip(voltages).fold(
+            Complex64::new(current, 0.0),
+            |sum, (conductance, voltage)| sum + conductance * voltage,
+        )
+    }
+}
+
+// small signal parameters of a transistor at its operating point
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct SmallSignal {
+    // dIc / dVbe or dId / dVgs (S)
+    pub gm: f64,
+    // output conductance dIc / dVce or dId / dVds (S)
+    pub gds: f64,
+    // dVbe / dIb (Ω), infinite for MOSFETs
+    pub r_pi: f64,
+}
+
+// SPICE's pnjlim: limits the change of a pn junction voltage to keep the exponential in range,
+// returns the limited voltage and whether it was limited
+pub fn limit_junction_voltage(
+    voltage: f64,
+    previous: f64,
+    thermal_voltage: f64,
+    critical_voltage: f64,
+) -> (f64, bool) {
+    if voltage <= critical_voltage || (voltage - previous).abs() <= 2.0 * thermal_voltage {
+        return (voltage, false);
+    }
+    let limited = if previous > 0.0 {
+        let argument = 1.0 + (voltage - previous) / thermal_voltage;
+        if argument > 0.0 {
+            previous + thermal_voltage * argument.ln()
+        } else {
+            critical_voltage
+        }
+    } else {
+        thermal_voltage * (voltage / thermal_voltage).ln()
+    };
+    (limited, true)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_limit_junction_voltage() {
+        let (vt, vcrit) = (0.025, 0.6);
+        // small steps and steps below the critical voltage pass
+        assert_eq!(limit_junction_voltage(0.62, 0.6, vt, vcrit), (0.62, false));
+        assert_eq!(
+            limit_junction_voltage(-10.0, 0.7, vt, vcrit),
+            (-10.0, false)
+        );
+        // from a forward biased junction the step grows logarithmically
+        let (limited, was_limited) = limit_junction_voltage(5.0, 0.7, vt, vcrit);
+        assert!(was_limited);
+        assert!((limited - (0.7 + vt * (1.0 + 4.3 / vt).ln())).abs() < 1e-12);
+        // from a reverse biased one it starts at the thermal voltage scale
+        let (limited, _) = limit_junction_voltage(5.0, -1.0, vt, vcrit);
+        assert!((limited - vt * (5.0 / vt).ln()).abs() < 1e-12);
+    }
+}
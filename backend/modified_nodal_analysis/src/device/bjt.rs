@@ -0,0 +1,202 @@
+use crate::analysis::newton::{BiasUpdate, NewtonOptions};
+use crate::analysis::Bias;
+use crate::device::diode::GMIN;
+use crate::device::{limit_junction_voltage, Companion, Polarity, SmallSignal};
+use std::f64::consts::SQRT_2;
+
+// Ebers-Moll transport model with the forward Early effect of Gummel-Poon, the currents of a PNP
+// are the ones of the NPN with every voltage and current negated
+// terminals: collector, base, emitter
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct BjtModel {
+    pub polarity: Polarity,
+    // Is (A)
+    pub saturation_current: f64,
+    // βF
+    pub forward_beta: f64,
+    // βR
+    pub reverse_beta: f64,
+    // VAF (V), no base width modulation if None
+    pub early_voltage: Option<f64>,
+    // Vt = kT/q (V)
+    pub thermal_voltage: f64,
+}
+
+impl Default for BjtModel {
+    // the SPICE defaults at 300 K
+    fn default() -> Self {
+        Self {
+            polarity: Polarity::N,
+            saturation_current: 1e-16,
+            forward_beta: 100.0,
+            reverse_beta: 1.0,
+            early_voltage: None,
+            thermal_voltage: 0.025852,
+        }
+    }
+}
+
+// the currents of the NPN equivalent at one bias and their derivatives by (vbe, vbc)
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct BjtOperatingPoint {
+    pub collector_current: f64,
+    pub base_current: f64,
+    pub collector_conductances: [f64; 2],
+    pub base_conductances: [f64; 2],
+}
+
+impl BjtModel {
+    pub fn validate(&self) -> Result<(), String> {
+        if !(self.saturation_current > 0.0
+            && self.forward_beta > 0.0
+            && self.reverse_beta > 0.0
+            && self.thermal_voltage > 0.0)
+        {
+            return Err("BJT needs a positive Is, βF, βR and Vt".to_string());
+        }
+        if self.early_voltage.is_some_and(|voltage| voltage <= 0.0) {
+            return Err("BJT has an invalid Early voltage".to_string());
+        }
+        Ok(())
+    }
+
+    fn critical_voltage(&self) -> f64 {
+        self.thermal_voltage * (self.thermal_voltage / (SQRT_2 * self.saturation_current)).ln()
+    }
+
+    // (vbe, vbc) of the NPN equivalent from the collector, base and emitter voltages
+    pub fn junction_voltages(&self, voltages: &[f64]) -> [f64; 2] {
+        let sign = self.polarity.sign();
+        [
+            sign * (voltages[1] - voltages[2]),
+            sign * (voltages[1] - voltages[0]),
+        ]
+    }
+
+    // GMIN is in parallel to both junctions
+    pub fn evaluate(&self, base_emitter: f64, base_collector: f64) -> BjtOperatingPoint {
+        let (is, vt) = (self.saturation_current, self.thermal_voltage);
+        let (forward_exponential, reverse_exponential) =
+            ((base_emitter / vt).exp(), (base_collector / vt).exp());
+        let (forward, reverse) = (
+            is * (forward_exponential - 1.0),
+            is * (reverse_exponential - 1.0),
+        );
+        let (forward_conductance, reverse_conductance) =
+            (is / vt * forward_exponential, is / vt * reverse_exponential);
+        // the Early effect scales the transport current with 1 - vbc / VAF
+        let (early, early_derivative) = match self.early_voltage {
+            Some(voltage) => (1.0 - base_collector / voltage, -1.0 / voltage),
+            None => (1.0, 0.0),
+        };
+        let transport = (forward - reverse) * early;
+        BjtOperatingPoint {
+            collector_current: transport - reverse / self.reverse_beta - GMIN * base_collector,
+            base_current: forward / self.forward_beta
+                + reverse / self.reverse_beta
+                + GMIN * (base_emitter + base_collector),
+            collector_conductances: [
+                forward_conductance * early,
+                -reverse_conductance * early + (forward - reverse) * early_derivative
+                    - reverse_conductance / self.reverse_beta
+                    - GMIN,
+            ],
+            base_conductances: [
+                forward_conductance / self.forward_beta + GMIN,
+                reverse_conductance / self.reverse_beta + GMIN,
+            ],
+        }
+    }
+
+    // collector and base current into the device and out of the emitter as functions of
+    // v(base) - v(emitter) and v(base) - v(collector)
+    pub fn companions(&self, bias: Bias) -> [Companion<2>; 2] {
+        let point = self.evaluate(bias[0], bias[1]);
+        let companion = |current: f64, conductances: [f64; 2]| Companion {
+            conductances,
+            current: self.polarity.sign()
+                * (current - conductances[0] * bias[0] - conductances[1] * bias[1]),
+        };
+        [
+            companion(point.collector_current, point.collector_conductances),
+            companion(point.base_current, point.base_conductances),
+        ]
+    }
+
+    // gm and the output conductance at a constant vce, rπ from the base current
+    pub fn small_signal(&self, bias: Bias) -> SmallSignal {
+        let point = self.evaluate(bias[0], bias[1]);
+        let [collector_be, collector_bc] = point.collector_conductances;
+        let [base_be, base_bc] = point.base_conductances;
+        SmallSignal {
+            gm: collector_be + collector_bc,
+            gds: -collector_bc,
+            r_pi: 1.0 / (base_be + base_bc),
+        }
+    }
+
+    // both junction voltages are limited like the one of a diode
+    pub fn next_bias(&self, voltages: &[f64], bias: Bias, options: &NewtonOptions) -> BiasUpdate {
+        let [base_emitter, base_collector] = self.junction_voltages(voltages);
+        let point = self.evaluate(bias[0], bias[1]);
+        let predict = |current: f64, conductances: [f64; 2]| {
+            current
+                + conductances[0] * (base_emitter - bias[0])
+                + conductances[1] * (base_collector - bias[1])
+        };
+        let collector = predict(point.collector_current, point.collector_conductances);
+        let base = predict(point.base_current, point.base_conductances);
+        let (vt, critical) = (self.thermal_voltage, self.critical_voltage());
+        let (base_emitter, limited_emitter) =
+            limit_junction_voltage(base_emitter, bias[0], vt, critical);
+        let (base_collector, limited_collector) =
+            limit_junction_voltage(base_collector, bias[1], vt, critical);
+        let exact = self.evaluate(base_emitter, base_collector);
+        BiasUpdate {
+            bias: [base_emitter, base_collector, 0.0],
+            limited: limited_emitter || limited_collector,
+            converged: options.current_converged(collector, exact.collector_current)
+                && options.current_converged(base, exact.base_current),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_conductances() {
+        // the derivatives match finite differences in every region
+        let model = BjtModel {
+            early_voltage: Some(50.0),
+            ..Default::default()
+        };
+        let delta = 1e-7;
+        for (vbe, vbc) in [(0.7, -3.0), (0.7, 0.6), (-1.0, 0.65), (-1.0, -1.0)] {
+            let point = model.evaluate(vbe, vbc);
+            let by_vbe = model.evaluate(vbe + delta, vbc);
+            let by_vbc = model.evaluate(vbe, vbc + delta);
+            let close = |derivative: f64, finite: f64| {
+                (derivative - finite).abs() <= 1e-4 * derivative.abs() + 1e-12
+            };
+            for (current, conductances, by_vbe, by_vbc) in [
+                (
+                    point.collector_current,
+                    point.collector_conductances,
+                    by_vbe.collector_current,
+                    by_vbc.collector_current,
+                ),
+                (
+                    point.base_current,
+                    point.base_conductances,
+                    by_vbe.base_current,
+                    by_vbc.base_current,
+                ),
+            ] {
+                assert!(close(conductances[0], (by_vbe - current) / delta));
+                assert!(close(conductances[1], (by_vbc - current) / delta));
+            }
+        }
+    }
+}
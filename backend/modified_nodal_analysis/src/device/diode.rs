@@ -0,0 +1,100 @@
+use std::f64::consts::SQRT_2;
+
+use crate::device::limit_junction_voltage;
+
+// conductance in parallel to every junction, keeps reverse biased diodes from floating a node
+pub const GMIN: f64 = 1e-12;
+
+// Shockley diode i = Is * (exp(vd / (n * Vt)) - 1) with the junction voltage vd behind the
+// series resistance
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct DiodeModel {
+    // Is (A)
+    pub saturation_current: f64,
+    // n
+    pub emission_coefficient: f64,
+    // Vt = kT/q (V)
+    pub thermal_voltage: f64,
+    // Rs (Ω)
+    pub series_resistance: f64,
+}
+
+impl Default for DiodeModel {
+    // the SPICE defaults at 300 K
+    fn default() -> Self {
+        Self {
+            saturation_current: 1e-14,
+            emission_coefficient: 1.0,
+            thermal_voltage: 0.025852,
+            series_resistance: 0.0,
+        }
+    }
+}
+
+// the diode linearized at one junction voltage: i = current + conductance * (v - voltage)
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Linearization {
+    // across the terminals, including the series resistance
+    pub voltage: f64,
+    pub current: f64,
+    pub conductance: f64,
+}
+
+impl DiodeModel {
+    pub fn validate(&self) -> Result<(), String> {
+        if !(self.saturation_current > 0.0
+            && self.emission_coefficient > 0.0
+            && self.thermal_voltage > 0.0)
+        {
+            return Err("Diode needs a positive Is, n and Vt".to_string());
+        }
+        if !(self.series_resistance >= 0.0 && self.series_resistance.is_finite()) {
+            return Err("Diode has an invalid series resistance".to_string());
+        }
+        Ok(())
+    }
+
+    // n * Vt
+    fn slope(&self) -> f64 {
+        self.emission_coefficient * self.thermal_voltage
+    }
+
+    // above this junction voltage the current grows too fast to take full Newton steps
+    pub fn critical_voltage(&self) -> f64 {
+        self.slope() * (self.slope() / (SQRT_2 * self.saturation_current)).ln()
+    }
+
+    // current and conductance of the junction, including GMIN
+    pub fn junction(&self, junction_voltage: f64) -> (f64, f64) {
+        let exponential = (junction_voltage / self.slope()).exp();
+        (
+            self.saturation_current * (exponential - 1.0) + GMIN * junction_voltage,
+            self.saturation_current / self.slope() * exponential + GMIN,
+        )
+    }
+
+    // the series resistance divides the junction conductance: g / (1 + Rs * g)
+    pub fn linearize(&self, junction_voltage: f64) -> Linearization {
+        let (current, conductance) = self.junction(junction_voltage);
+        Linearization {
+            voltage: junction_voltage + self.series_resistance * current,
+            current,
+            conductance: conductance / (1.0 + self.series_resistance * conductance),
+        }
+    }
+
+    // the junction voltage for the next Newton iteration from the solved terminal voltage,
+    // limited and with the current of the linear model at that voltage
+    pub fn next_junction_voltage(&self, voltage: f64, junction_voltage: f64) -> (f64, bool, f64) {
+        let linearization = self.linearize(junction_voltage);
+        let current =
+            linearization.current + linearization.conductance * (voltage - linearization.voltage);
+        let (next, limited) = limit_junction_voltage(
+            voltage - self.series_resistance * current,
+            junction_voltage,
+            self.slope(),
+            self.critical_voltage(),
+        );
+        (next, limited, current)
+    }
+}
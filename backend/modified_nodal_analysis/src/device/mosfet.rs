@@ -0,0 +1,243 @@
+use crate::analysis::newton::{BiasUpdate, NewtonOptions};
+use crate::analysis::Bias;
+use crate::device::diode::GMIN;
+use crate::device::{Companion, Polarity, SmallSignal};
+
+// Shichman-Hodges (SPICE level 1) square law, the PMOS is the NMOS with every voltage and current
+// negated, drain and source swap roles when vds < 0
+// terminals: drain, gate, source, bulk
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct MosfetModel {
+    pub polarity: Polarity,
+    // VTO (V), negative for an enhancement PMOS like in SPICE
+    pub threshold_voltage: f64,
+    // KP = μ * Cox (A/V²)
+    pub transconductance: f64,
+    // W and L (m)
+    pub width: f64,
+    pub length: f64,
+    // λ (1/V)
+    pub channel_length_modulation: f64,
+    // γ (√V)
+    pub body_effect: f64,
+    // 2φF (V)
+    pub surface_potential: f64,
+}
+
+impl Default for MosfetModel {
+    // the SPICE defaults
+    fn default() -> Self {
+        Self {
+            polarity: Polarity::N,
+            threshold_voltage: 0.0,
+            transconductance: 2e-5,
+            width: 1e-4,
+            length: 1e-4,
+            channel_length_modulation: 0.0,
+            body_effect: 0.0,
+            surface_potential: 0.6,
+        }
+    }
+}
+
+// the drain current of the NMOS equivalent at one bias and its derivatives by (vgs, vds, vbs)
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct MosfetOperatingPoint {
+    pub drain_current: f64,
+    pub conductances: [f64; 3],
+}
+
+impl MosfetModel {
+    pub fn validate(&self) -> Result<(), String> {
+        if !(self.transconductance > 0.0 && self.width > 0.0 && self.length > 0.0) {
+            return Err("MOSFET needs a positive KP, W and L".to_string());
+        }
+        if !(self.channel_length_modulation >= 0.0
+            && self.body_effect >= 0.0
+            && self.surface_potential > 0.0)
+        {
+            return Err("MOSFET has an invalid λ, γ or 2φF".to_string());
+        }
+        Ok(())
+    }
+
+    // (vgs, vds, vbs) of the NMOS equivalent from the drain, gate, source and bulk voltages
+    pub fn terminal_voltages(&self, voltages: &[f64]) -> [f64; 3] {
+        let sign = self.polarity.sign();
+        [
+            sign * (voltages[1] - voltages[2]),
+            sign * (voltages[0] - voltages[2]),
+            sign * (voltages[3] - voltages[2]),
+        ]
+    }
+
+    // the forward mode vds >= 0: drain current, gm, gds and gmbs
+    fn forward(&self, gate_source: f64, drain_source: f64, bulk_source: f64) -> [f64; 4] {
+        let sign = self.polarity.sign();
+        // the body effect raises the threshold with a reverse biased bulk
+        let (threshold, threshold_derivative) = if self.body_effect > 0.0 {
+            let root = (self.surface_potential - bulk_source).max(0.0).sqrt();
+            let derivative = if root > 0.0 {
+                -self.body_effect / (2.0 * root)
+            } else {
+                0.0
+            };
+            (
+                sign * self.threshold_voltage
+                    + self.body_effect * (root - self.surface_potential.sqrt()),
+                derivative,
+            )
+        } else {
+            (sign * self.threshold_voltage, 0.0)
+        };
+        let beta = self.transconductance * self.width / self.length;
+        let overdrive = gate_source - threshold;
+        let lambda = self.channel_length_modulation;
+        let modulation = 1.0 + lambda * drain_source;
+        let (current, gm, gds) = if overdrive <= 0.0 {
+            (0.0, 0.0, 0.0)
+        } else if drain_source < overdrive {
+            let channel = (overdrive - drain_source / 2.0) * drain_source;
+            (
+                beta * channel * modulation,
+                beta * drain_source * modulation,
+                beta * (overdrive - drain_source) * modulation + beta * channel * lambda,
+            )
+        } else {
+            let saturation = overdrive * overdrive / 2.0;
+            (
+                beta * saturation * modulation,
+                beta * overdrive * modulation,
+                beta * saturation * lambda,
+            )
+        };
+        // GMIN keeps a cut off channel from floating the drain
+        [
+            current + GMIN * drain_source,
+            gm,
+            gds + GMIN,
+            -gm * threshold_derivative,
+        ]
+    }
+
+    pub fn evaluate(
+        &self,
+        gate_source: f64,
+        drain_source: f64,
+        bulk_source: f64,
+    ) -> MosfetOperatingPoint {
+        if drain_source >= 0.0 {
+            let [current, gm, gds, gmbs] = self.forward(gate_source, drain_source, bulk_source);
+            return MosfetOperatingPoint {
+                drain_current: current,
+                conductances: [gm, gds, gmbs],
+            };
+        }
+        // the source acts as the drain: i = -f(vgd, vsd, vbd)
+        let [current, gm, gds, gmbs] = self.forward(
+            gate_source - drain_source,
+            -drain_source,
+            bulk_source - drain_source,
+        );
+        MosfetOperatingPoint {
+            drain_current: -current,
+            conductances: [-gm, gm + gds + gmbs, -gmbs],
+        }
+    }
+
+    // drain current into the device and out of the source as a function of
+    // v(gate) - v(source), v(drain) - v(source) and v(bulk) - v(source)
+    pub fn companion(&self, bias: Bias) -> Companion<3> {
+        let point = self.evaluate(bias[0], bias[1], bias[2]);
+        let linear: f64 = point
+            .conductances
+            .iter()
+            .zip(bias)
+            .map(|(conductance, voltage)| conductance * voltage)
+            .sum();
+        Companion {
+            conductances: point.conductances,
+            current: self.polarity.sign() * (point.drain_current - linear),
+        }
+    }
+
+    pub fn small_signal(&self, bias: Bias) -> SmallSignal {
+        let point = self.evaluate(bias[0], bias[1], bias[2]);
+        SmallSignal {
+            gm: point.conductances[0],
+            gds: point.conductances[1],
+            r_pi: f64::INFINITY,
+        }
+    }
+
+    // the square law has no exponential to keep in range, the step is never limited
+    pub fn next_bias(&self, voltages: &[f64], bias: Bias, options: &NewtonOptions) -> BiasUpdate {
+        let next = self.terminal_voltages(voltages);
+        let point = self.evaluate(bias[0], bias[1], bias[2]);
+        let predicted = point.drain_current
+            + (0..3)
+                .map(|index| point.conductances[index] * (next[index] - bias[index]))
+                .sum::<f64>();
+        let exact = self.evaluate(next[0], next[1], next[2]).drain_current;
+        BiasUpdate {
+            bias: next,
+            limited: false,
+            converged: options.current_converged(predicted, exact),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_regions() {
+        let model = MosfetModel {
+            threshold_voltage: 1.0,
+            channel_length_modulation: 0.01,
+            body_effect: 0.5,
+            ..Default::default()
+        };
+        let beta = 2e-5;
+        // cut off only leaks through GMIN
+        let point = model.evaluate(0.5, 2.0, 0.0);
+        assert!((point.drain_current - 2.0 * GMIN).abs() < 1e-20);
+        // saturation: beta / 2 * (vgs - vt)² * (1 + λ vds)
+        let point = model.evaluate(3.0, 5.0, 0.0);
+        assert!((point.drain_current - (beta * 2.0 * 1.05 + 5.0 * GMIN)).abs() < 1e-15);
+        // swapping drain and source negates the current
+        let reverse = model.evaluate(3.0 - 5.0, -5.0, -5.0);
+        assert!((reverse.drain_current + point.drain_current).abs() < 1e-15);
+
+        // the derivatives match finite differences in both modes and every region
+        let delta = 1e-7;
+        for bias in [
+            [3.0, 0.5, -1.0],
+            [3.0, 5.0, -1.0],
+            [0.5, 2.0, 0.0],
+            [3.0, -0.5, 0.0],
+            [1.0, -5.0, -2.0],
+        ] {
+            let point = model.evaluate(bias[0], bias[1], bias[2]);
+            for index in 0..3 {
+                let mut shifted = bias;
+                shifted[index] += delta;
+                let finite = (model
+                    .evaluate(shifted[0], shifted[1], shifted[2])
+                    .drain_current
+                    - point.drain_current)
+                    / delta;
+                let derivative = point.conductances[index];
+                assert!(
+                    (derivative - finite).abs() <= 1e-4 * derivative.abs() + 1e-9,
+                    "{:?} {} {} {}",
+                    bias,
+                    index,
+                    derivative,
+                    finite
+                );
+            }
+        }
+    }
+}
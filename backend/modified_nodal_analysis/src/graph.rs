@@ -1,3 +1,4 @@
-pub mod adjacency_matrix;
 pub mod component;
 pub mod node;
+pub mod terminal;
+pub mod terminal_graph;
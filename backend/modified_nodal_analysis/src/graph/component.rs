@@ -1,21 +1,687 @@
 use num::complex::Complex64;
+use std::f64::consts::PI;
+use std::fmt;
+use std::str::FromStr;
+use symbolic_manipulation::polynomial::{Monomial, Polynomial};
+use symbolic_manipulation::rational::Rational;
+use symbolic_manipulation::Symbol;
+
+use crate::analysis::newton::{BiasUpdate, NewtonOptions};
+use crate::analysis::symbolic::{laplace_variable, Domain, SymbolicSystem};
+use crate::analysis::transient::Integration;
+use crate::analysis::{Analysis, Bias, ComponentState};
+use crate::device::bjt::BjtModel;
+use crate::device::diode::DiodeModel;
+use crate::device::mosfet::MosfetModel;
+use crate::device::{Polarity, SmallSignal};
+use crate::mna::MnaSystem;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct ComponentId(pub usize);
+
+impl fmt::Display for ComponentId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+// the value passed along with the kind is the resistance (Ω), capacitance (F), inductance (H),
+// DC source voltage (V), DC source current (A), the gain of a controlled source or the open-loop
+// gain of a finite op-amp, the small signal phasor of a source is set separately
+#[derive(Clone, Debug, PartialEq)]
+pub enum ComponentKind {
+    Resistor,
+    Capacitor,
+    Inductor,
+    // the current flows from the positive (first) terminal through the source to the negative one
+    VoltageSource,
+    CurrentSource,
+    // controlled sources with the output between the first two terminals, the voltage controlled
+    // ones sense v(third) - v(fourth), the current controlled ones the branch current of their
+    // controlling source
+    // v = gain * v(control)
+    Vcvs,
+    // i = gain * v(control), the transconductance in S
+    Vccs,
+    // v = gain * i(control), the transresistance in Ω
+    Ccvs,
+    // i = gain * i(control)
+    Cccs,
+    // terminals: output, non-inverting and inverting input, the output is referenced to ground
+    // ideal (nullor): no input current and no voltage between the inputs, the value is ignored
+    OpAmp,
+    // v(output) = A * (v(+) - v(-)) with A = gain / (1 + jω * gain / (2π * gain_bandwidth)),
+    // the gain bandwidth product (Hz) only limits the AC analysis and is infinite if None
+    FiniteOpAmp { gain_bandwidth: Option<f64> },
+    // anode and cathode, the value is ignored
+    Diode(DiodeModel),
+    // collector, base and emitter, the value is ignored
+    Bjt(BjtModel),
+    // drain, gate, source and bulk, the value is ignored
+    Mosfet(MosfetModel),
+}
+
+impl ComponentKind {
+    // prefix of generated component names, the SPICE element letter
+    pub fn symbol(&self) -> &'static str {
+        match self {
+            ComponentKind::Resistor => "R",
+            ComponentKind::Capacitor => "C",
+            ComponentKind::Inductor => "L",
+            ComponentKind::VoltageSource => "V",
+            ComponentKind::CurrentSource => "I",
+            ComponentKind::Vcvs => "E",
+            ComponentKind::Vccs => "G",
+            ComponentKind::Ccvs => "H",
+            ComponentKind::Cccs => "F",
+            ComponentKind::OpAmp | ComponentKind::FiniteOpAmp { .. } => "U",
+            ComponentKind::Diode(_) => "D",
+            ComponentKind::Bjt(_) => "Q",
+            ComponentKind::Mosfet(_) => "M",
+        }
+    }
+
+    pub fn terminal_count(&self) -> usize {
+        match self {
+            ComponentKind::Vcvs | ComponentKind::Vccs | ComponentKind::Mosfet(_) => 4,
+            ComponentKind::OpAmp | ComponentKind::FiniteOpAmp { .. } | ComponentKind::Bjt(_) => 3,
+            _ => 2,
+        }
+    }
+
+    // components that need their branch current as an additional unknown in the MNA system
+    pub fn has_branch(&self) -> bool {
+        matches!(
+            self,
+            ComponentKind::Inductor
+                | ComponentKind::VoltageSource
+                | ComponentKind::Vcvs
+                | ComponentKind::Ccvs
+                | ComponentKind::OpAmp
+                | ComponentKind::FiniteOpAmp { .. }
+        )
+    }
+
+    // sources controlled by the branch current of another component
+    pub fn is_current_controlled(&self) -> bool {
+        matches!(self, ComponentKind::Ccvs | ComponentKind::Cccs)
+    }
+
+    // components whose stamp depends on the bias, they need Newton-Raphson iterations
+    pub fn is_nonlinear(&self) -> bool {
+        matches!(
+            self,
+            ComponentKind::Diode(_) | ComponentKind::Bjt(_) | ComponentKind::Mosfet(_)
+        )
+    }
+
+    // small signal parameters of a transistor linearized at the bias
+    pub fn small_signal(&self, bias: Bias) -> Option<SmallSignal> {
+        match self {
+            ComponentKind::Bjt(model) => Some(model.small_signal(bias)),
+            ComponentKind::Mosfet(model) => Some(model.small_signal(bias)),
+            _ => None,
+        }
+    }
+
+    // the bias of the next Newton iteration from the solved terminal voltages of this one,
+    // None for linear components
+    pub fn next_bias(
+        &self,
+        voltages: &[f64],
+        bias: Bias,
+        options: &NewtonOptions,
+    ) -> Option<BiasUpdate> {
+        match self {
+            ComponentKind::Diode(model) => {
+                let (junction_voltage, limited, current) =
+                    model.next_junction_voltage(voltages[0] - voltages[1], bias[0]);
+                // the linear model has to predict the current of the diode
+                let (exact, _) = model.junction(junction_voltage);
+                Some(BiasUpdate {
+                    bias: [junction_voltage, 0.0, 0.0],
+                    limited,
+                    converged: options.current_converged(current, exact),
+                })
+            }
+            ComponentKind::Bjt(model) => Some(model.next_bias(voltages, bias, options)),
+            ComponentKind::Mosfet(model) => Some(model.next_bias(voltages, bias, options)),
+            _ => None,
+        }
+    }
+
+    // stamps the model of the analysis, terminals are the unknown indices (None = ground)
+    pub fn stamp(
+        &self,
+        system: &mut MnaSystem,
+        terminals: &[Option<usize>],
+        branch: Option<usize>,
+        value: Complex64,
+        analysis: Analysis,
+        state: &ComponentState,
+    ) -> Result<(), String> {
+        let (positive, negative) = (terminals[0], terminals[1]);
+        let zero = Complex64::new(0.0, 0.0);
+        match self {
+            ComponentKind::Resistor => {
+                if value == zero {
+                    return Err("Resistor has zero resistance".to_string());
+                }
+                system.stamp_admittance(positive, negative, value.inv());
+            }
+            ComponentKind::Capacitor => match analysis {
+                // open circuit
+                Analysis::Dc => {}
+                Analysis::Ac { omega } => {
+                    system.stamp_admittance(positive, negative, Complex64::new(0.0, omega) * value);
+                }
+                // the initial voltage is forced by a voltage source
+                Analysis::Initial => {
+                    if let Some(voltage) = state.initial_condition {
+                        let branch = branch.ok_or("Capacitor has no branch")?;
+                        system.stamp_voltage_source(branch, positive, negative, voltage);
+                    }
+                }
+                // companion model: conductance in parallel to a current source
+                Analysis::Transient { step, method } => {
+                    let (conductance, current) = match method {
+                        Integration::BackwardEuler => {
+                            let conductance = value / step;
+                            (conductance, -conductance * state.voltage)
+                        }
+                        Integration::Trapezoidal => {
+                            let conductance = 2.0 * value / step;
+                            (conductance, -conductance * state.voltage - state.current)
+                        }
+                    };
+                    system.stamp_admittance(positive, negative, conductance);
+                    system.stamp_current_source(positive, negative, current);
+                }
+            },
+            // v+ - v- = jωL * i, a short circuit at DC
+            ComponentKind::Inductor => {
+                let branch = branch.ok_or("Inductor has no branch")?;
+                match analysis {
+                    Analysis::Dc => {
+                        system.stamp_voltage_source(branch, positive, negative, zero);
+                    }
+                    Analysis::Ac { omega } => {
+                        system.stamp_voltage_source(branch, positive, negative, zero);
+                        system.stamp_branch_impedance(branch, Complex64::new(0.0, omega) * value);
+                    }
+                    Analysis::Initial => match state.initial_condition {
+                        Some(current) => {
+                            system.stamp_current_branch(branch, positive, negative, current)
+                        }
+                        None => system.stamp_voltage_source(branch, positive, negative, zero),
+                    },
+                    // companion model: resistance in series to a voltage source
+                    Analysis::Transient { step, method } => {
+                        let (impedance, voltage) = match method {
+                            Integration::BackwardEuler => {
+                                let impedance = value / step;
+                                (impedance, -impedance * state.current)
+                            }
+                            Integration::Trapezoidal => {
+                                let impedance = 2.0 * value / step;
+                                (impedance, -impedance * state.current - state.voltage)
+                            }
+                        };
+                        system.stamp_voltage_source(branch, positive, negative, voltage);
+                        system.stamp_branch_impedance(branch, impedance);
+                    }
+                }
+            }
+            ComponentKind::VoltageSource => {
+                let branch = branch.ok_or("Voltage source has no branch")?;
+                system.stamp_voltage_source(branch, positive, negative, value);
+            }
+            ComponentKind::CurrentSource => {
+                system.stamp_current_source(positive, negative, value);
+            }
+            ComponentKind::Vcvs => {
+                let branch = branch.ok_or("Controlled source has no branch")?;
+                system.stamp_voltage_source(branch, positive, negative, zero);
+                system.stamp_voltage_gain(branch, terminals[2], terminals[3], value);
+            }
+            ComponentKind::Vccs => {
+                system.stamp_transadmittance(positive, negative, terminals[2], terminals[3], value);
+            }
+            ComponentKind::Ccvs => {
+                let branch = branch.ok_or("Controlled source has no branch")?;
+                let control = state
+                    .controlling_branch
+                    .ok_or("Controlling source has no branch")?;
+                system.stamp_voltage_source(branch, positive, negative, zero);
+                system.stamp_transimpedance(branch, control, value);
+            }
+            ComponentKind::Cccs => {
+                let control = state
+                    .controlling_branch
+                    .ok_or("Controlling source has no branch")?;
+                system.stamp_current_gain(positive, negative, control, value);
+            }
+            ComponentKind::OpAmp => {
+                let branch = branch.ok_or("Op-amp has no branch")?;
+                system.stamp_op_amp(branch, terminals[0], terminals[1], terminals[2], zero);
+            }
+            ComponentKind::FiniteOpAmp { gain_bandwidth } => {
+                let branch = branch.ok_or("Op-amp has no branch")?;
+                if value == zero {
+                    return Err("Op-amp has zero gain".to_string());
+                }
+                let mut inverse_gain = value.inv();
+                if let (Analysis::Ac { omega }, Some(gain_bandwidth)) = (analysis, gain_bandwidth) {
+                    if *gain_bandwidth <= 0.0 {
+                        return Err(format!(
+                            "Invalid gain bandwidth product {} Hz",
+                            gain_bandwidth
+                        ));
+                    }
+                    inverse_gain += Complex64::new(0.0, omega / (2.0 * PI * gain_bandwidth));
+                }
+                system.stamp_op_amp(
+                    branch,
+                    terminals[0],
+                    terminals[1],
+                    terminals[2],
+                    inverse_gain,
+                );
+            }
+            // companion model: the conductance at the bias in parallel to a current source,
+            // only the conductance in the small signal analysis
+            ComponentKind::Diode(model) => {
+                model.validate()?;
+                let linearization = model.linearize(state.bias[0]);
+                let conductance = Complex64::new(linearization.conductance, 0.0);
+                system.stamp_admittance(positive, negative, conductance);
+                if !matches!(analysis, Analysis::Ac { .. }) {
+                    let current =
+                        linearization.current - linearization.conductance * linearization.voltage;
+                    system.stamp_current_source(positive, negative, current.into());
+                }
+            }
+            // companion model: voltage controlled current sources for the conductances and
+            // independent ones for the rest of the currents at the bias
+            ComponentKind::Bjt(model) => {
+                model.validate()?;
+                let (collector, base, emitter) = (terminals[0], terminals[1], terminals[2]);
+                let small_signal = matches!(analysis, Analysis::Ac { .. });
+                for (terminal, companion) in [collector, base]
+                    .into_iter()
+                    .zip(model.companions(state.bias))
+                {
+                    let [base_emitter, base_collector] = companion.conductances;
+                    system.stamp_transadmittance(
+                        terminal,
+                        emitter,
+                        base,
+                        emitter,
+                        base_emitter.into(),
+                    );
+                    system.stamp_transadmittance(
+                        terminal,
+                        emitter,
+                        base,
+                        collector,
+                        base_collector.into(),
+                    );
+                    if !small_signal {
+                        system.stamp_current_source(terminal, emitter, companion.current.into());
+                    }
+                }
+            }
+            ComponentKind::Mosfet(model) => {
+                model.validate()?;
+                let (drain, gate, source, bulk) =
+                    (terminals[0], terminals[1], terminals[2], terminals[3]);
+                let companion = model.companion(state.bias);
+                for (control, conductance) in
+                    [gate, drain, bulk].into_iter().zip(companion.conductances)
+                {
+                    system.stamp_transadmittance(
+                        drain,
+                        source,
+                        control,
+                        source,
+                        conductance.into(),
+                    );
+                }
+                if !matches!(analysis, Analysis::Ac { .. }) {
+                    system.stamp_current_source(drain, source, companion.current.into());
+                }
+            }
+        }
+        Ok(())
+    }
+
+    // stamps the model with the symbol of the component (R1, C1, ...) as its value
+    pub fn stamp_symbolic(
+        &self,
+        system: &mut SymbolicSystem,
+        terminals: &[Option<usize>],
+        branch: Option<usize>,
+        controlling_branch: Option<usize>,
+        symbol: &Symbol,
+        domain: Domain,
+    ) -> Result<(), String> {
+        let (positive, negative) = (terminals[0], terminals[1]);
+        let value = Polynomial::symbol(symbol.clone());
+        let s = Polynomial::symbol(laplace_variable());
+        match self {
+            ComponentKind::Resistor => {
+                let admittance = Polynomial::term(
+                    Rational::from_integer(1.into()),
+                    Monomial::symbol(symbol.clone(), -1),
+                );
+                system.stamp_admittance(positive, negative, &admittance);
+            }
+            ComponentKind::Capacitor => {
+                if domain == Domain::Laplace {
+                    let admittance = s.checked_mul(&value).ok_or("Exponent out of range")?;
+                    system.stamp_admittance(positive, negative, &admittance);
+                }
+            }
+            ComponentKind::Inductor => {
+                let branch = branch.ok_or("Inductor has no branch")?;
+                system.stamp_voltage_source(branch, positive, negative, &Polynomial::zero());
+                if domain == Domain::Laplace {
+                    let impedance = s.checked_mul(&value).ok_or("Exponent out of range")?;
+                    system.stamp_branch_impedance(branch, &impedance);
+                }
+            }
+            ComponentKind::VoltageSource => {
+                let branch = branch.ok_or("Voltage source has no branch")?;
+                system.stamp_voltage_source(branch, positive, negative, &value);
+            }
+            ComponentKind::CurrentSource => {
+                system.stamp_current_source(positive, negative, &value);
+            }
+            ComponentKind::Vcvs => {
+                let branch = branch.ok_or("Controlled source has no branch")?;
+                system.stamp_voltage_source(branch, positive, negative, &Polynomial::zero());
+                system.stamp_voltage_gain(branch, terminals[2], terminals[3], &value);
+            }
+            ComponentKind::Vccs => {
+                system.stamp_transadmittance(
+                    positive,
+                    negative,
+                    terminals[2],
+                    terminals[3],
+                    &value,
+                );
+            }
+            ComponentKind::Ccvs => {
+                let branch = branch.ok_or("Controlled source has no branch")?;
+                let control = controlling_branch.ok_or("Controlling source has no branch")?;
+                system.stamp_voltage_source(branch, positive, negative, &Polynomial::zero());
+                system.stamp_transimpedance(branch, control, &value);
+            }
+            ComponentKind::Cccs => {
+                let control = controlling_branch.ok_or("Controlling source has no branch")?;
+                system.stamp_current_gain(positive, negative, control, &value);
+            }
+            ComponentKind::OpAmp => {
+                let branch = branch.ok_or("Op-amp has no branch")?;
+                system.stamp_op_amp(
+                    branch,
+                    terminals[0],
+                    terminals[1],
+                    terminals[2],
+                    &Polynomial::zero(),
+                );
+            }
+            // the symbolic model keeps the open-loop gain but not the gain bandwidth product
+            ComponentKind::FiniteOpAmp { .. } => {
+                let branch = branch.ok_or("Op-amp has no branch")?;
+                let inverse_gain = Polynomial::term(
+                    Rational::from_integer(1.into()),
+                    Monomial::symbol(symbol.clone(), -1),
+                );
+                system.stamp_op_amp(
+                    branch,
+                    terminals[0],
+                    terminals[1],
+                    terminals[2],
+                    &inverse_gain,
+                );
+            }
+            ComponentKind::Diode(_) => return Err("Diodes have no symbolic model".to_string()),
+            ComponentKind::Bjt(_) | ComponentKind::Mosfet(_) => {
+                return Err("Transistors have no symbolic model".to_string())
+            }
+        }
+        Ok(())
+    }
+
+    // current into the positive terminal, the collector or the drain, from the solved terminal
+    // voltages or the branch current, control is the controlling current of current controlled
+    // sources
+    pub fn current(
+        &self,
+        voltages: &[Complex64],
+        control: Option<Complex64>,
+        branch_current: Option<Complex64>,
+        value: Complex64,
+        analysis: Analysis,
+        state: &ComponentState,
+    ) -> Complex64 {
+        let zero = Complex64::new(0.0, 0.0);
+        let voltage = voltages[0] - voltages[1];
+        let small_signal = matches!(analysis, Analysis::Ac { .. });
+        match self {
+            ComponentKind::Resistor => voltage / value,
+            ComponentKind::Capacitor => match analysis {
+                Analysis::Dc => zero,
+                Analysis::Ac { omega } => voltage * Complex64::new(0.0, omega) * value,
+                Analysis::Initial => branch_current.unwrap_or(zero),
+                Analysis::Transient { step, method } => match method {
+                    Integration::BackwardEuler => value / step * (voltage - state.voltage),
+                    Integration::Trapezoidal => {
+                        2.0 * value / step * (voltage - state.voltage) - state.current
+                    }
+                },
+            },
+            ComponentKind::Inductor
+            | ComponentKind::VoltageSource
+            | ComponentKind::Vcvs
+            | ComponentKind::Ccvs
+            | ComponentKind::OpAmp
+            | ComponentKind::FiniteOpAmp { .. } => branch_current.unwrap_or(zero),
+            ComponentKind::CurrentSource => value,
+            ComponentKind::Vccs => value * (voltages[2] - voltages[3]),
+            ComponentKind::Cccs => value * control.unwrap_or(zero),
+            ComponentKind::Diode(model) => {
+                let linearization = model.linearize(state.bias[0]);
+                match analysis {
+                    Analysis::Ac { .. } => voltage * linearization.conductance,
+                    _ => (linearization.current
+                        + linearization.conductance * (voltage.re - linearization.voltage))
+                        .into(),
+                }
+            }
+            ComponentKind::Bjt(model) => {
+                let [collector, _] = model.companions(state.bias);
+                collector.current_at(
+                    [voltages[1] - voltages[2], voltages[1] - voltages[0]],
+                    small_signal,
+                )
+            }
+            ComponentKind::Mosfet(model) => model.companion(state.bias).current_at(
+                [
+                    voltages[1] - voltages[2],
+                    voltages[0] - voltages[2],
+                    voltages[3] - voltages[2],
+                ],
+                small_signal,
+            ),
+        }
+    }
+}
+
+// the names match the ElectricItemType of the frontend
+impl fmt::Display for ComponentKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ComponentKind::Resistor => write!(f, "resistor"),
+            ComponentKind::Capacitor => write!(f, "capacitor"),
+            ComponentKind::Inductor => write!(f, "inductor"),
+            ComponentKind::VoltageSource => write!(f, "voltage_source"),
+            ComponentKind::CurrentSource => write!(f, "current_source"),
+            ComponentKind::Vcvs => write!(f, "vcvs"),
+            ComponentKind::Vccs => write!(f, "vccs"),
+            ComponentKind::Ccvs => write!(f, "ccvs"),
+            ComponentKind::Cccs => write!(f, "cccs"),
+            ComponentKind::OpAmp => write!(f, "op_amp"),
+            ComponentKind::FiniteOpAmp { .. } => write!(f, "finite_op_amp"),
+            ComponentKind::Diode(_) => write!(f, "diode"),
+            ComponentKind::Bjt(model) => match model.polarity {
+                Polarity::N => write!(f, "npn"),
+                Polarity::P => write!(f, "pnp"),
+            },
+            ComponentKind::Mosfet(model) => match model.polarity {
+                Polarity::N => write!(f, "nmos"),
+                Polarity::P => write!(f, "pmos"),
+            },
+        }
+    }
+}
+
+impl FromStr for ComponentKind {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "resistor" => Ok(ComponentKind::Resistor),
+            "capacitor" => Ok(ComponentKind::Capacitor),
+            "inductor" => Ok(ComponentKind::Inductor),
+            "voltage_source" => Ok(ComponentKind::VoltageSource),
+            "current_source" => Ok(ComponentKind::CurrentSource),
+            "vcvs" => Ok(ComponentKind::Vcvs),
+            "vccs" => Ok(ComponentKind::Vccs),
+            "ccvs" => Ok(ComponentKind::Ccvs),
+            "cccs" => Ok(ComponentKind::Cccs),
+            "op_amp" => Ok(ComponentKind::OpAmp),
+            "finite_op_amp" => Ok(ComponentKind::FiniteOpAmp {
+                gain_bandwidth: None,
+            }),
+            "diode" => Ok(ComponentKind::Diode(DiodeModel::default())),
+            "npn" | "pnp" => Ok(ComponentKind::Bjt(BjtModel {
+                polarity: if s == "npn" { Polarity::N } else { Polarity::P },
+                ..Default::default()
+            })),
+            "nmos" | "pmos" => Ok(ComponentKind::Mosfet(MosfetModel {
+                polarity: if s == "nmos" {
+                    Polarity::N
+                } else {
+                    Polarity::P
+                },
+                ..Default::default()
+            })),
+            _ => Err(format!("Unknown component kind {}", s)),
+        }
+    }
+}
 
 pub struct Component {
     name: String,
-    terminal_ids: [usize; 2],
+    kind: ComponentKind,
+    // output terminals first, then the controlling ones
+    terminal_ids: Vec<usize>,
     value: Complex64,
+    // phasor of an independent source in the AC analysis, 0 makes it an AC ground or open
+    ac: Complex64,
+    // capacitor voltage or inductor current at the start of a transient analysis
+    initial_condition: Option<Complex64>,
+    // component whose branch current controls a current controlled source
+    controlling_source: Option<ComponentId>,
 }
 
 impl Component {
-    pub fn new(name: String, value: Complex64) -> Self {
+    pub fn new(
+        name: String,
+        kind: ComponentKind,
+        terminal_ids: Vec<usize>,
+        value: Complex64,
+    ) -> Self {
         Self {
             name,
-            terminal_ids: [0, 0],
+            kind,
+            terminal_ids,
             value,
+            ac: Complex64::new(0.0, 0.0),
+            initial_condition: None,
+            controlling_source: None,
+        }
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub(crate) fn set_name(&mut self, name: String) {
+        self.name = name;
+    }
+
+    pub fn kind(&self) -> &ComponentKind {
+        &self.kind
+    }
+
+    pub fn ids(&self) -> &[usize] {
+        &self.terminal_ids
+    }
+
+    pub fn value(&self) -> Complex64 {
+        self.value
+    }
+
+    pub fn ac(&self) -> Complex64 {
+        self.ac
+    }
+
+    pub fn set_ac(&mut self, value: Complex64) -> Result<(), String> {
+        if !matches!(
+            self.kind,
+            ComponentKind::VoltageSource | ComponentKind::CurrentSource
+        ) {
+            return Err(format!("{} is not an independent source", self.name));
+        }
+        self.ac = value;
+        Ok(())
+    }
+
+    pub fn initial_condition(&self) -> Option<Complex64> {
+        self.initial_condition
+    }
+
+    pub fn set_initial_condition(&mut self, value: Option<Complex64>) -> Result<(), String> {
+        if !matches!(
+            self.kind,
+            ComponentKind::Capacitor | ComponentKind::Inductor
+        ) {
+            return Err(format!("{} cannot have an initial condition", self.name));
+        }
+        self.initial_condition = value;
+        Ok(())
+    }
+
+    pub fn controlling_source(&self) -> Option<ComponentId> {
+        self.controlling_source
+    }
+
+    pub(crate) fn set_controlling_source(&mut self, source: ComponentId) -> Result<(), String> {
+        if !self.kind.is_current_controlled() {
+            return Err(format!("{} is not a current controlled source", self.name));
         }
+        self.controlling_source = Some(source);
+        Ok(())
     }
 
-    pub fn ids(&self) -> [usize; 2] {
-        self.terminal_ids
+    // whether the component needs its branch current as an unknown in this analysis
+    pub fn has_branch(&self, analysis: Analysis) -> bool {
+        self.kind.has_branch()
+            || (self.kind == ComponentKind::Capacitor
+                && analysis == Analysis::Initial
+                && self.initial_condition.is_some())
     }
 }
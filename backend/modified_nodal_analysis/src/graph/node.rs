@@ -1,6 +1,8 @@
 use num::complex::Complex64;
 use std::collections::HashSet;
 use std::fmt;
+
+#[derive(Clone)]
 pub struct Node {
     id: usize,
     terminal_ids: HashSet<usize>,
@@ -16,7 +18,23 @@ impl Node {
         }
     }
 
-    fn is_attached(&self, terminal_id: &usize) -> bool {
+    pub fn id(&self) -> usize {
+        self.id
+    }
+
+    pub fn terminal_ids(&self) -> &HashSet<usize> {
+        &self.terminal_ids
+    }
+
+    pub fn voltage(&self) -> Option<Complex64> {
+        self.voltage
+    }
+
+    pub fn set_voltage(&mut self, voltage: Complex64) {
+        self.voltage = Some(voltage);
+    }
+
+    pub fn is_attached(&self, terminal_id: &usize) -> bool {
         self.terminal_ids.contains(terminal_id)
     }
 }
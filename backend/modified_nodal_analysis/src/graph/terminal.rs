@@ -0,0 +1,57 @@
+use std::collections::BTreeSet;
+use std::collections::HashMap;
+
+use crate::graph::component::ComponentId;
+
+// hands out terminal ids (starting at 1) and remembers which pin of which component owns them
+pub struct TerminalAllocator {
+    next_id: usize,
+    // freed ids are reused, the smallest first
+    free_ids: BTreeSet<usize>,
+    owners: HashMap<usize, (ComponentId, usize)>,
+}
+
+impl TerminalAllocator {
+    pub fn new() -> Self {
+        Self {
+            next_id: 1,
+            free_ids: BTreeSet::new(),
+            owners: HashMap::new(),
+        }
+    }
+
+    pub fn allocate(&mut self, component_id: ComponentId, pin: usize) -> usize {
+        let id = match self.free_ids.pop_first() {
+            Some(id) => id,
+            None => {
+                self.next_id += 1;
+                self.next_id - 1
+            }
+        };
+        self.owners.insert(id, (component_id, pin));
+        id
+    }
+
+    pub fn free(&mut self, id: &usize) -> Result<(), String> {
+        if self.owners.remove(id).is_none() {
+            return Err(format!("Terminal {} is not allocated", id));
+        }
+        self.free_ids.insert(*id);
+        Ok(())
+    }
+
+    // (component, pin index) of an allocated terminal
+    pub fn owner(&self, id: &usize) -> Option<(ComponentId, usize)> {
+        self.owners.get(id).copied()
+    }
+
+    pub fn is_allocated(&self, id: &usize) -> bool {
+        self.owners.contains_key(id)
+    }
+}
+
+impl Default for TerminalAllocator {
+    fn default() -> Self {
+        Self::new()
+    }
+}
@@ -0,0 +1,203 @@
+use std::collections::HashMap;
+use std::collections::HashSet;
+use std::fmt;
+
+use crate::graph::node::Node;
+
+// connectivity of the terminals, the connected sets (nodes) are kept in a disjoint-set forest
+// connections are stored as well, so a removal can split the one set it touches
+pub struct TerminalGraph {
+    // indexed by terminal id, None for ids that are not registered
+    parents: Vec<Option<usize>>,
+    sizes: Vec<usize>,
+    connections: HashMap<usize, HashSet<usize>>,
+}
+
+impl TerminalGraph {
+    pub fn new() -> Self {
+        Self {
+            parents: Vec::new(),
+            sizes: Vec::new(),
+            connections: HashMap::new(),
+        }
+    }
+
+    pub fn contains(&self, id: &usize) -> bool {
+        matches!(self.parents.get(*id), Some(Some(_)))
+    }
+
+    pub fn add_terminal(&mut self, id: usize) -> Result<(), String> {
+        if self.contains(&id) {
+            return Err(format!("Terminal {} already exists", id));
+        }
+        if id >= self.parents.len() {
+            self.parents.resize(id + 1, None);
+            self.sizes.resize(id + 1, 0);
+        }
+        self.parents[id] = Some(id);
+        self.sizes[id] = 1;
+        Ok(())
+    }
+
+    pub fn remove_terminal(&mut self, id: &usize) {
+        if !self.contains(id) {
+            return;
+        }
+        self.parents[*id] = None;
+        self.sizes[*id] = 0;
+        // an unconnected terminal is a set of its own, otherwise the rest of its set may fall
+        // apart into one set per former neighbour
+        let connected = self.connections.remove(id).unwrap_or_default();
+        for other in connected.iter() {
+            if let Some(other_connections) = self.connections.get_mut(other) {
+                other_connections.remove(id);
+            }
+        }
+        let mut relabeled = HashSet::new();
+        for other in connected {
+            if !relabeled.contains(&other) {
+                relabeled.extend(self.relabel(other));
+            }
+        }
+    }
+
+    pub fn add_connection(&mut self, id_1: &usize, id_2: &usize) -> Result<(), String> {
+        self.check_pair(id_1, id_2)?;
+        self.connections.entry(*id_1).or_default().insert(*id_2);
+        self.connections.entry(*id_2).or_default().insert(*id_1);
+        self.union(*id_1, *id_2);
+        Ok(())
+    }
+
+    pub fn remove_connection(&mut self, id_1: &usize, id_2: &usize) -> Result<(), String> {
+        self.check_pair(id_1, id_2)?;
+        let removed = self
+            .connections
+            .get_mut(id_1)
+            .map(|connected| connected.remove(id_2))
+            .unwrap_or(false);
+        if !removed {
+            return Err(format!("Terminals {} and {} are not connected", id_1, id_2));
+        }
+        if let Some(connected) = self.connections.get_mut(id_2) {
+            connected.remove(id_1);
+        }
+        // only the set of the two terminals is searched, it splits if id_2 is out of reach now
+        if !self.relabel(*id_1).contains(id_2) {
+            self.relabel(*id_2);
+        }
+        Ok(())
+    }
+
+    pub fn is_connected(&mut self, id_1: &usize, id_2: &usize) -> bool {
+        if !self.contains(id_1) || !self.contains(id_2) {
+            return false;
+        }
+        self.find(*id_1) == self.find(*id_2)
+    }
+
+    // one node per connected set (isolated terminals included), numbered by their smallest terminal id
+    pub fn create_nodes(&mut self) -> Vec<Node> {
+        let mut node_indices = HashMap::<usize, usize>::new();
+        let mut terminal_sets: Vec<HashSet<usize>> = Vec::new();
+        for id in 0..self.parents.len() {
+            if !self.contains(&id) {
+                continue;
+            }
+            let root = self.find(id);
+            let index = *node_indices.entry(root).or_insert_with(|| {
+                terminal_sets.push(HashSet::new());
+                terminal_sets.len() - 1
+            });
+            terminal_sets[index].insert(id);
+        }
+        terminal_sets
+            .into_iter()
+            .enumerate()
+            .map(|(index, terminal_ids)| Node::new(index, terminal_ids))
+            .collect()
+    }
+
+    fn check_pair(&self, id_1: &usize, id_2: &usize) -> Result<(), String> {
+        for id in [id_1, id_2] {
+            if !self.contains(id) {
+                return Err(format!("Terminal {} does not exist", id));
+            }
+        }
+        if id_1 == id_2 {
+            return Err(format!("Terminal {} cannot be connected to itself", id_1));
+        }
+        Ok(())
+    }
+
+    // iterative with path halving, so deep trees cannot overflow the stack
+    fn find(&mut self, mut id: usize) -> usize {
+        while let Some(parent) = self.parents[id] {
+            if parent == id {
+                break;
+            }
+            let grandparent = self.parents[parent].unwrap_or(parent);
+            self.parents[id] = Some(grandparent);
+            id = grandparent;
+        }
+        id
+    }
+
+    fn union(&mut self, id_1: usize, id_2: usize) {
+        let mut root_1 = self.find(id_1);
+        let mut root_2 = self.find(id_2);
+        if root_1 == root_2 {
+            return;
+        }
+        // union by size keeps the trees flat
+        if self.sizes[root_1] < self.sizes[root_2] {
+            std::mem::swap(&mut root_1, &mut root_2);
+        }
+        self.parents[root_2] = Some(root_1);
+        self.sizes[root_1] += self.sizes[root_2];
+    }
+
+    // the terminals reachable from id become one set with id as its root, iterative like find
+    fn relabel(&mut self, id: usize) -> HashSet<usize> {
+        let mut reached = HashSet::from([id]);
+        let mut pending = vec![id];
+        while let Some(current) = pending.pop() {
+            self.parents[current] = Some(id);
+            for other in self.connections.get(&current).into_iter().flatten() {
+                if reached.insert(*other) {
+                    pending.push(*other);
+                }
+            }
+        }
+        self.sizes[id] = reached.len();
+        reached
+    }
+}
+
+impl Default for TerminalGraph {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl fmt::Display for TerminalGraph {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for id in 0..self.parents.len() {
+            if !self.contains(&id) {
+                continue;
+            }
+            write!(f, "Terminal {:3}:", id)?;
+            let mut connected = self
+                .connections
+                .get(&id)
+                .map(|connected| connected.iter().copied().collect::<Vec<usize>>())
+                .unwrap_or_default();
+            connected.sort_unstable();
+            for other in connected {
+                write!(f, " {:3}", other)?;
+            }
+            writeln!(f)?;
+        }
+        Ok(())
+    }
+}
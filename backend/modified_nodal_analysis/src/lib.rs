@@ -1,63 +1,754 @@
 use core::fmt;
 use num::complex::Complex64;
+use std::collections::BTreeMap;
+use std::collections::HashMap;
 
-mod graph;
-use crate::graph::adjacency_matrix::AdjacencyMatrix;
-use crate::graph::component::Component;
+pub mod analysis;
+pub mod device;
+pub mod graph;
+pub mod matrix;
+pub mod mna;
+pub mod netlist;
+use crate::graph::component::{Component, ComponentId, ComponentKind};
+use crate::graph::node::Node;
+use crate::graph::terminal::TerminalAllocator;
+use crate::graph::terminal_graph::TerminalGraph;
 
-struct Circuit {
-    components: Vec<Component>,
-    adjacency_matrix: AdjacencyMatrix,
+pub struct Circuit {
+    components: BTreeMap<ComponentId, Component>,
+    next_component_id: usize,
+    terminals: TerminalAllocator,
+    name_counters: HashMap<&'static str, usize>,
+    // component of each upper case name, names are unique ignoring the case
+    names: HashMap<String, ComponentId>,
+    terminal_graph: TerminalGraph,
+    nodes: Vec<Node>,
+    ground: Option<usize>,
 }
 
 impl Circuit {
     pub fn new() -> Self {
         Self {
-            components: Vec::new(),
-            adjacency_matrix: AdjacencyMatrix::new(),
+            components: BTreeMap::new(),
+            next_component_id: 0,
+            terminals: TerminalAllocator::new(),
+            name_counters: HashMap::new(),
+            names: HashMap::new(),
+            terminal_graph: TerminalGraph::new(),
+            nodes: Vec::new(),
+            ground: None,
         }
     }
 
-    pub fn add_component(&mut self) {
-        let component = Component::new(String::from("R"), Complex64::new(1.0, 0.0));
-        for id in component.ids().iter() {
-            self.adjacency_matrix.add_terminal(*id);
+    // the name is generated from the kind (R1, R2, C1, ...), skipping names already in use
+    pub fn add_component(&mut self, kind: ComponentKind, value: Complex64) -> ComponentId {
+        let id = ComponentId(self.next_component_id);
+        self.next_component_id += 1;
+        let name = loop {
+            let counter = self.name_counters.entry(kind.symbol()).or_insert(0);
+            *counter += 1;
+            let name = format!("{}{}", kind.symbol(), counter);
+            if self.find_component(&name).is_none() {
+                break name;
+            }
+        };
+        let terminal_ids = (0..kind.terminal_count())
+            .map(|pin| self.terminals.allocate(id, pin))
+            .collect::<Vec<usize>>();
+        for terminal_id in terminal_ids.iter() {
+            // freshly allocated ids are not in the terminal graph yet, each starts as a set of
+            // its own in the disjoint-set forest
+            self.terminal_graph
+                .add_terminal(*terminal_id)
+                .expect("terminal allocator handed out a registered id");
         }
-        self.components.push(component);
+        self.names.insert(name.to_ascii_uppercase(), id);
+        self.components
+            .insert(id, Component::new(name, kind, terminal_ids, value));
+        id
+    }
+
+    // removes the component together with all connections of its terminals
+    pub fn remove_component(&mut self, id: ComponentId) -> Result<Component, String> {
+        let component = self
+            .components
+            .remove(&id)
+            .ok_or(format!("Component {} does not exist", id))?;
+        self.names.remove(&component.name().to_ascii_uppercase());
+        for terminal_id in component.ids().iter() {
+            self.terminal_graph.remove_terminal(terminal_id);
+            self.terminals.free(terminal_id)?;
+            if self.ground == Some(*terminal_id) {
+                self.ground = None;
+            }
+        }
+        Ok(component)
+    }
+
+    pub fn component(&self, id: ComponentId) -> Option<&Component> {
+        self.components.get(&id)
+    }
+
+    pub fn find_component(&self, name: &str) -> Option<ComponentId> {
+        self.names.get(&name.to_ascii_uppercase()).copied()
+    }
+
+    // names are unique, ignoring the case like SPICE does
+    pub fn rename_component(&mut self, id: ComponentId, name: &str) -> Result<(), String> {
+        if name.is_empty() {
+            return Err("Component names cannot be empty".to_string());
+        }
+        if let Some(other) = self.find_component(name).filter(|other| *other != id) {
+            return Err(format!(
+                "Name {} is already used by component {}",
+                name, other
+            ));
+        }
+        let component = self
+            .components
+            .get_mut(&id)
+            .ok_or(format!("Component {} does not exist", id))?;
+        self.names.remove(&component.name().to_ascii_uppercase());
+        self.names.insert(name.to_ascii_uppercase(), id);
+        component.set_name(name.to_string());
+        Ok(())
+    }
+
+    // the branch current of the source controls a CCVS or CCCS, the source needs a branch
+    // like voltage sources and inductors have
+    pub fn set_controlling_source(
+        &mut self,
+        id: ComponentId,
+        source: ComponentId,
+    ) -> Result<(), String> {
+        let controlling = self
+            .components
+            .get(&source)
+            .ok_or(format!("Component {} does not exist", source))?;
+        if !controlling.kind().has_branch() {
+            return Err(format!(
+                "{} does not carry a branch current",
+                controlling.name()
+            ));
+        }
+        self.components
+            .get_mut(&id)
+            .ok_or(format!("Component {} does not exist", id))?
+            .set_controlling_source(source)
+    }
+
+    // the component and pin index a terminal belongs to
+    pub fn terminal_owner(&self, terminal_id: &usize) -> Option<(ComponentId, usize)> {
+        self.terminals.owner(terminal_id)
+    }
+
+    pub fn connect(&mut self, id_1: &usize, id_2: &usize) -> Result<(), String> {
+        self.terminal_graph.add_connection(id_1, id_2)
+    }
+
+    pub fn disconnect(&mut self, id_1: &usize, id_2: &usize) -> Result<(), String> {
+        self.terminal_graph.remove_connection(id_1, id_2)
+    }
+
+    // the node of this terminal is the 0 V reference of all analyses
+    pub fn set_ground(&mut self, terminal_id: usize) {
+        self.ground = Some(terminal_id);
+    }
+
+    // nodes of the last analysis, including their voltages
+    pub fn nodes(&self) -> &[Node] {
+        &self.nodes
+    }
+
+    // node id of a terminal in the last analysis
+    pub fn node_of(&self, terminal_id: &usize) -> Option<usize> {
+        self.nodes
+            .iter()
+            .find(|node| node.is_attached(terminal_id))
+            .map(|node| node.id())
+    }
+
+    pub fn voltage(&self, terminal_id: &usize) -> Option<Complex64> {
+        self.nodes
+            .iter()
+            .find(|node| node.is_attached(terminal_id))
+            .and_then(|node| node.voltage())
+    }
+}
+
+impl Default for Circuit {
+    fn default() -> Self {
+        Self::new()
     }
 }
 
 impl fmt::Display for Circuit {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        write!(f, "{:}", self.adjacency_matrix)
+        write!(f, "{:}", self.terminal_graph)
     }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::analysis::ac::SweepKind;
+    use crate::analysis::transient::Integration;
+
+    fn resistance(value: f64) -> Complex64 {
+        Complex64::new(value, 0.0)
+    }
+
+    fn assert_close(actual: Option<Complex64>, expected: f64) {
+        let actual = actual.expect("value was not computed");
+        assert!(
+            (actual - Complex64::new(expected, 0.0)).norm() < 1e-9,
+            "expected {}, got {}",
+            expected,
+            actual
+        );
+    }
 
     #[test]
     fn test_construction() {
         let mut circuit = Circuit::new();
 
-        circuit.add_component();
-        circuit.add_component();
-        circuit.add_component();
-        circuit.add_component();
+        circuit.add_component(ComponentKind::Resistor, resistance(1.0));
+        circuit.add_component(ComponentKind::Resistor, resistance(1.0));
+        circuit.add_component(ComponentKind::Resistor, resistance(1.0));
+        circuit.add_component(ComponentKind::Resistor, resistance(1.0));
 
-        // circuit.adjacency_matrix.remove_terminal(&3);
+        // circuit.terminal_graph.remove_terminal(&3);
 
-        circuit.adjacency_matrix.add_connection(&1, &2);
-        circuit.adjacency_matrix.add_connection(&1, &5);
-        circuit.adjacency_matrix.add_connection(&3, &7);
-        circuit.adjacency_matrix.add_connection(&7, &8);
+        circuit.connect(&1, &2).unwrap();
+        circuit.connect(&1, &5).unwrap();
+        circuit.connect(&3, &7).unwrap();
+        circuit.connect(&7, &8).unwrap();
 
         println!("{}", circuit);
 
-        let nodes = circuit.adjacency_matrix.create_nodes();
+        let nodes = circuit.terminal_graph.create_nodes();
         for node in nodes.iter() {
             println!("{}", node);
         }
     }
+
+    #[test]
+    fn test_incremental_connectivity() {
+        let mut circuit = Circuit::new();
+        for _ in 0..4 {
+            circuit.add_component(ComponentKind::Resistor, resistance(1.0));
+        }
+        circuit.connect(&1, &2).unwrap();
+        circuit.connect(&1, &5).unwrap();
+        circuit.connect(&3, &7).unwrap();
+        circuit.connect(&7, &8).unwrap();
+
+        let nodes = circuit.terminal_graph.create_nodes();
+        // numbered by the smallest terminal, unconnected terminals are nodes of their own
+        let sets = nodes
+            .iter()
+            .map(|node| {
+                let mut ids = node.terminal_ids().iter().copied().collect::<Vec<usize>>();
+                ids.sort_unstable();
+                (node.id(), ids)
+            })
+            .collect::<Vec<(usize, Vec<usize>)>>();
+        assert_eq!(
+            sets,
+            vec![
+                (0, vec![1, 2, 5]),
+                (1, vec![3, 7, 8]),
+                (2, vec![4]),
+                (3, vec![6]),
+            ]
+        );
+
+        // removing a connection splits the node again
+        circuit.disconnect(&1, &5).unwrap();
+        assert!(circuit.disconnect(&1, &5).is_err());
+        assert!(!circuit.terminal_graph.is_connected(&1, &5));
+        assert!(circuit.terminal_graph.is_connected(&3, &8));
+        circuit.connect(&5, &2).unwrap();
+        assert!(circuit.terminal_graph.is_connected(&1, &5));
+        assert_eq!(circuit.terminal_graph.create_nodes().len(), 4);
+        // a removal that leaves another path keeps the node
+        circuit.connect(&1, &5).unwrap();
+        circuit.disconnect(&5, &2).unwrap();
+        assert!(circuit.terminal_graph.is_connected(&2, &5));
+        // removing the middle terminal of 3 - 7 - 8 splits its node in two
+        circuit.terminal_graph.remove_terminal(&7);
+        assert!(!circuit.terminal_graph.is_connected(&3, &8));
+        assert_eq!(circuit.terminal_graph.create_nodes().len(), 5);
+    }
+
+    fn resistor_chain(length: usize) -> Circuit {
+        let mut circuit = Circuit::new();
+        for _ in 0..length {
+            circuit.add_component(ComponentKind::Resistor, resistance(1.0));
+        }
+        // the second terminal of every resistor is connected to the first one of the next
+        for index in 1..length {
+            circuit.connect(&(2 * index), &(2 * index + 1)).unwrap();
+        }
+        circuit
+    }
+
+    #[test]
+    fn test_large_construction() {
+        // 100k terminals
+        let mut circuit = resistor_chain(50_000);
+        assert_eq!(circuit.terminal_graph.create_nodes().len(), 50_001);
+
+        // one long node through all terminals would overflow a recursive search
+        for index in 1..50_000 {
+            circuit.connect(&(2 * index - 1), &(2 * index + 1)).unwrap();
+        }
+        assert!(circuit.terminal_graph.is_connected(&1, &99_999));
+        // only the last terminal of the chain is left on its own
+        assert_eq!(circuit.terminal_graph.create_nodes().len(), 2);
+    }
+
+    // cargo test --release -- --ignored --nocapture bench_construction
+    #[test]
+    #[ignore]
+    fn bench_construction() {
+        for length in [1_000, 10_000, 50_000, 500_000] {
+            let start = std::time::Instant::now();
+            let mut circuit = resistor_chain(length);
+            let built = start.elapsed();
+            let nodes = circuit.terminal_graph.create_nodes();
+            let numbered = start.elapsed() - built;
+            circuit.disconnect(&2, &3).unwrap();
+            let split = circuit.terminal_graph.create_nodes();
+            let renumbered = start.elapsed() - built - numbered;
+            assert_eq!(nodes.len() + 1, split.len());
+            println!(
+                "{:7} terminals: construction {:?}, nodes {:?}, nodes after removal {:?}",
+                2 * length,
+                built,
+                numbered,
+                renumbered
+            );
+        }
+    }
+
+    #[test]
+    fn test_voltage_divider() {
+        let mut circuit = Circuit::new();
+        let source = circuit.add_component(ComponentKind::VoltageSource, resistance(10.0)); // terminals 1 (+), 2 (-)
+        circuit.add_component(ComponentKind::Resistor, resistance(1000.0)); // terminals 3, 4
+        circuit.add_component(ComponentKind::Resistor, resistance(4000.0)); // terminals 5, 6
+
+        circuit.connect(&1, &3).unwrap();
+        circuit.connect(&4, &5).unwrap();
+        circuit.connect(&6, &2).unwrap();
+        circuit.set_ground(2);
+
+        let solution = circuit.solve_dc().unwrap();
+
+        assert_close(circuit.voltage(&1), 10.0);
+        assert_close(circuit.voltage(&4), 8.0);
+        assert_close(circuit.voltage(&2), 0.0);
+        // the source delivers 2 mA, which flows out of its positive terminal
+        assert_close(solution.current(source), -0.002);
+    }
+
+    #[test]
+    fn test_current_source() {
+        let mut circuit = Circuit::new();
+        circuit.add_component(ComponentKind::CurrentSource, resistance(0.5)); // terminals 1, 2
+        circuit.add_component(ComponentKind::Resistor, resistance(100.0)); // terminals 3, 4
+        circuit.add_component(ComponentKind::Inductor, resistance(1e-3)); // terminals 5, 6
+        circuit.add_component(ComponentKind::Capacitor, resistance(1e-6)); // terminals 7, 8
+
+        // the current flows from 1 through the source to 2, then through the inductor and R1 back
+        circuit.connect(&2, &5).unwrap();
+        circuit.connect(&6, &3).unwrap();
+        circuit.connect(&4, &1).unwrap();
+        circuit.connect(&7, &3).unwrap();
+        circuit.connect(&8, &1).unwrap();
+        circuit.set_ground(1);
+
+        circuit.solve_dc().unwrap();
+
+        assert_close(circuit.voltage(&3), 50.0);
+        assert_close(circuit.voltage(&2), 50.0);
+        assert_close(circuit.voltage(&7), 50.0);
+        assert!(circuit.nodes().iter().all(|node| node.voltage().is_some()));
+    }
+
+    #[test]
+    fn test_component_kinds() {
+        let mut circuit = Circuit::new();
+        let r1 = circuit.add_component(ComponentKind::Resistor, resistance(1.0));
+        let c1 = circuit.add_component(ComponentKind::Capacitor, resistance(1e-6));
+        let r2 = circuit.add_component(ComponentKind::Resistor, resistance(0.0));
+
+        assert_eq!(circuit.component(r1).unwrap().name(), "R1");
+        assert_eq!(circuit.component(c1).unwrap().name(), "C1");
+        assert_eq!(circuit.component(r2).unwrap().name(), "R2");
+        // the frontend item types map onto the kinds
+        for item_type in ["resistor", "capacitor", "inductor", "voltage_source"] {
+            let kind = item_type.parse::<ComponentKind>().unwrap();
+            assert_eq!(kind.to_string(), item_type);
+        }
+        assert!("transistor".parse::<ComponentKind>().is_err());
+        // zero resistance cannot be stamped
+        assert!(circuit.solve_dc().is_err());
+    }
+
+    #[test]
+    fn test_floating_subcircuit() {
+        // R2 (5, 6), R3 (7, 8) and R4 (9, 10) form a loop without a path to ground, rounding
+        // leaves its pivot slightly off zero
+        let mut circuit = Circuit::new();
+        circuit.add_component(ComponentKind::VoltageSource, resistance(1.0)); // 1, 2
+        circuit.add_component(ComponentKind::Resistor, resistance(1.0)); // 3, 4
+        for value in [0.3, 0.7, 1.1] {
+            circuit.add_component(ComponentKind::Resistor, resistance(value));
+        }
+        for (a, b) in [(1, 3), (4, 2), (6, 7), (8, 9), (10, 5)] {
+            circuit.connect(&a, &b).unwrap();
+        }
+        circuit.set_ground(2);
+        let error = circuit.solve_dc().err().unwrap();
+        assert!(error.starts_with("Matrix is singular"), "{}", error);
+    }
+
+    #[test]
+    fn test_unique_names() {
+        let mut circuit = Circuit::new();
+        let r1 = circuit.add_component(ComponentKind::Resistor, resistance(1.0));
+        circuit.rename_component(r1, "r2").unwrap();
+        let r2 = circuit.add_component(ComponentKind::Resistor, resistance(1.0));
+        assert_eq!(circuit.component(r2).unwrap().name(), "R3");
+        assert!(circuit.rename_component(r2, "R2").is_err());
+
+        // generated names do not collide with the ones of an imported netlist
+        let netlist = crate::netlist::Netlist::parse("t\nR2 a 0 1k\nR1 a 0 1k\n").unwrap();
+        let (mut circuit, _) = netlist.to_circuit().unwrap();
+        let r3 = circuit.add_component(ComponentKind::Resistor, resistance(1.0));
+        assert_eq!(circuit.component(r3).unwrap().name(), "R4");
+        assert_eq!(circuit.find_component("r4"), Some(r3));
+        assert_eq!(circuit.find_component("R2"), Some(ComponentId(0)));
+    }
+
+    #[test]
+    fn test_unique_terminals() {
+        let mut circuit = Circuit::new();
+        let r1 = circuit.add_component(ComponentKind::Resistor, resistance(1.0));
+        let r2 = circuit.add_component(ComponentKind::Resistor, resistance(1.0));
+
+        assert_eq!(circuit.component(r1).unwrap().ids(), [1, 2]);
+        assert_eq!(circuit.component(r2).unwrap().ids(), [3, 4]);
+        assert_eq!(circuit.terminal_owner(&2), Some((r1, 1)));
+        assert_eq!(circuit.terminal_owner(&3), Some((r2, 0)));
+        assert_eq!(circuit.terminal_owner(&5), None);
+    }
+
+    #[test]
+    fn test_remove_component() {
+        let mut circuit = Circuit::new();
+        circuit.add_component(ComponentKind::VoltageSource, resistance(6.0)); // 1, 2
+        let r1 = circuit.add_component(ComponentKind::Resistor, resistance(1.0)); // 3, 4
+        circuit.add_component(ComponentKind::Resistor, resistance(2.0)); // 5, 6
+        circuit.connect(&1, &3).unwrap();
+        circuit.connect(&4, &5).unwrap();
+        circuit.connect(&6, &2).unwrap();
+        circuit.set_ground(2);
+
+        circuit.remove_component(r1).unwrap();
+        assert!(circuit.remove_component(r1).is_err());
+        assert_eq!(circuit.terminal_owner(&3), None);
+        assert!(circuit.connect(&1, &3).is_err());
+
+        // the freed terminals are reused without the old connections
+        let r3 = circuit.add_component(ComponentKind::Resistor, resistance(4.0));
+        assert_ne!(r3, r1);
+        assert_eq!(circuit.component(r3).unwrap().ids(), [3, 4]);
+        assert_eq!(circuit.component(r3).unwrap().name(), "R3");
+        assert_eq!(circuit.terminal_owner(&4), Some((r3, 1)));
+        assert!(circuit
+            .terminal_graph
+            .create_nodes()
+            .iter()
+            .filter(|node| node.is_attached(&3) || node.is_attached(&4))
+            .all(|node| node.terminal_ids().len() == 1));
+        circuit.connect(&1, &3).unwrap();
+        circuit.connect(&4, &5).unwrap();
+
+        circuit.solve_dc().unwrap();
+        assert_close(circuit.voltage(&5), 2.0);
+    }
+
+    #[test]
+    fn test_controlled_sources() {
+        // inverting amplifier: E1 amplifies v(0) - v(minus), R1 from the input to minus,
+        // R2 from minus to the output
+        let mut circuit = Circuit::new();
+        let source = circuit.add_component(ComponentKind::VoltageSource, resistance(1.0)); // 1, 2
+        let e1 = circuit.add_component(ComponentKind::Vcvs, resistance(1e5)); // 3, 4, 5, 6
+        circuit.add_component(ComponentKind::Resistor, resistance(1e3)); // 7, 8
+        circuit.add_component(ComponentKind::Resistor, resistance(1e4)); // 9, 10
+        assert_eq!(circuit.component(e1).unwrap().ids(), [3, 4, 5, 6]);
+        assert_eq!(circuit.component(e1).unwrap().name(), "E1");
+        for (a, b) in [(1, 7), (8, 6), (6, 9), (10, 3), (4, 2), (5, 2)] {
+            circuit.connect(&a, &b).unwrap();
+        }
+        circuit.set_ground(2);
+        circuit.solve_dc().unwrap();
+        // v = -A * R2 / (R1 + R2 + A * R1), -R2 / R1 for an infinite gain
+        let gain = -1e5 * 1e4 / (1e3 + 1e4 + 1e5 * 1e3);
+        assert_close(circuit.voltage(&3), gain);
+
+        // H1 turns the current of the source into a voltage
+        let h1 = circuit.add_component(ComponentKind::Ccvs, resistance(1e3)); // 11, 12
+        circuit.connect(&12, &2).unwrap();
+        assert!(circuit.solve_dc().is_err());
+        let r1 = circuit.find_component("R1").unwrap();
+        assert!(circuit.set_controlling_source(h1, r1).is_err());
+        assert!(circuit.set_controlling_source(r1, source).is_err());
+        circuit.set_controlling_source(h1, source).unwrap();
+        let solution = circuit.solve_dc().unwrap();
+        let input_current = solution.current(source).unwrap().re;
+        assert_close(circuit.voltage(&11), 1e3 * input_current);
+        // the current into the inverting input is (1 V - v(minus)) / R1
+        assert_close(
+            Some(Complex64::new(-input_current, 0.0)),
+            (1.0 - circuit.voltage(&6).unwrap().re) / 1e3,
+        );
+        circuit.remove_component(source).unwrap();
+        assert!(circuit.solve_dc().is_err());
+    }
+
+    // V1 (1, 2) drives U1 (3 output, 4 +, 5 -) through R1 (6, 7) with the feedback resistor R2 (8, 9)
+    fn inverting_amplifier(kind: ComponentKind, gain: f64) -> Circuit {
+        let mut circuit = Circuit::new();
+        circuit.add_component(ComponentKind::VoltageSource, resistance(1.0));
+        circuit.add_component(kind, resistance(gain));
+        circuit.add_component(ComponentKind::Resistor, resistance(1e3));
+        circuit.add_component(ComponentKind::Resistor, resistance(1e4));
+        for (a, b) in [(1, 6), (7, 5), (5, 8), (9, 3), (4, 2)] {
+            circuit.connect(&a, &b).unwrap();
+        }
+        circuit.set_ground(2);
+        circuit
+    }
+
+    #[test]
+    fn test_ideal_op_amp() {
+        let mut circuit = inverting_amplifier(ComponentKind::OpAmp, 0.0);
+        let u1 = circuit.find_component("U1").unwrap();
+        assert_eq!(circuit.component(u1).unwrap().ids(), [3, 4, 5]);
+        let solution = circuit.solve_dc().unwrap();
+        assert_close(circuit.voltage(&3), -10.0);
+        // virtual ground at the inverting input, the output sinks the 1 mA through R1 and R2
+        assert_close(circuit.voltage(&5), 0.0);
+        assert_close(solution.current(u1), 1e-3);
+
+        // non-inverting amplifier: the source moves to the + input and R1 to ground
+        circuit.disconnect(&4, &2).unwrap();
+        circuit.disconnect(&1, &6).unwrap();
+        circuit.connect(&1, &4).unwrap();
+        circuit.connect(&6, &2).unwrap();
+        circuit.solve_dc().unwrap();
+        assert_close(circuit.voltage(&3), 11.0);
+    }
+
+    #[test]
+    fn test_finite_op_amp() {
+        let gain = 1e5;
+        let kind = ComponentKind::FiniteOpAmp {
+            gain_bandwidth: Some(1e6),
+        };
+        let mut circuit = inverting_amplifier(kind, gain);
+        circuit.solve_dc().unwrap();
+        assert_close(circuit.voltage(&3), -gain * 1e4 / (1e3 + 1e4 + gain * 1e3));
+        let v1 = circuit.find_component("V1").unwrap();
+        circuit.set_ac(v1, 1.0, 0.0).unwrap();
+
+        // the closed-loop bandwidth is the gain bandwidth product over the noise gain 1 + R2 / R1
+        let corner = 1e6 / 11.0;
+        let sweep = circuit
+            .ac_sweep(corner / 100.0, corner, 2, SweepKind::Decade)
+            .unwrap();
+        let output = circuit.node_of(&3).unwrap();
+        for (frequency, voltage) in sweep.frequencies().iter().zip(sweep.node_voltages(output)) {
+            let open_loop = gain / Complex64::new(1.0, frequency * gain / 1e6);
+            let expected = -open_loop * 1e4 / (1e3 + 1e4 + open_loop * 1e3);
+            assert!((voltage - expected).norm() < 1e-9);
+        }
+        let bode = sweep.bode(output);
+        assert!((bode[0].magnitude_db - 20.0).abs() < 1e-2);
+        assert!((bode[4].magnitude_db - 20.0 + 3.0103).abs() < 1e-2);
+
+        let kind = ComponentKind::FiniteOpAmp {
+            gain_bandwidth: None,
+        };
+        let mut circuit = inverting_amplifier(kind, 0.0);
+        assert!(circuit.solve_dc().is_err());
+    }
+
+    #[test]
+    fn test_sweep_frequencies() {
+        let linear = SweepKind::Linear.frequencies(0.0, 100.0, 5).unwrap();
+        assert_eq!(linear, vec![0.0, 25.0, 50.0, 75.0, 100.0]);
+        let decade = SweepKind::Decade.frequencies(10.0, 1000.0, 2).unwrap();
+        assert_eq!(decade.len(), 5);
+        assert!((decade[1] - 10.0 * 10.0_f64.sqrt()).abs() < 1e-9);
+        assert!((decade[4] - 1000.0).abs() < 1e-9);
+        let octave = SweepKind::Octave.frequencies(100.0, 800.0, 1).unwrap();
+        assert_eq!(octave.len(), 4);
+        assert!(SweepKind::Decade.frequencies(0.0, 10.0, 1).is_err());
+        assert!(SweepKind::Linear.frequencies(10.0, 1.0, 3).is_err());
+    }
+
+    #[test]
+    fn test_ac_low_pass() {
+        let mut circuit = Circuit::new();
+        // the DC value only sets the operating point
+        let source = circuit.add_component(ComponentKind::VoltageSource, resistance(5.0)); // 1, 2
+        let r1 = circuit.add_component(ComponentKind::Resistor, resistance(1000.0)); // 3, 4
+        let c1 = circuit.add_component(ComponentKind::Capacitor, resistance(1e-6)); // 5, 6
+        circuit.connect(&1, &3).unwrap();
+        circuit.connect(&4, &5).unwrap();
+        circuit.connect(&6, &2).unwrap();
+        circuit.set_ground(2);
+        circuit.set_ac(source, 1.0, 0.0).unwrap();
+        // only independent sources have a phasor
+        assert!(circuit.set_ac(r1, 1.0, 0.0).is_err());
+
+        let cutoff = 1.0 / (2.0 * std::f64::consts::PI * 1000.0 * 1e-6);
+        let sweep = circuit
+            .ac_sweep(cutoff / 100.0, cutoff * 100.0, 10, SweepKind::Decade)
+            .unwrap();
+        assert_eq!(sweep.frequencies().len(), 41);
+        let input = circuit.node_of(&1).unwrap();
+        let output = circuit.node_of(&4).unwrap();
+        let bode = sweep.bode_transfer(input, output);
+
+        // flat in the pass band, -3 dB and -45° at the cutoff frequency, -20 dB per decade above
+        assert!(bode[0].magnitude_db.abs() < 1e-3);
+        assert!((bode[20].frequency - cutoff).abs() < 1e-6);
+        assert!((bode[20].magnitude_db + 3.0103).abs() < 1e-3);
+        assert!((bode[20].phase_degrees + 45.0).abs() < 1e-6);
+        assert!((bode[40].magnitude_db - bode[30].magnitude_db + 20.0).abs() < 0.1);
+        assert!((sweep.bode(output)[20].magnitude_db + 3.0103).abs() < 1e-3);
+        // the phase of the source shifts every phasor
+        circuit.set_ac(source, 2.0, 90.0).unwrap();
+        let shifted = circuit
+            .ac_sweep(cutoff, cutoff, 1, SweepKind::Linear)
+            .unwrap();
+        assert!(
+            (shifted.node_voltages(output)[0] - Complex64::new(1.0, 1.0)).norm() < 1e-9,
+            "{}",
+            shifted.node_voltages(output)[0]
+        );
+
+        // resistor and capacitor carry the same current
+        for (i_r, i_c) in sweep.currents(r1).iter().zip(sweep.currents(c1)) {
+            assert!((i_r - i_c).norm() < 1e-12);
+        }
+    }
+
+    #[test]
+    fn test_ac_inductor() {
+        let mut circuit = Circuit::new();
+        let source = circuit.add_component(ComponentKind::VoltageSource, resistance(1.0)); // 1, 2
+        circuit.add_component(ComponentKind::Inductor, resistance(1e-3)); // 3, 4
+        circuit.connect(&1, &3).unwrap();
+        circuit.connect(&4, &2).unwrap();
+        circuit.set_ground(2);
+        circuit.set_ac(source, 1.0, 0.0).unwrap();
+
+        let sweep = circuit
+            .ac_sweep(1000.0, 1000.0, 1, SweepKind::Linear)
+            .unwrap();
+        // i = v / (jωL), flowing out of the positive terminal of the source
+        let omega = 2.0 * std::f64::consts::PI * 1000.0;
+        let expected = -Complex64::new(1.0, 0.0) / Complex64::new(0.0, omega * 1e-3);
+        assert!((sweep.currents(source)[0] - expected).norm() < 1e-12);
+    }
+
+    fn rc_circuit() -> (Circuit, ComponentId) {
+        let mut circuit = Circuit::new();
+        circuit.add_component(ComponentKind::VoltageSource, resistance(5.0)); // 1, 2
+        circuit.add_component(ComponentKind::Resistor, resistance(1000.0)); // 3, 4
+        let c1 = circuit.add_component(ComponentKind::Capacitor, resistance(1e-6)); // 5, 6
+        circuit.connect(&1, &3).unwrap();
+        circuit.connect(&4, &5).unwrap();
+        circuit.connect(&6, &2).unwrap();
+        circuit.set_ground(2);
+        (circuit, c1)
+    }
+
+    #[test]
+    fn test_transient_rc_charge() {
+        let tau = 1e-3;
+        for (method, tolerance) in [
+            (Integration::BackwardEuler, 2e-2),
+            (Integration::Trapezoidal, 1e-4),
+        ] {
+            let (mut circuit, c1) = rc_circuit();
+            circuit.set_initial_condition(c1, Some(0.0)).unwrap();
+            let waveforms = circuit
+                .transient_with(5.0 * tau, tau / 100.0, method)
+                .unwrap();
+            assert_eq!(waveforms.times().len(), 501);
+            assert!((waveforms.times()[500] - 5.0 * tau).abs() < 1e-15);
+
+            let output = circuit.node_of(&4).unwrap();
+            let voltages = waveforms.node_voltages(output);
+            let currents = waveforms.currents(c1);
+            for (index, time) in waveforms.times().iter().enumerate() {
+                let expected = 5.0 * (1.0 - (-time / tau).exp());
+                assert!(
+                    (voltages[index] - expected).abs() < tolerance,
+                    "{:?} at {} s: {} instead of {}",
+                    method,
+                    time,
+                    voltages[index],
+                    expected
+                );
+                let expected_current = 5.0e-3 * (-time / tau).exp();
+                assert!((currents[index] - expected_current).abs() < tolerance * 1e-3);
+            }
+        }
+    }
+
+    #[test]
+    fn test_transient_operating_point_start() {
+        // without an initial condition the capacitor starts charged
+        let (mut circuit, _) = rc_circuit();
+        let waveforms = circuit.transient(1e-3, 1e-4).unwrap();
+        let output = circuit.node_of(&4).unwrap();
+        assert!(waveforms
+            .node_voltages(output)
+            .iter()
+            .all(|voltage| (voltage - 5.0).abs() < 1e-9));
+        assert!(circuit.transient(1e-3, 0.0).is_err());
+    }
+
+    #[test]
+    fn test_transient_rl() {
+        let mut circuit = Circuit::new();
+        let source = circuit.add_component(ComponentKind::VoltageSource, resistance(1.0)); // 1, 2
+        circuit.add_component(ComponentKind::Resistor, resistance(10.0)); // 3, 4
+        let l1 = circuit.add_component(ComponentKind::Inductor, resistance(1e-2)); // 5, 6
+        circuit.connect(&1, &3).unwrap();
+        circuit.connect(&4, &5).unwrap();
+        circuit.connect(&6, &2).unwrap();
+        circuit.set_ground(2);
+        circuit.set_initial_condition(l1, Some(0.0)).unwrap();
+        // only capacitors and inductors have initial conditions
+        assert!(circuit.set_initial_condition(source, Some(1.0)).is_err());
+
+        let tau = 1e-2 / 10.0;
+        let waveforms = circuit.transient(3.0 * tau, tau / 200.0).unwrap();
+        let currents = waveforms.currents(l1);
+        for (index, time) in waveforms.times().iter().enumerate() {
+            let expected = 0.1 * (1.0 - (-time / tau).exp());
+            assert!((currents[index] - expected).abs() < 1e-5);
+        }
+    }
 }
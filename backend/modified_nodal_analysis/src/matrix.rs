@@ -0,0 +1,121 @@
+use num::complex::Complex64;
+use num::Zero;
+use std::ops::{Index, IndexMut};
+
+// pivots below this fraction of the largest entry of their column are treated as zero
+const PIVOT_TOLERANCE: f64 = 1e-12;
+
+// dense row-major matrix, the MNA systems of our schematics are small enough
+#[derive(Clone, Debug, PartialEq)]
+pub struct Matrix<T = Complex64> {
+    rows: usize,
+    columns: usize,
+    data: Vec<T>,
+}
+
+impl<T: Clone + Zero> Matrix<T> {
+    pub fn zeros(rows: usize, columns: usize) -> Self {
+        Self {
+            rows,
+            columns,
+            data: vec![T::zero(); rows * columns],
+        }
+    }
+
+    pub fn rows(&self) -> usize {
+        self.rows
+    }
+
+    pub fn columns(&self) -> usize {
+        self.columns
+    }
+
+    pub fn transpose(&self) -> Self {
+        let mut transposed = Self::zeros(self.columns, self.rows);
+        for row in 0..self.rows {
+            for column in 0..self.columns {
+                transposed[(column, row)] = self[(row, column)].clone();
+            }
+        }
+        transposed
+    }
+}
+
+impl<T> Index<(usize, usize)> for Matrix<T> {
+    type Output = T;
+
+    fn index(&self, (row, column): (usize, usize)) -> &T {
+        &self.data[row * self.columns + column]
+    }
+}
+
+impl<T> IndexMut<(usize, usize)> for Matrix<T> {
+    fn index_mut(&mut self, (row, column): (usize, usize)) -> &mut T {
+        &mut self.data[row * self.columns + column]
+    }
+}
+
+impl Matrix<Complex64> {
+    // gaussian elimination with partial pivoting, returns x for A * x = rhs
+    pub fn solve(&self, rhs: &[Complex64]) -> Result<Vec<Complex64>, String> {
+        if self.rows != self.columns || self.rows != rhs.len() {
+            return Err(format!(
+                "Cannot solve a {}x{} system with {} right hand side entries",
+                self.rows,
+                self.columns,
+                rhs.len()
+            ));
+        }
+        let n = self.rows;
+        let mut a = self.data.clone();
+        let mut x = rhs.to_vec();
+        // a pivot that is zero up to rounding relative to its column means a singular matrix
+        let tolerances = (0..n)
+            .map(|column| {
+                let largest = (0..n)
+                    .map(|row| a[row * n + column].norm())
+                    .fold(0.0, f64::max);
+                PIVOT_TOLERANCE * largest
+            })
+            .collect::<Vec<f64>>();
+        for pivot in 0..n {
+            // pick the largest remaining entry of the column to keep the elimination stable
+            let mut best = pivot;
+            for row in pivot + 1..n {
+                if a[row * n + pivot].norm() > a[best * n + pivot].norm() {
+                    best = row;
+                }
+            }
+            if a[best * n + pivot].norm() <= tolerances[pivot] {
+                return Err(format!("Matrix is singular (column {})", pivot));
+            }
+            if best != pivot {
+                for column in 0..n {
+                    a.swap(pivot * n + column, best * n + column);
+                }
+                x.swap(pivot, best);
+            }
+            for row in pivot + 1..n {
+                let factor = a[row * n + pivot] / a[pivot * n + pivot];
+                if factor.is_zero() {
+                    continue;
+                }
+                for column in pivot..n {
+                    let value = a[pivot * n + column];
+                    a[row * n + column] -= factor * value;
+                }
+                let value = x[pivot];
+                x[row] -= factor * value;
+            }
+        }
+        // back substitution
+        for row in (0..n).rev() {
+            let mut sum = x[row];
+            for column in row + 1..n {
+                sum -= a[row * n + column] * x[column];
+            }
+            x[row] = sum / a[row * n + row];
+        }
+        Ok(x)
+    }
+}
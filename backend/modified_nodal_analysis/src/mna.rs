@@ -0,0 +1,227 @@
+use num::complex::Complex64;
+
+use crate::matrix::Matrix;
+
+// the MNA system in block form (ground is not part of the unknowns):
+// | G B | | v |   | i |
+// | C D | | j | = | e |
+// v are the node voltages, j the branch currents of the voltage sources
+pub struct MnaSystem {
+    g: Matrix,
+    b: Matrix,
+    c: Matrix,
+    d: Matrix,
+    i: Vec<Complex64>,
+    e: Vec<Complex64>,
+}
+
+impl MnaSystem {
+    pub fn new(node_count: usize, branch_count: usize) -> Self {
+        Self {
+            g: Matrix::zeros(node_count, node_count),
+            b: Matrix::zeros(node_count, branch_count),
+            c: Matrix::zeros(branch_count, node_count),
+            d: Matrix::zeros(branch_count, branch_count),
+            i: vec![Complex64::new(0.0, 0.0); node_count],
+            e: vec![Complex64::new(0.0, 0.0); branch_count],
+        }
+    }
+
+    pub fn node_count(&self) -> usize {
+        self.g.rows()
+    }
+
+    pub fn branch_count(&self) -> usize {
+        self.d.rows()
+    }
+
+    // admittance between two nodes, None is the ground node
+    pub fn stamp_admittance(
+        &mut self,
+        node_1: Option<usize>,
+        node_2: Option<usize>,
+        admittance: Complex64,
+    ) {
+        if let Some(n1) = node_1 {
+            self.g[(n1, n1)] += admittance;
+        }
+        if let Some(n2) = node_2 {
+            self.g[(n2, n2)] += admittance;
+        }
+        if let (Some(n1), Some(n2)) = (node_1, node_2) {
+            self.g[(n1, n2)] -= admittance;
+            self.g[(n2, n1)] -= admittance;
+        }
+    }
+
+    // current flows from the positive node through the source to the negative node (SPICE convention)
+    pub fn stamp_current_source(
+        &mut self,
+        positive: Option<usize>,
+        negative: Option<usize>,
+        current: Complex64,
+    ) {
+        if let Some(p) = positive {
+            self.i[p] -= current;
+        }
+        if let Some(n) = negative {
+            self.i[n] += current;
+        }
+    }
+
+    // v(positive) - v(negative) = voltage, the branch current flows into the positive terminal
+    pub fn stamp_voltage_source(
+        &mut self,
+        branch: usize,
+        positive: Option<usize>,
+        negative: Option<usize>,
+        voltage: Complex64,
+    ) {
+        let one = Complex64::new(1.0, 0.0);
+        if let Some(p) = positive {
+            self.b[(p, branch)] += one;
+            self.c[(branch, p)] += one;
+        }
+        if let Some(n) = negative {
+            self.b[(n, branch)] -= one;
+            self.c[(branch, n)] -= one;
+        }
+        self.e[branch] += voltage;
+    }
+
+    // branch with a fixed current flowing from the positive through the component to the negative node
+    pub fn stamp_current_branch(
+        &mut self,
+        branch: usize,
+        positive: Option<usize>,
+        negative: Option<usize>,
+        current: Complex64,
+    ) {
+        let one = Complex64::new(1.0, 0.0);
+        if let Some(p) = positive {
+            self.b[(p, branch)] += one;
+        }
+        if let Some(n) = negative {
+            self.b[(n, branch)] -= one;
+        }
+        self.d[(branch, branch)] += one;
+        self.e[branch] += current;
+    }
+
+    // adds -z to the branch equation: v(positive) - v(negative) - z * j = e
+    pub fn stamp_branch_impedance(&mut self, branch: usize, impedance: Complex64) {
+        self.d[(branch, branch)] -= impedance;
+    }
+
+    // current gain * v(control+ - control-) from the positive through the source to the negative node
+    pub fn stamp_transadmittance(
+        &mut self,
+        positive: Option<usize>,
+        negative: Option<usize>,
+        control_positive: Option<usize>,
+        control_negative: Option<usize>,
+        gain: Complex64,
+    ) {
+        for (node, sign) in [(positive, 1.0), (negative, -1.0)] {
+            let Some(row) = node else { continue };
+            if let Some(column) = control_positive {
+                self.g[(row, column)] += sign * gain;
+            }
+            if let Some(column) = control_negative {
+                self.g[(row, column)] -= sign * gain;
+            }
+        }
+    }
+
+    // adds -gain * v(control+ - control-) to the branch equation of a voltage source
+    pub fn stamp_voltage_gain(
+        &mut self,
+        branch: usize,
+        control_positive: Option<usize>,
+        control_negative: Option<usize>,
+        gain: Complex64,
+    ) {
+        if let Some(column) = control_positive {
+            self.c[(branch, column)] -= gain;
+        }
+        if let Some(column) = control_negative {
+            self.c[(branch, column)] += gain;
+        }
+    }
+
+    // current gain * j(control) from the positive through the source to the negative node
+    pub fn stamp_current_gain(
+        &mut self,
+        positive: Option<usize>,
+        negative: Option<usize>,
+        control: usize,
+        gain: Complex64,
+    ) {
+        if let Some(p) = positive {
+            self.b[(p, control)] += gain;
+        }
+        if let Some(n) = negative {
+            self.b[(n, control)] -= gain;
+        }
+    }
+
+    // adds -z * j(control) to the branch equation of a voltage source
+    pub fn stamp_transimpedance(&mut self, branch: usize, control: usize, impedance: Complex64) {
+        self.d[(branch, control)] -= impedance;
+    }
+
+    // op-amp whose output current j into the output node holds
+    // inverse_gain * v(output) = v(input+) - v(input-), the ideal nullor has no inverse gain
+    pub fn stamp_op_amp(
+        &mut self,
+        branch: usize,
+        output: Option<usize>,
+        input_positive: Option<usize>,
+        input_negative: Option<usize>,
+        inverse_gain: Complex64,
+    ) {
+        let one = Complex64::new(1.0, 0.0);
+        if let Some(o) = output {
+            self.b[(o, branch)] += one;
+            self.c[(branch, o)] += inverse_gain;
+        }
+        if let Some(p) = input_positive {
+            self.c[(branch, p)] -= one;
+        }
+        if let Some(n) = input_negative {
+            self.c[(branch, n)] += one;
+        }
+    }
+
+    // assembles A = [G B; C D] and z = [i; e] and solves for x = [v; j]
+    pub fn solve(&self) -> Result<(Vec<Complex64>, Vec<Complex64>), String> {
+        let nodes = self.node_count();
+        let branches = self.branch_count();
+        let mut a = Matrix::zeros(nodes + branches, nodes + branches);
+        for row in 0..nodes {
+            for column in 0..nodes {
+                a[(row, column)] = self.g[(row, column)];
+            }
+            for column in 0..branches {
+                a[(row, nodes + column)] = self.b[(row, column)];
+            }
+        }
+        for row in 0..branches {
+            for column in 0..nodes {
+                a[(nodes + row, column)] = self.c[(row, column)];
+            }
+            for column in 0..branches {
+                a[(nodes + row, nodes + column)] = self.d[(row, column)];
+            }
+        }
+        let z = self
+            .i
+            .iter()
+            .chain(self.e.iter())
+            .cloned()
+            .collect::<Vec<Complex64>>();
+        let mut x = a.solve(&z)?;
+        let branch_currents = x.split_off(nodes);
+        Ok((x, branch_currents))
+    }
+}
@@ -0,0 +1,844 @@
+use num::complex::Complex64;
+use std::collections::BTreeMap;
+use std::fmt;
+
+use crate::analysis::ac::SweepKind;
+use crate::graph::component::{Component, ComponentKind};
+use crate::Circuit;
+
+// a practical subset of SPICE: the first line is the title, '*' starts a comment line,
+// ';' an inline comment and '+' continues the previous line
+#[derive(Clone, Debug, PartialEq)]
+pub struct NetlistError {
+    pub line: usize,
+    pub message: String,
+}
+
+impl NetlistError {
+    fn new(line: usize, message: String) -> Self {
+        Self { line, message }
+    }
+}
+
+impl fmt::Display for NetlistError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "line {}: {}", self.line, self.message)
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum ElementKind {
+    Resistor,
+    Capacitor,
+    Inductor,
+    VoltageSource,
+    CurrentSource,
+    // E: voltage controlled voltage source, controlled by v(nc+) - v(nc-)
+    Vcvs { controlling_nodes: [String; 2] },
+    // F: current controlled current source, controlled by the current through a voltage source
+    Cccs { controlling_source: String },
+    // G: voltage controlled current source
+    Vccs { controlling_nodes: [String; 2] },
+    // H: current controlled voltage source
+    Ccvs { controlling_source: String },
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Element {
+    pub name: String,
+    pub kind: ElementKind,
+    // positive and negative node
+    pub nodes: [String; 2],
+    pub value: Complex64,
+    // small signal phasor of a source with an AC specification
+    pub ac: Option<Complex64>,
+    pub initial_condition: Option<f64>,
+    pub line: usize,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum Command {
+    Op,
+    Ac {
+        kind: SweepKind,
+        points: usize,
+        start_hz: f64,
+        stop_hz: f64,
+    },
+    Tran {
+        t_step: f64,
+        t_stop: f64,
+    },
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Netlist {
+    pub title: String,
+    pub elements: Vec<Element>,
+    pub commands: Vec<Command>,
+}
+
+// parses a number with an optional SI suffix, trailing unit letters are ignored (10kOhm, 1uF)
+pub fn parse_value(token: &str) -> Result<f64, String> {
+    let lower = token.to_lowercase();
+    let bytes = lower.as_bytes();
+    let mut end = 0;
+    while end < bytes.len() {
+        let c = bytes[end] as char;
+        let exponent_follows = (c == 'e')
+            && end > 0
+            && bytes[end - 1].is_ascii_digit()
+            && bytes
+                .get(end + 1)
+                .map(|next| next.is_ascii_digit() || *next == b'-' || *next == b'+')
+                .unwrap_or(false);
+        let sign_of_exponent = (c == '-' || c == '+') && end > 0 && bytes[end - 1] == b'e';
+        let sign = (c == '-' || c == '+') && end == 0;
+        if c.is_ascii_digit() || c == '.' || exponent_follows || sign_of_exponent || sign {
+            end += 1;
+        } else {
+            break;
+        }
+    }
+    let number = lower[..end]
+        .parse::<f64>()
+        .map_err(|_| format!("Invalid value {}", token))?;
+    let suffix = &lower[end..];
+    let scale = if suffix.starts_with("meg") {
+        1e6
+    } else if suffix.starts_with("mil") {
+        25.4e-6
+    } else {
+        match suffix.chars().next() {
+            Some('t') => 1e12,
+            Some('g') => 1e9,
+            Some('k') => 1e3,
+            Some('m') => 1e-3,
+            Some('u') => 1e-6,
+            Some('n') => 1e-9,
+            Some('p') => 1e-12,
+            Some('f') => 1e-15,
+            Some(c) if c.is_ascii_alphabetic() => 1.0,
+            None => 1.0,
+            Some(_) => return Err(format!("Invalid value {}", token)),
+        }
+    };
+    Ok(number * scale)
+}
+
+pub fn is_ground(node: &str) -> bool {
+    node == "0" || node.eq_ignore_ascii_case("gnd")
+}
+
+// joins continuation lines, strips comments and keeps the number of the first physical line
+fn logical_lines(text: &str) -> Vec<(usize, String)> {
+    let mut lines: Vec<(usize, String)> = Vec::new();
+    for (index, line) in text.lines().enumerate() {
+        let number = index + 1;
+        if number == 1 {
+            // the title
+            lines.push((number, line.trim().to_string()));
+            continue;
+        }
+        let line = line.split(';').next().unwrap_or("").trim();
+        if line.is_empty() || line.starts_with('*') {
+            continue;
+        }
+        if let Some(continuation) = line.strip_prefix('+') {
+            if let Some((_, previous)) = lines.last_mut().filter(|(n, _)| *n != 1) {
+                previous.push(' ');
+                previous.push_str(continuation.trim());
+                continue;
+            }
+        }
+        lines.push((number, line.to_string()));
+    }
+    lines
+}
+
+impl Netlist {
+    pub fn parse(text: &str) -> Result<Netlist, NetlistError> {
+        let mut lines = logical_lines(text).into_iter();
+        let title = lines.next().map(|(_, title)| title).unwrap_or_default();
+        let mut elements = Vec::new();
+        let mut commands = Vec::new();
+        for (number, line) in lines {
+            let tokens = line
+                .split(|c: char| c.is_whitespace() || c == ',' || c == '(' || c == ')')
+                .filter(|token| !token.is_empty())
+                .collect::<Vec<&str>>();
+            if tokens.is_empty() {
+                continue;
+            }
+            let error = |message: String| NetlistError::new(number, message);
+            if tokens[0].starts_with('.') {
+                match tokens[0].to_lowercase().as_str() {
+                    ".end" => break,
+                    ".op" => commands.push(Command::Op),
+                    ".ac" => commands.push(Self::parse_ac(&tokens).map_err(error)?),
+                    ".tran" => commands.push(Self::parse_tran(&tokens).map_err(error)?),
+                    card => return Err(error(format!("Unsupported card {}", card))),
+                }
+            } else {
+                let mut element = Self::parse_element(&tokens).map_err(error)?;
+                element.line = number;
+                if elements
+                    .iter()
+                    .any(|other: &Element| other.name.eq_ignore_ascii_case(&element.name))
+                {
+                    return Err(error(format!("Duplicate element {}", element.name)));
+                }
+                elements.push(element);
+            }
+        }
+        Ok(Netlist {
+            title,
+            elements,
+            commands,
+        })
+    }
+
+    fn parse_ac(tokens: &[&str]) -> Result<Command, String> {
+        if tokens.len() != 5 {
+            return Err(".ac expects: .ac dec|oct|lin points fstart fstop".to_string());
+        }
+        let kind = match tokens[1].to_lowercase().as_str() {
+            "dec" => SweepKind::Decade,
+            "oct" => SweepKind::Octave,
+            "lin" => SweepKind::Linear,
+            other => return Err(format!("Unknown sweep {}", other)),
+        };
+        let points = parse_value(tokens[2])?;
+        if points < 1.0 || points.fract() != 0.0 {
+            return Err(format!("Invalid number of points {}", tokens[2]));
+        }
+        Ok(Command::Ac {
+            kind,
+            points: points as usize,
+            start_hz: parse_value(tokens[3])?,
+            stop_hz: parse_value(tokens[4])?,
+        })
+    }
+
+    fn parse_tran(tokens: &[&str]) -> Result<Command, String> {
+        if tokens.len() != 3 {
+            return Err(".tran expects: .tran tstep tstop".to_string());
+        }
+        Ok(Command::Tran {
+            t_step: parse_value(tokens[1])?,
+            t_stop: parse_value(tokens[2])?,
+        })
+    }
+
+    fn parse_element(tokens: &[&str]) -> Result<Element, String> {
+        let name = tokens[0].to_string();
+        let letter = name
+            .chars()
+            .next()
+            .map(|c| c.to_ascii_uppercase())
+            .unwrap_or(' ');
+        let expect = |count: usize, usage: &str| {
+            if tokens.len() < count {
+                Err(format!("{} expects: {}", name, usage))
+            } else {
+                Ok(())
+            }
+        };
+        let mut initial_condition = None;
+        let mut ac = None;
+        let (kind, value) = match letter {
+            'R' | 'C' | 'L' => {
+                expect(4, "name n+ n- value")?;
+                for token in &tokens[4..] {
+                    match token.to_lowercase().strip_prefix("ic=") {
+                        Some(value) if letter != 'R' => {
+                            initial_condition = Some(parse_value(value)?)
+                        }
+                        _ => return Err(format!("Unexpected {}", token)),
+                    }
+                }
+                let kind = match letter {
+                    'R' => ElementKind::Resistor,
+                    'C' => ElementKind::Capacitor,
+                    _ => ElementKind::Inductor,
+                };
+                (kind, Complex64::new(parse_value(tokens[3])?, 0.0))
+            }
+            'V' | 'I' => {
+                expect(4, "name n+ n- [[DC] value] [AC magnitude [phase]]")?;
+                let kind = match letter {
+                    'V' => ElementKind::VoltageSource,
+                    _ => ElementKind::CurrentSource,
+                };
+                let (value, phasor) = Self::parse_source_value(&tokens[3..])?;
+                ac = phasor;
+                (kind, value)
+            }
+            'E' | 'G' => {
+                expect(6, "name n+ n- nc+ nc- gain")?;
+                let controlling_nodes = [tokens[3].to_string(), tokens[4].to_string()];
+                let kind = match letter {
+                    'E' => ElementKind::Vcvs { controlling_nodes },
+                    _ => ElementKind::Vccs { controlling_nodes },
+                };
+                (kind, Complex64::new(parse_value(tokens[5])?, 0.0))
+            }
+            'F' | 'H' => {
+                expect(5, "name n+ n- vname gain")?;
+                let controlling_source = tokens[3].to_string();
+                let kind = match letter {
+                    'F' => ElementKind::Cccs { controlling_source },
+                    _ => ElementKind::Ccvs { controlling_source },
+                };
+                (kind, Complex64::new(parse_value(tokens[4])?, 0.0))
+            }
+            _ => return Err(format!("Unknown element {}", name)),
+        };
+        let used = match kind {
+            ElementKind::Vcvs { .. } | ElementKind::Vccs { .. } => 6,
+            ElementKind::Cccs { .. } | ElementKind::Ccvs { .. } => 5,
+            _ => tokens.len(),
+        };
+        if tokens.len() > used {
+            return Err(format!("Unexpected {}", tokens[used]));
+        }
+        Ok(Element {
+            name,
+            kind,
+            nodes: [tokens[1].to_string(), tokens[2].to_string()],
+            value,
+            ac,
+            initial_condition,
+            line: 0,
+        })
+    }
+
+    // [[DC] value] [AC magnitude [phase in degrees]], the DC value defaults to 0
+    fn parse_source_value(tokens: &[&str]) -> Result<(Complex64, Option<Complex64>), String> {
+        let mut dc = None;
+        let mut ac = None;
+        let mut index = 0;
+        while index < tokens.len() {
+            match tokens[index].to_lowercase().as_str() {
+                "dc" | "ac" if index + 1 == tokens.len() => {
+                    return Err(format!("{} expects a value", tokens[index]))
+                }
+                "dc" if dc.is_none() && ac.is_none() => {
+                    dc = Some(parse_value(tokens[index + 1])?);
+                    index += 2;
+                }
+                // the AC specification comes last
+                "ac" if ac.is_none() && tokens.len() <= index + 3 => {
+                    let magnitude = parse_value(tokens[index + 1])?;
+                    let phase = match tokens.get(index + 2) {
+                        Some(token) => parse_value(token)?,
+                        None => 0.0,
+                    };
+                    ac = Some(Complex64::from_polar(magnitude, phase.to_radians()));
+                    index = tokens.len();
+                }
+                _ if index == 0 => {
+                    dc = Some(parse_value(tokens[0])?);
+                    index += 1;
+                }
+                _ => return Err(format!("Unexpected {}", tokens[index])),
+            }
+        }
+        Ok((Complex64::new(dc.unwrap_or(0.0), 0.0), ac))
+    }
+
+    // builds the circuit, the nets are mapped to one of their terminals
+    pub fn to_circuit(&self) -> Result<(Circuit, BTreeMap<String, usize>), NetlistError> {
+        let mut circuit = Circuit::new();
+        let mut nets = BTreeMap::<String, usize>::new();
+        // controlling sources may follow the sources they control
+        let mut current_controlled = Vec::new();
+        for element in self.elements.iter() {
+            let error = |message: String| NetlistError::new(element.line, message);
+            let mut element_nets = element.nodes.to_vec();
+            let kind = match &element.kind {
+                ElementKind::Resistor => ComponentKind::Resistor,
+                ElementKind::Capacitor => ComponentKind::Capacitor,
+                ElementKind::Inductor => ComponentKind::Inductor,
+                ElementKind::VoltageSource => ComponentKind::VoltageSource,
+                ElementKind::CurrentSource => ComponentKind::CurrentSource,
+                ElementKind::Vcvs { controlling_nodes } => {
+                    element_nets.extend(controlling_nodes.iter().cloned());
+                    ComponentKind::Vcvs
+                }
+                ElementKind::Vccs { controlling_nodes } => {
+                    element_nets.extend(controlling_nodes.iter().cloned());
+                    ComponentKind::Vccs
+                }
+                ElementKind::Ccvs { controlling_source } => {
+                    current_controlled.push((element, controlling_source));
+                    ComponentKind::Ccvs
+                }
+                ElementKind::Cccs { controlling_source } => {
+                    current_controlled.push((element, controlling_source));
+                    ComponentKind::Cccs
+                }
+            };
+            let id = circuit.add_component(kind, element.value);
+            circuit.rename_component(id, &element.name).map_err(error)?;
+            if let Some(ac) = element.ac {
+                circuit
+                    .set_ac(id, ac.norm(), ac.arg().to_degrees())
+                    .map_err(error)?;
+            }
+            if element.initial_condition.is_some() {
+                circuit
+                    .set_initial_condition(id, element.initial_condition)
+                    .map_err(error)?;
+            }
+            let terminal_ids = circuit.components[&id].ids().to_vec();
+            for (net, terminal_id) in element_nets.iter().zip(terminal_ids) {
+                let net = if is_ground(net) { "0" } else { net.as_str() };
+                match nets.get(net) {
+                    Some(first) => circuit.connect(first, &terminal_id).map_err(error)?,
+                    None => {
+                        nets.insert(net.to_string(), terminal_id);
+                    }
+                }
+            }
+        }
+        for (element, controlling_source) in current_controlled {
+            let error = |message: String| NetlistError::new(element.line, message);
+            let id = circuit
+                .find_component(&element.name)
+                .expect("the element was added");
+            let source = circuit
+                .find_component(controlling_source)
+                .ok_or(error(format!(
+                    "Controlling source {} does not exist",
+                    controlling_source
+                )))?;
+            circuit.set_controlling_source(id, source).map_err(error)?;
+        }
+        if let Some(ground) = nets.get("0") {
+            circuit.set_ground(*ground);
+        }
+        Ok((circuit, nets))
+    }
+
+    // nets are numbered by node, the ground node is 0
+    pub fn from_circuit(title: &str, circuit: &mut Circuit) -> Result<Netlist, String> {
+        let topology = circuit.topology()?;
+        let net_name = |terminal_id: &usize| -> String {
+            match topology.node_of(terminal_id) {
+                Some(node_id) if node_id == topology.ground() => "0".to_string(),
+                Some(node_id) => topology
+                    .unknown_of_node(node_id)
+                    .map(|unknown| (unknown + 1).to_string())
+                    .unwrap_or_default(),
+                None => "?".to_string(),
+            }
+        };
+        // SPICE derives the kind from the first letter of the name
+        let element_name = |component: &Component| {
+            let mut name = component.name().to_string();
+            let letter = component.kind().symbol();
+            if !name.to_ascii_uppercase().starts_with(letter) {
+                name.insert_str(0, letter);
+            }
+            name
+        };
+        let elements = circuit
+            .components
+            .values()
+            .map(|component| {
+                let ids = component.ids();
+                let controlling_source = || -> Result<String, String> {
+                    component
+                        .controlling_source()
+                        .and_then(|source| circuit.components.get(&source))
+                        .map(element_name)
+                        .ok_or(format!("{} has no controlling source", component.name()))
+                };
+                let kind = match component.kind() {
+                    ComponentKind::Resistor => ElementKind::Resistor,
+                    ComponentKind::Capacitor => ElementKind::Capacitor,
+                    ComponentKind::Inductor => ElementKind::Inductor,
+                    ComponentKind::VoltageSource => ElementKind::VoltageSource,
+                    ComponentKind::CurrentSource => ElementKind::CurrentSource,
+                    ComponentKind::Vcvs => ElementKind::Vcvs {
+                        controlling_nodes: [net_name(&ids[2]), net_name(&ids[3])],
+                    },
+                    ComponentKind::Vccs => ElementKind::Vccs {
+                        controlling_nodes: [net_name(&ids[2]), net_name(&ids[3])],
+                    },
+                    ComponentKind::Ccvs => ElementKind::Ccvs {
+                        controlling_source: controlling_source()?,
+                    },
+                    ComponentKind::Cccs => ElementKind::Cccs {
+                        controlling_source: controlling_source()?,
+                    },
+                    ComponentKind::OpAmp | ComponentKind::FiniteOpAmp { .. } => {
+                        return Err(format!("Op-amp {} has no SPICE element", component.name()))
+                    }
+                    ComponentKind::Diode(_) => {
+                        return Err(format!(
+                            "Exporting diode {} is not supported",
+                            component.name()
+                        ))
+                    }
+                    ComponentKind::Bjt(_) | ComponentKind::Mosfet(_) => {
+                        return Err(format!(
+                            "Exporting transistor {} is not supported",
+                            component.name()
+                        ))
+                    }
+                };
+                Ok(Element {
+                    name: element_name(component),
+                    kind,
+                    nodes: [net_name(&ids[0]), net_name(&ids[1])],
+                    value: component.value(),
+                    ac: Some(component.ac()).filter(|ac| *ac != Complex64::new(0.0, 0.0)),
+                    initial_condition: component.initial_condition().map(|value| value.re),
+                    line: 0,
+                })
+            })
+            .collect::<Result<Vec<Element>, String>>()?;
+        Ok(Netlist {
+            title: title.to_string(),
+            elements,
+            commands: Vec::new(),
+        })
+    }
+}
+
+impl fmt::Display for Element {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} {} {}", self.name, self.nodes[0], self.nodes[1])?;
+        match &self.kind {
+            ElementKind::Vcvs { controlling_nodes } | ElementKind::Vccs { controlling_nodes } => {
+                write!(f, " {} {}", controlling_nodes[0], controlling_nodes[1])?
+            }
+            ElementKind::Cccs { controlling_source } | ElementKind::Ccvs { controlling_source } => {
+                write!(f, " {}", controlling_source)?
+            }
+            _ => {}
+        }
+        write!(f, " {}", self.value.re)?;
+        if let Some(ac) = self.ac {
+            write!(f, " AC {} {}", ac.norm(), ac.arg().to_degrees())?;
+        }
+        if let Some(initial_condition) = self.initial_condition {
+            write!(f, " IC={}", initial_condition)?;
+        }
+        Ok(())
+    }
+}
+
+impl fmt::Display for Command {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Command::Op => write!(f, ".op"),
+            Command::Ac {
+                kind,
+                points,
+                start_hz,
+                stop_hz,
+            } => {
+                let kind = match kind {
+                    SweepKind::Linear => "lin",
+                    SweepKind::Decade => "dec",
+                    SweepKind::Octave => "oct",
+                };
+                write!(f, ".ac {} {} {} {}", kind, points, start_hz, stop_hz)
+            }
+            Command::Tran { t_step, t_stop } => write!(f, ".tran {} {}", t_step, t_stop),
+        }
+    }
+}
+
+impl fmt::Display for Netlist {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "{}", self.title)?;
+        for element in self.elements.iter() {
+            writeln!(f, "{}", element)?;
+        }
+        for command in self.commands.iter() {
+            writeln!(f, "{}", command)?;
+        }
+        writeln!(f, ".end")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // reads the `name = value` lines of the hand-derived expectations, the names are the ones
+    // ngspice prints (v(node) and source#branch)
+    fn read_operating_point(text: &str) -> Vec<(String, f64)> {
+        text.lines()
+            .filter(|line| !line.starts_with('*'))
+            .filter_map(|line| line.split_once('='))
+            .map(|(name, value)| (name.trim().to_string(), value.trim().parse().unwrap()))
+            .collect()
+    }
+
+    fn check_fixture(netlist: &str, operating_point: &str) {
+        let netlist = Netlist::parse(netlist).unwrap();
+        assert_eq!(netlist.commands, vec![Command::Op]);
+        let (mut circuit, nets) = netlist.to_circuit().unwrap();
+        let solution = circuit.solve_dc().unwrap();
+        for (name, expected) in read_operating_point(operating_point) {
+            let actual = if let Some(net) = name
+                .strip_prefix("v(")
+                .and_then(|name| name.strip_suffix(')'))
+            {
+                circuit.voltage(&nets[net]).unwrap().re
+            } else {
+                let source = name.strip_suffix("#branch").unwrap();
+                let id = circuit.find_component(source).unwrap();
+                solution.current(id).unwrap().re
+            };
+            assert!(
+                (actual - expected).abs() <= 1e-9 * expected.abs().max(1.0),
+                "{}: {} instead of {}",
+                name,
+                actual,
+                expected
+            );
+        }
+    }
+
+    #[test]
+    fn test_parse_value() {
+        assert_eq!(parse_value("10").unwrap(), 10.0);
+        assert_eq!(parse_value("4.7k").unwrap(), 4700.0);
+        assert_eq!(parse_value("4.7K").unwrap(), 4700.0);
+        assert_eq!(parse_value("1meg").unwrap(), 1e6);
+        assert_eq!(parse_value("2MEGohm").unwrap(), 2e6);
+        assert_eq!(parse_value("3m").unwrap(), 3e-3);
+        assert!((parse_value("10uF").unwrap() - 10e-6).abs() < 1e-18);
+        assert!((parse_value("22n").unwrap() - 22e-9).abs() < 1e-21);
+        assert!((parse_value("100p").unwrap() - 100e-12).abs() < 1e-24);
+        assert_eq!(parse_value("-1.5e-3").unwrap(), -1.5e-3);
+        assert_eq!(parse_value("1e3k").unwrap(), 1e6);
+        assert_eq!(parse_value("5V").unwrap(), 5.0);
+        assert!(parse_value("k").is_err());
+        assert!(parse_value("1%").is_err());
+    }
+
+    #[test]
+    fn test_parse_netlist() {
+        let netlist = Netlist::parse(
+            "title line\n\
+             * comment\n\
+             C1 a 0 1u IC=2\n\
+             L1 a b\n\
+             + 10m ; continued value\n\
+             V1 b 0 AC 1 90\n\
+             E1 c 0 a b 10\n\
+             F1 c 0 V1 2\n\
+             G1 c 0 a b 1m\n\
+             H1 d 0 V1 1k\n\
+             .ac dec 10 1 1meg\n\
+             .tran 1u 5m\n\
+             .end\n\
+             R1 ignored after end 1\n",
+        )
+        .unwrap();
+        assert_eq!(netlist.title, "title line");
+        assert_eq!(netlist.elements.len(), 7);
+        assert_eq!(netlist.elements[0].initial_condition, Some(2.0));
+        assert_eq!(netlist.elements[1].value, Complex64::new(10e-3, 0.0));
+        assert_eq!(netlist.elements[1].line, 4);
+        assert_eq!(netlist.elements[2].value, Complex64::new(0.0, 0.0));
+        assert!((netlist.elements[2].ac.unwrap() - Complex64::new(0.0, 1.0)).norm() < 1e-12);
+        assert_eq!(
+            netlist.elements[3].kind,
+            ElementKind::Vcvs {
+                controlling_nodes: ["a".to_string(), "b".to_string()]
+            }
+        );
+        assert_eq!(
+            netlist.elements[4].kind,
+            ElementKind::Cccs {
+                controlling_source: "V1".to_string()
+            }
+        );
+        assert_eq!(netlist.elements[6].value, Complex64::new(1000.0, 0.0));
+        assert_eq!(
+            netlist.commands,
+            vec![
+                Command::Ac {
+                    kind: SweepKind::Decade,
+                    points: 10,
+                    start_hz: 1.0,
+                    stop_hz: 1e6
+                },
+                Command::Tran {
+                    t_step: 1e-6,
+                    t_stop: 5e-3
+                }
+            ]
+        );
+    }
+
+    #[test]
+    fn test_line_numbers() {
+        let error = |text: &str| Netlist::parse(text).unwrap_err();
+        assert_eq!(error("t\nR1 a 0 1k\n\nX1 a 0 1").line, 4);
+        assert_eq!(error("t\n* c\nR1 a 0\n").line, 3);
+        assert_eq!(error("t\nR1 a 0 one\n").line, 2);
+        assert_eq!(error("t\nR1 a 0 1\nr1 b 0 1").line, 3);
+        assert_eq!(error("t\n.options abstol=1n\n").line, 2);
+        assert_eq!(error("t\nV1 a 0 DC 1 AC\n").line, 2);
+        assert_eq!(error("t\nV1 a 0 AC 1 0 DC 1\n").line, 2);
+        assert_eq!(error("t\nR1 a 0 1 IC=3\n").line, 2);
+        assert_eq!(
+            error("t\nR1 a\n+ 0 1k 5\n").to_string(),
+            "line 2: Unexpected 5"
+        );
+    }
+
+    #[test]
+    fn test_round_trip() {
+        let text = "round trip\n\
+                    V1 in 0 DC 2 AC 1 -45\n\
+                    R1 in out 4.7k\n\
+                    C1 out 0 100n IC=0.5\n\
+                    L1 out x 1m\n\
+                    I1 x 0 2m\n\
+                    G1 x 0 in out 0.5\n\
+                    H1 y 0 V1 100\n\
+                    .op\n\
+                    .ac oct 5 100 1k\n\
+                    .tran 1e-6 0.001\n";
+        let netlist = Netlist::parse(text).unwrap();
+        let written = netlist.to_string();
+        let reparsed = Netlist::parse(&written).unwrap();
+        assert_eq!(reparsed.to_string(), written);
+        assert_eq!(reparsed.commands, netlist.commands);
+        for (a, b) in netlist.elements.iter().zip(reparsed.elements.iter()) {
+            assert_eq!(a.name, b.name);
+            assert_eq!(a.kind, b.kind);
+            assert_eq!(a.nodes, b.nodes);
+            assert!((a.value - b.value).norm() < 1e-12);
+            match (a.ac, b.ac) {
+                (Some(a), Some(b)) => assert!((a - b).norm() < 1e-12),
+                (a, b) => assert_eq!(a, b),
+            }
+            assert_eq!(a.initial_condition, b.initial_condition);
+        }
+    }
+
+    #[test]
+    fn test_source_values() {
+        let value = |text: &str| Netlist::parse(text).unwrap().elements[0].clone();
+        let source = value("t\nV1 a 0 5\n");
+        assert_eq!((source.value.re, source.ac), (5.0, None));
+        let source = value("t\nV1 a 0 DC 5\n");
+        assert_eq!((source.value.re, source.ac), (5.0, None));
+        let source = value("t\nI1 a 0 AC 2\n");
+        assert_eq!(
+            (source.value.re, source.ac),
+            (0.0, Some(Complex64::new(2.0, 0.0)))
+        );
+        let source = value("t\nV1 a 0 DC 5 AC 1 180\n");
+        assert_eq!(source.value.re, 5.0);
+        assert!((source.ac.unwrap() + 1.0).norm() < 1e-12);
+        let source = value("t\nV1 a 0 5 AC 1\n");
+        assert_eq!(
+            (source.value.re, source.ac),
+            (5.0, Some(Complex64::new(1.0, 0.0)))
+        );
+
+        // the bias supply stays an AC ground, only the AC source drives the sweep
+        let text = "ac sources\n\
+                    Vcc vcc 0 DC 5\n\
+                    Vin in 0 DC 1 AC 1\n\
+                    R1 in out 1k\n\
+                    R2 out vcc 1k\n";
+        let (mut circuit, nets) = Netlist::parse(text).unwrap().to_circuit().unwrap();
+        circuit.solve_dc().unwrap();
+        assert!((circuit.voltage(&nets["out"]).unwrap().re - 3.0).abs() < 1e-12);
+        let sweep = circuit.ac_sweep(1e3, 1e3, 1, SweepKind::Linear).unwrap();
+        let output = circuit.node_of(&nets["out"]).unwrap();
+        assert!((sweep.node_voltages(output)[0] - 0.5).norm() < 1e-12);
+    }
+
+    #[test]
+    fn test_fixtures() {
+        check_fixture(
+            include_str!("../fixtures/divider.cir"),
+            include_str!("../fixtures/divider.expected"),
+        );
+        check_fixture(
+            include_str!("../fixtures/ladder.cir"),
+            include_str!("../fixtures/ladder.expected"),
+        );
+    }
+
+    #[test]
+    fn test_circuit_export() {
+        let netlist = Netlist::parse(include_str!("../fixtures/ladder.cir")).unwrap();
+        let (mut circuit, _) = netlist.to_circuit().unwrap();
+        circuit.solve_dc().unwrap();
+        let exported = Netlist::from_circuit("exported ladder", &mut circuit).unwrap();
+        let written = exported.to_string();
+        assert!(written.starts_with("exported ladder\nVin "));
+        assert!(written.contains("\nR4 "));
+
+        let (mut imported, _) = Netlist::parse(&written).unwrap().to_circuit().unwrap();
+        imported.solve_dc().unwrap();
+        for (id, component) in circuit.components.iter() {
+            let copy = &imported.components[id];
+            assert_eq!(component.name(), copy.name());
+            for (terminal, copy_terminal) in component.ids().iter().zip(copy.ids().iter()) {
+                let expected = circuit.voltage(terminal).unwrap();
+                assert!((imported.voltage(copy_terminal).unwrap() - expected).norm() < 1e-12);
+            }
+        }
+    }
+
+    #[test]
+    fn test_controlled_sources() {
+        // F1 refers to the source defined after it
+        let text = "controlled sources\n\
+                    V1 in 0 1\n\
+                    R1 in 0 1k\n\
+                    E1 a 0 in 0 2\n\
+                    R2 a 0 1k\n\
+                    G1 0 b a 0 1m\n\
+                    R3 b 0 1k\n\
+                    F1 0 c Vsense 3\n\
+                    Vsense b d 0\n\
+                    R4 d 0 1k\n\
+                    R5 c 0 1k\n\
+                    H1 e 0 V1 1k\n\
+                    R6 e 0 1k\n";
+        let (mut circuit, nets) = Netlist::parse(text).unwrap().to_circuit().unwrap();
+        circuit.solve_dc().unwrap();
+        let voltage = |net: &str| circuit.voltage(&nets[net]).unwrap().re;
+        // G1 pushes 2 mA into R3 || R4, F1 three times the 1 mA through Vsense into R5
+        assert!((voltage("a") - 2.0).abs() < 1e-12);
+        assert!((voltage("b") - 1.0).abs() < 1e-12);
+        assert!((voltage("c") - 3.0).abs() < 1e-12);
+        // the 1 mA into R1 flows out of the positive terminal of V1
+        assert!((voltage("e") + 1.0).abs() < 1e-12);
+
+        let exported = Netlist::from_circuit("exported", &mut circuit).unwrap();
+        let (mut imported, _) = Netlist::parse(&exported.to_string())
+            .unwrap()
+            .to_circuit()
+            .unwrap();
+        imported.solve_dc().unwrap();
+        for component in circuit.components.values() {
+            for terminal in component.ids() {
+                let expected = circuit.voltage(terminal).unwrap();
+                assert!((imported.voltage(terminal).unwrap() - expected).norm() < 1e-12);
+            }
+        }
+
+        let missing = Netlist::parse("t\nV1 a 0 1\nF1 b 0 V2 2\n").unwrap();
+        assert_eq!(missing.to_circuit().err().unwrap().line, 3);
+        let resistor = Netlist::parse("t\nR1 a 0 1\nH1 b 0 R1 2\n").unwrap();
+        assert_eq!(resistor.to_circuit().err().unwrap().line, 3);
+    }
+}
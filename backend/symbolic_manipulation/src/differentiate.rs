@@ -0,0 +1,277 @@
+use crate::{Expression, Operator, Symbol};
+
+fn natural_logarithm(expression: Expression) -> Expression {
+    Expression::operation(
+        Operator::Logarithm,
+        vec![Expression::Symbol(Symbol::euler()), expression],
+    )
+}
+
+fn negation(expression: Expression) -> Expression {
+    Expression::operation(Operator::Negation, vec![expression])
+}
+
+fn square(expression: Expression) -> Expression {
+    Expression::operation(
+        Operator::Exponentiation,
+        vec![expression, Expression::integer(2)],
+    )
+}
+
+// a single operand stands for itself, e.g. the divisor of a / b
+fn product(mut factors: Vec<Expression>) -> Expression {
+    match factors.len() {
+        1 => factors.remove(0),
+        _ => Expression::operation(Operator::Multiplication, factors),
+    }
+}
+
+impl Expression {
+    pub(crate) fn depends_on(&self, symbol: &Symbol) -> bool {
+        match self {
+            Expression::Constant(_) => false,
+            Expression::Symbol(other) => other == symbol,
+            Expression::OperatorExpression(operator_expression) => operator_expression
+                .operands
+                .iter()
+                .any(|operand| operand.depends_on(symbol)),
+        }
+    }
+
+    // derivative with respect to the symbol, logarithms to base e stand for the natural logarithm
+    pub fn differentiate(&self, symbol: &Symbol) -> Expression {
+        let mut derivative = self.derivative(symbol);
+        derivative.simplify();
+        derivative
+    }
+
+    fn derivative(&self, symbol: &Symbol) -> Expression {
+        let operator_expression = match self {
+            Expression::OperatorExpression(operator_expression) if self.depends_on(symbol) => {
+                operator_expression
+            }
+            Expression::Symbol(other) if other == symbol => return Expression::integer(1),
+            _ => return Expression::integer(0),
+        };
+        let operands = &operator_expression.operands;
+        match operator_expression.operator {
+            Operator::Negation => negation(operands[0].derivative(symbol)),
+            // (1/f)' = -f' / f^2
+            Operator::Reciprocal => negation(Expression::operation(
+                Operator::Division,
+                vec![operands[0].derivative(symbol), square(operands[0].clone())],
+            )),
+            // the factorial is only defined for integers, so it is constant wherever it exists
+            Operator::Factorial => Expression::integer(0),
+            Operator::Addition | Operator::Subtraction => Expression::operation(
+                operator_expression.operator.clone(),
+                operands
+                    .iter()
+                    .map(|operand| operand.derivative(symbol))
+                    .collect(),
+            ),
+            // (f g h)' = f' g h + f g' h + f g h'
+            Operator::Multiplication => {
+                let terms = (0..operands.len())
+                    .filter(|index| operands[*index].depends_on(symbol))
+                    .map(|index| {
+                        let mut factors = operands.clone();
+                        factors[index] = operands[index].derivative(symbol);
+                        product(factors)
+                    })
+                    .collect::<Vec<Expression>>();
+                match terms.len() {
+                    1 => terms.into_iter().next().unwrap(),
+                    _ => Expression::operation(Operator::Addition, terms),
+                }
+            }
+            // (f / g)' = (f' g - f g') / g^2, with g as the product of all divisors
+            Operator::Division => {
+                let dividend = &operands[0];
+                let divisor = product(operands[1..].to_vec());
+                if !divisor.depends_on(symbol) {
+                    let mut operands = operands.clone();
+                    operands[0] = dividend.derivative(symbol);
+                    return Expression::operation(Operator::Division, operands);
+                }
+                let divisor_derivative =
+                    product(vec![dividend.clone(), divisor.derivative(symbol)]);
+                let numerator = if dividend.depends_on(symbol) {
+                    Expression::operation(
+                        Operator::Subtraction,
+                        vec![
+                            product(vec![dividend.derivative(symbol), divisor.clone()]),
+                            divisor_derivative,
+                        ],
+                    )
+                } else {
+                    negation(divisor_derivative)
+                };
+                Expression::operation(Operator::Division, vec![numerator, square(divisor)])
+            }
+            Operator::Exponentiation => {
+                let (base, exponent) = (&operands[0], &operands[1]);
+                // (f^n)' = n f^(n - 1) f'
+                if !exponent.depends_on(symbol) {
+                    return product(vec![
+                        exponent.clone(),
+                        Expression::operation(
+                            Operator::Exponentiation,
+                            vec![
+                                base.clone(),
+                                Expression::operation(
+                                    Operator::Subtraction,
+                                    vec![exponent.clone(), Expression::integer(1)],
+                                ),
+                            ],
+                        ),
+                        base.derivative(symbol),
+                    ]);
+                }
+                // (a^g)' = a^g ln(a) g'
+                if !base.depends_on(symbol) {
+                    return product(vec![
+                        self.clone(),
+                        natural_logarithm(base.clone()),
+                        exponent.derivative(symbol),
+                    ]);
+                }
+                // (f^g)' = f^g (g' ln(f) + g f' / f)
+                product(vec![
+                    self.clone(),
+                    Expression::operation(
+                        Operator::Addition,
+                        vec![
+                            product(vec![
+                                exponent.derivative(symbol),
+                                natural_logarithm(base.clone()),
+                            ]),
+                            Expression::operation(
+                                Operator::Division,
+                                vec![
+                                    product(vec![exponent.clone(), base.derivative(symbol)]),
+                                    base.clone(),
+                                ],
+                            ),
+                        ],
+                    ),
+                ])
+            }
+            // root(n, f) = f^(1/n)
+            Operator::Root => Expression::operation(
+                Operator::Exponentiation,
+                vec![
+                    operands[1].clone(),
+                    Expression::operation(
+                        Operator::Division,
+                        vec![Expression::integer(1), operands[0].clone()],
+                    ),
+                ],
+            )
+            .derivative(symbol),
+            Operator::Logarithm => {
+                let (base, value) = (&operands[0], &operands[1]);
+                // log(b, f)' = f' / (f ln(b))
+                if !base.depends_on(symbol) {
+                    return Expression::operation(
+                        Operator::Division,
+                        vec![
+                            value.derivative(symbol),
+                            product(vec![value.clone(), natural_logarithm(base.clone())]),
+                        ],
+                    );
+                }
+                // log(b, f) = ln(f) / ln(b)
+                Expression::operation(
+                    Operator::Division,
+                    vec![
+                        natural_logarithm(value.clone()),
+                        natural_logarithm(base.clone()),
+                    ],
+                )
+                .derivative(symbol)
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use num::complex::Complex64;
+    use std::collections::HashMap;
+
+    use super::*;
+
+    fn derivative(input: &str, symbol: &str) -> Expression {
+        Expression::parse(input)
+            .unwrap()
+            .differentiate(&Symbol::new(symbol.to_string()))
+    }
+
+    // compares the derivative against a central difference quotient at x
+    fn assert_numeric(input: &str, x: f64, others: &[(&str, f64)]) {
+        let mut values = others
+            .iter()
+            .map(|(name, value)| (Symbol::new(name.to_string()), Complex64::from(*value)))
+            .collect::<HashMap<Symbol, Complex64>>();
+        let expression = Expression::parse(input).unwrap();
+        let symbol = Symbol::new("x".to_string());
+        let h = 1e-6 * x.abs().max(1.0);
+        let mut at = |x: f64| {
+            values.insert(symbol.clone(), x.into());
+            expression.evaluate(&values).unwrap()
+        };
+        let expected = (at(x + h) - at(x - h)) / (2.0 * h);
+        values.insert(symbol.clone(), x.into());
+        let actual = expression.differentiate(&symbol).evaluate(&values).unwrap();
+        assert!(
+            (actual - expected).norm() < 1e-5 * expected.norm().max(1.0),
+            "d/dx {} at {}: {} != {}",
+            input,
+            x,
+            actual,
+            expected
+        );
+    }
+
+    #[test]
+    fn test_rules() {
+        assert_eq!(derivative("x^3 + 2*x + y", "x").to_infix(), "2 + 3 * x^2");
+        assert_eq!(derivative("y * x", "x").to_infix(), "y");
+        assert_eq!(
+            derivative("log(2, x)", "x").to_infix(),
+            "1 / (x * log(_e, 2))"
+        );
+        assert_eq!(derivative("_e ^ x", "x").to_infix(), "_e^x");
+        assert_eq!(derivative("e ^ x", "x").to_infix(), "e^x * log(_e, e)");
+        assert_eq!(derivative("sqrt(x) + n!", "x").to_infix(), "1/2 * x^(-1/2)");
+        assert_eq!(derivative("y^2", "x").to_infix(), "0");
+    }
+
+    #[test]
+    fn test_numeric() {
+        assert_numeric("x * a - 3 / x", 2.0, &[("a", 5.0)]);
+        assert_numeric("(x^2 + 1) / (x - 3) / (2 * x)", 1.5, &[]);
+        assert_numeric("1 / (x^2 + 1)", 0.7, &[]);
+        assert_numeric("x ^ x", 1.3, &[]);
+        assert_numeric("2 ^ (x^2)", 0.9, &[]);
+        assert_numeric("root(3, x^2 + 1) * log(10, 3 * x)", 2.5, &[]);
+        assert_numeric("log(x, 8)", 3.0, &[]);
+        assert_numeric("-(a * x - x^3)^3", 1.1, &[("a", 2.0)]);
+    }
+
+    #[test]
+    fn test_sensitivity() {
+        // dV/dR2 of a voltage divider
+        let divider = "Vin * R2 / (R1 + R2)";
+        let sensitivity = Expression::parse(divider)
+            .unwrap()
+            .differentiate(&Symbol::new("R2".to_string()));
+        let values = [("Vin", 10.0), ("R1", 1e3), ("R2", 3e3)]
+            .iter()
+            .map(|(name, value)| (Symbol::new(name.to_string()), Complex64::from(*value)))
+            .collect::<HashMap<Symbol, Complex64>>();
+        let expected = 10.0 * 1e3 / (4e3 * 4e3);
+        assert!((sensitivity.evaluate(&values).unwrap() - expected).norm() < 1e-12);
+    }
+}
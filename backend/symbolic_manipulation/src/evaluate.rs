@@ -0,0 +1,207 @@
+use core::fmt;
+use num::complex::Complex64;
+use num::{ToPrimitive, Zero};
+use std::collections::HashMap;
+
+use crate::{Expression, Operator, Symbol};
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum EvalError {
+    UnboundSymbol(Symbol),
+    DivisionByZero,
+    // the operands are outside of the domain of the operator, e.g. the factorial of 1/2
+    Domain(String),
+}
+
+impl fmt::Display for EvalError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            EvalError::UnboundSymbol(symbol) => write!(f, "Symbol {} has no value", symbol),
+            EvalError::DivisionByZero => write!(f, "Division by zero"),
+            EvalError::Domain(message) => write!(f, "{}", message),
+        }
+    }
+}
+
+fn real_integer(value: Complex64) -> Option<i64> {
+    (value.im == 0.0 && value.re.fract() == 0.0 && value.re.abs() < i64::MAX as f64)
+        .then_some(value.re as i64)
+}
+
+fn reciprocal(value: Complex64) -> Result<Complex64, EvalError> {
+    if value.is_zero() {
+        return Err(EvalError::DivisionByZero);
+    }
+    Ok(value.inv())
+}
+
+fn factorial(value: Complex64) -> Result<Complex64, EvalError> {
+    match real_integer(value) {
+        // 171! exceeds the range of f64
+        Some(n) if n > 170 => Ok(f64::INFINITY.into()),
+        Some(n) if n >= 0 => Ok((1..=n).map(|factor| factor as f64).product::<f64>().into()),
+        _ => Err(EvalError::Domain(format!(
+            "Factorial of {} is not defined",
+            value
+        ))),
+    }
+}
+
+// 0^0 is 1, like the rule ?x ^ 0 -> 1 of simplify and powi
+fn power(base: Complex64, exponent: Complex64) -> Result<Complex64, EvalError> {
+    if base.is_zero() {
+        if exponent.is_zero() {
+            return Ok(Complex64::new(1.0, 0.0));
+        }
+        if exponent.re > 0.0 {
+            return Ok(Complex64::zero());
+        }
+        return Err(EvalError::DivisionByZero);
+    }
+    // integer powers stay exact for real bases
+    match real_integer(exponent).and_then(|exponent| i32::try_from(exponent).ok()) {
+        Some(exponent) => Ok(base.powi(exponent)),
+        None => Ok(base.powc(exponent)),
+    }
+}
+
+fn root(n: Complex64, value: Complex64) -> Result<Complex64, EvalError> {
+    if n.is_zero() {
+        return Err(EvalError::DivisionByZero);
+    }
+    // odd roots of negative numbers are real, like in simplify
+    match real_integer(n) {
+        Some(n) if n % 2 != 0 && value.im == 0.0 && value.re < 0.0 => {
+            Ok((-(-value.re).powf(1.0 / n as f64)).into())
+        }
+        _ => power(value, n.inv()),
+    }
+}
+
+fn logarithm(base: Complex64, value: Complex64) -> Result<Complex64, EvalError> {
+    if value.is_zero() || base.is_zero() {
+        return Err(EvalError::Domain(
+            "Logarithm of zero is not defined".to_string(),
+        ));
+    }
+    let denominator = base.ln();
+    if denominator.is_zero() {
+        return Err(EvalError::Domain(
+            "Logarithm to base 1 is not defined".to_string(),
+        ));
+    }
+    Ok(value.ln() / denominator)
+}
+
+impl Expression {
+    // numeric value with every symbol replaced by its value
+    pub fn evaluate(&self, values: &HashMap<Symbol, Complex64>) -> Result<Complex64, EvalError> {
+        let operator_expression = match self {
+            Expression::Constant(constant) => {
+                return constant.to_f64().map(Complex64::from).ok_or_else(|| {
+                    EvalError::Domain(format!("Constant {} does not fit into a float", constant))
+                })
+            }
+            Expression::Symbol(symbol) => {
+                if *symbol == Symbol::euler() {
+                    return Ok(std::f64::consts::E.into());
+                }
+                return values
+                    .get(symbol)
+                    .copied()
+                    .ok_or_else(|| EvalError::UnboundSymbol(symbol.clone()));
+            }
+            Expression::OperatorExpression(operator_expression) => operator_expression,
+        };
+        let operands = operator_expression
+            .operands
+            .iter()
+            .map(|operand| operand.evaluate(values))
+            .collect::<Result<Vec<Complex64>, EvalError>>()?;
+        match operator_expression.operator {
+            Operator::Negation => Ok(-operands[0]),
+            Operator::Reciprocal => reciprocal(operands[0]),
+            Operator::Factorial => factorial(operands[0]),
+            Operator::Addition => Ok(operands.iter().sum()),
+            Operator::Subtraction => Ok(operands[0] - operands[1..].iter().sum::<Complex64>()),
+            Operator::Multiplication => Ok(operands.iter().product()),
+            Operator::Division => {
+                Ok(operands[0] * reciprocal(operands[1..].iter().product::<Complex64>())?)
+            }
+            Operator::Exponentiation => power(operands[0], operands[1]),
+            Operator::Logarithm => logarithm(operands[0], operands[1]),
+            Operator::Root => root(operands[0], operands[1]),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn evaluate(input: &str, values: &[(&str, Complex64)]) -> Result<Complex64, EvalError> {
+        let values = values
+            .iter()
+            .map(|(name, value)| (Symbol::new(name.to_string()), *value))
+            .collect::<HashMap<Symbol, Complex64>>();
+        Expression::parse(input).unwrap().evaluate(&values)
+    }
+
+    #[test]
+    fn test_evaluate() {
+        let gain = evaluate(
+            "Vin * R2 / (R1 + R2)",
+            &[
+                ("Vin", 10.0.into()),
+                ("R1", 1e3.into()),
+                ("R2", 4.7e3.into()),
+            ],
+        )
+        .unwrap();
+        assert!((gain - 4.7 / 5.7 * 10.0).norm() < 1e-12);
+        let impedance = evaluate(
+            "1 / (s * C)",
+            &[("s", Complex64::new(0.0, 1e3)), ("C", 1e-6.into())],
+        )
+        .unwrap();
+        assert!((impedance - Complex64::new(0.0, -1e3)).norm() < 1e-9);
+        assert_eq!(evaluate("4! - root(3, -8)", &[]).unwrap(), 26.0.into());
+        assert!((evaluate("log(2, 8)", &[]).unwrap() - 3.0).norm() < 1e-12);
+        // e is an ordinary variable, _e is Euler's number
+        assert_eq!(evaluate("e + 1", &[("e", 2.0.into())]).unwrap(), 3.0.into());
+        assert!((evaluate("log(_e, 1 / _e)", &[]).unwrap() + 1.0).norm() < 1e-12);
+    }
+
+    #[test]
+    fn test_evaluate_simplified() {
+        // evaluating before and after simplify gives the same value, also for 0^0
+        for (input, x) in [("x^0", 0.0), ("0^0 + x", 2.0), ("(x - x)^x", 0.0)] {
+            let values = [("x", x.into())];
+            let mut simplified = Expression::parse(input).unwrap();
+            simplified.simplify();
+            assert_eq!(
+                evaluate(input, &values),
+                evaluate(&simplified.to_infix(), &values),
+                "{}",
+                input
+            );
+        }
+    }
+
+    #[test]
+    fn test_evaluate_errors() {
+        assert_eq!(
+            evaluate("x + y", &[("x", 1.0.into())]),
+            Err(EvalError::UnboundSymbol(Symbol::new("y".to_string())))
+        );
+        assert_eq!(
+            evaluate("1 / (x - 1)", &[("x", 1.0.into())]),
+            Err(EvalError::DivisionByZero)
+        );
+        assert!(matches!(evaluate("0.5!", &[]), Err(EvalError::Domain(_))));
+        assert!(matches!(
+            evaluate("log(1, 2)", &[]),
+            Err(EvalError::Domain(_))
+        ));
+    }
+}
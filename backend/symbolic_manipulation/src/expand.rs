@@ -0,0 +1,322 @@
+use num::{BigInt, One};
+use std::collections::{BTreeMap, HashMap};
+
+use crate::polynomial::{Monomial, Polynomial, MAX_EXPONENT};
+use crate::rational::Rational;
+use crate::{Expression, Operator, Symbol};
+
+// parts that are no polynomials, like log(2, x) or 1 / (x + 1), stand in for symbols while
+// expanding, their names sort after all ordinary symbols
+const ATOM_PREFIX: char = '~';
+
+impl Expression {
+    // sum of products with distributed multiplications and powers, the result is canonical
+    // so (x + 1) * (x - 1) and x^2 - 1 expand to the same tree
+    pub fn expand(&self) -> Expression {
+        let mut atoms = HashMap::new();
+        let polynomial = self.expand_polynomial(&mut atoms);
+        materialize(&polynomial, &atoms)
+    }
+
+    fn expand_polynomial(&self, atoms: &mut HashMap<Symbol, Expression>) -> Polynomial {
+        let operator_expression = match self {
+            Expression::Constant(constant) => return Polynomial::constant(constant.clone()),
+            Expression::Symbol(symbol) => return Polynomial::symbol(symbol.clone()),
+            Expression::OperatorExpression(operator_expression) => operator_expression,
+        };
+        let operands = &operator_expression.operands;
+        let mut expanded = operands
+            .iter()
+            .map(|operand| operand.expand_polynomial(atoms));
+        match operator_expression.operator {
+            Operator::Negation => -&expanded.next().unwrap_or_default(),
+            Operator::Addition => expanded.fold(Polynomial::zero(), |sum, term| &sum + &term),
+            Operator::Subtraction => {
+                let first = expanded.next().unwrap_or_default();
+                expanded.fold(first, |difference, term| &difference - &term)
+            }
+            Operator::Multiplication => {
+                let factors = expanded.collect::<Vec<Polynomial>>();
+                factors.iter().fold(Polynomial::one(), |product, factor| {
+                    multiply(&product, factor, atoms)
+                })
+            }
+            Operator::Division => {
+                let first = expanded.next().unwrap_or_default();
+                let divisors = expanded.collect::<Vec<Polynomial>>();
+                divisors.iter().fold(first, |quotient, divisor| {
+                    let reciprocal = reciprocal(divisor, 1, atoms);
+                    multiply(&quotient, &reciprocal, atoms)
+                })
+            }
+            Operator::Reciprocal => {
+                let operand = expanded.next().unwrap_or_default();
+                let reciprocal = reciprocal(&operand, 1, atoms);
+                multiply(&reciprocal, &Polynomial::one(), atoms)
+            }
+            Operator::Exponentiation => {
+                let base = expanded.next().unwrap_or_default();
+                let exponent = expanded.next().unwrap_or_default();
+                let integer = exponent
+                    .as_constant()
+                    .filter(|exponent| exponent.is_integer())
+                    .map(|exponent| exponent.to_integer());
+                let mut terms = base.terms();
+                let power = match (integer, terms.next(), terms.next()) {
+                    // powers of a monomial multiply its exponents, however large they get
+                    (Some(integer), Some((monomial, coefficient)), None)
+                        if coefficient.is_one() =>
+                    {
+                        let mut exponents = exponents(monomial, atoms);
+                        for exponent in exponents.values_mut() {
+                            *exponent *= &integer;
+                        }
+                        Some(power_product(exponents, atoms))
+                    }
+                    (Some(integer), _, _) => match i32::try_from(integer) {
+                        Ok(exponent @ 0..) => base
+                            .pow(exponent as u32)
+                            .map(|power| multiply(&power, &Polynomial::one(), atoms)),
+                        Ok(exponent) => {
+                            let reciprocal = reciprocal(&base, exponent.unsigned_abs(), atoms);
+                            Some(multiply(&reciprocal, &Polynomial::one(), atoms))
+                        }
+                        Err(_) => None,
+                    },
+                    (None, _, _) => None,
+                };
+                // powers that are no polynomial or too large to multiply out stay as they are
+                power.unwrap_or_else(|| {
+                    let power = Expression::operation(
+                        Operator::Exponentiation,
+                        vec![materialize(&base, atoms), materialize(&exponent, atoms)],
+                    );
+                    atom(power, atoms)
+                })
+            }
+            Operator::Factorial | Operator::Root | Operator::Logarithm => {
+                let operands = expanded.collect::<Vec<Polynomial>>();
+                let operands = operands
+                    .iter()
+                    .map(|operand| materialize(operand, atoms))
+                    .collect();
+                atom(
+                    Expression::operation(operator_expression.operator.clone(), operands),
+                    atoms,
+                )
+            }
+        }
+    }
+}
+
+// the expression with the atoms put back in
+fn materialize(polynomial: &Polynomial, atoms: &HashMap<Symbol, Expression>) -> Expression {
+    let mut expression = polynomial.to_expression();
+    expression.substitute_all(atoms);
+    expression
+}
+
+// identical atoms print the same, so they share one symbol
+fn atom(expression: Expression, atoms: &mut HashMap<Symbol, Expression>) -> Polynomial {
+    let symbol = Symbol::new(format!("{}{}", ATOM_PREFIX, expression.to_infix()));
+    atoms.insert(symbol.clone(), expression);
+    Polynomial::symbol(symbol)
+}
+
+// x^n with an integer n
+fn symbol_power(expression: &Expression) -> Option<(Symbol, BigInt)> {
+    let Expression::OperatorExpression(power) = expression else {
+        return None;
+    };
+    match (&power.operator, &power.operands[..]) {
+        (
+            Operator::Exponentiation,
+            [Expression::Symbol(symbol), Expression::Constant(exponent)],
+        ) if exponent.is_integer() => Some((symbol.clone(), exponent.to_integer())),
+        _ => None,
+    }
+}
+
+fn has_symbol_powers(polynomial: &Polynomial, atoms: &HashMap<Symbol, Expression>) -> bool {
+    polynomial.terms().any(|(monomial, _)| {
+        monomial
+            .exponents()
+            .any(|(symbol, _)| atoms.get(symbol).and_then(symbol_power).is_some())
+    })
+}
+
+// the exponents of the symbols, atoms of powers beyond MAX_EXPONENT count towards their symbol
+fn exponents(monomial: &Monomial, atoms: &HashMap<Symbol, Expression>) -> BTreeMap<Symbol, BigInt> {
+    let mut exponents = BTreeMap::<Symbol, BigInt>::new();
+    for (symbol, exponent) in monomial.exponents() {
+        let (symbol, exponent) = match atoms.get(symbol).and_then(symbol_power) {
+            Some((base, power)) => (base, power * exponent),
+            None => (symbol.clone(), BigInt::from(exponent)),
+        };
+        *exponents.entry(symbol).or_default() += exponent;
+    }
+    exponents
+}
+
+// the product of the powers, a power beyond MAX_EXPONENT becomes the atom x^n, so it prints
+// the same however it was reached
+fn power_product(
+    exponents: BTreeMap<Symbol, BigInt>,
+    atoms: &mut HashMap<Symbol, Expression>,
+) -> Polynomial {
+    let mut product = Polynomial::one();
+    for (symbol, exponent) in exponents {
+        let factor = match i32::try_from(&exponent) {
+            Ok(exponent) if exponent.unsigned_abs() <= MAX_EXPONENT.unsigned_abs() => {
+                Polynomial::term(Rational::one(), Monomial::symbol(symbol, exponent))
+            }
+            _ => {
+                let power = Expression::operation(
+                    Operator::Exponentiation,
+                    vec![
+                        Expression::Symbol(symbol),
+                        Expression::Constant(Rational::from_integer(exponent)),
+                    ],
+                );
+                atom(power, atoms)
+            }
+        };
+        // every symbol comes once and is within MAX_EXPONENT
+        product = product
+            .checked_mul(&factor)
+            .expect("the factors have distinct symbols");
+    }
+    product
+}
+
+// products add the exponents exactly, powers beyond MAX_EXPONENT become atoms of x^n
+fn multiply(a: &Polynomial, b: &Polynomial, atoms: &mut HashMap<Symbol, Expression>) -> Polynomial {
+    if !has_symbol_powers(a, atoms) && !has_symbol_powers(b, atoms) {
+        if let Some(product) = a.checked_mul(b) {
+            return product;
+        }
+    }
+    let mut product = Polynomial::zero();
+    for (monomial, coefficient) in a.terms() {
+        for (other_monomial, other_coefficient) in b.terms() {
+            let mut powers = exponents(monomial, atoms);
+            for (symbol, exponent) in exponents(other_monomial, atoms) {
+                *powers.entry(symbol).or_default() += exponent;
+            }
+            let term = power_product(powers, atoms).scale(&(coefficient * other_coefficient));
+            product = &product + &term;
+        }
+    }
+    product
+}
+
+// 1 / base^exponent, sums stay together as (x + 1)^-2 after their common monomial is split off,
+// powers too large to multiply out stay together as a whole
+fn reciprocal(
+    base: &Polynomial,
+    exponent: u32,
+    atoms: &mut HashMap<Symbol, Expression>,
+) -> Polynomial {
+    let power = |base: &Polynomial, atoms: &mut HashMap<Symbol, Expression>| {
+        let power = Expression::operation(
+            Operator::Exponentiation,
+            vec![
+                materialize(base, atoms),
+                Expression::integer(-(exponent as i128)),
+            ],
+        );
+        atom(power, atoms)
+    };
+    match base.inverse() {
+        Some(inverse) => inverse.pow(exponent).unwrap_or_else(|| power(base, atoms)),
+        None => {
+            let monomial =
+                Polynomial::term(base.content(), base.monomial_gcd().unwrap_or_default());
+            let Some(inverse) = monomial
+                .inverse()
+                .expect("a single term has an inverse")
+                .pow(exponent)
+            else {
+                return power(base, atoms);
+            };
+            let base = base
+                .divide_exact(&monomial)
+                .expect("the monomial divides every term");
+            let power = power(&base, atoms);
+            multiply(&inverse, &power, atoms)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn expand(input: &str) -> Expression {
+        Expression::parse(input).unwrap().expand()
+    }
+
+    #[test]
+    fn test_canonical() {
+        assert_eq!(expand("(x + 1) * (x - 1)"), expand("x^2 - 1"));
+        assert_eq!(expand("(x + 1) * (x - 1)").to_infix(), "x^2 - 1");
+        assert_eq!(expand("(b + a)^2"), expand("a^2 + 2*a*b + b^2"));
+        assert_eq!(
+            expand("(a + b)^3").to_infix(),
+            "a^3 + 3 * a^2 * b + 3 * a * b^2 + b^3"
+        );
+        assert_eq!(expand("(x - y) - (x + y)").to_infix(), "-2 * y");
+    }
+
+    #[test]
+    fn test_atoms() {
+        assert_eq!(
+            expand("x * (log(2, (x + 1)^2) + 1)").to_infix(),
+            "x * log(2, x^2 + 2 * x + 1) + x"
+        );
+        assert_eq!(
+            expand("(x + y) / (2 * x^2 + 2 * x)").to_infix(),
+            "1/2 * (x + 1)^-1 + 1/2 * x^-1 * y * (x + 1)^-1"
+        );
+        assert_eq!(expand("2 / (4 * x)").to_infix(), "1/2 * x^-1");
+        assert_eq!(expand("(a * b)^(x + x)").to_infix(), "(a * b)^(2 * x)");
+    }
+
+    #[test]
+    fn test_large_powers() {
+        assert_eq!(expand("x^1000000 / x^999999").to_infix(), "x");
+        assert_eq!(expand("x^2000000000").to_infix(), "x^2000000000");
+        assert_eq!(expand("2^2000000000").to_infix(), "2^2000000000");
+        assert_eq!(expand("(x + 1)^100000").to_infix(), "(x + 1)^100000");
+        // powers beyond the limit read x^n however they were reached
+        for (input, expected) in [
+            ("x^1000000 * x^1000000", "x^2000000"),
+            ("x^2000000000 * x^2000000000", "x^4000000000"),
+            ("(x^2000000000)^2", "x^4000000000"),
+            ("x * x^2000000000 * y", "y * x^2000000001"),
+            ("x^2000000000 / x^1999999999", "x"),
+            ("1 / x^2000000000", "x^-2000000000"),
+        ] {
+            assert_eq!(expand(input).to_infix(), expected, "{}", input);
+        }
+        assert_eq!(
+            expand("x * x^1000000 * x^1000000"),
+            expand("x^1000000 * x * x^1000000")
+        );
+        let huge = "(3^1000)^100000";
+        assert!(expand(huge).to_infix().ends_with("^100000"));
+        let parse = |input: &str| Expression::parse(input).unwrap();
+        assert!(!crate::equivalent(
+            &parse(&format!("{} * x", huge)),
+            &parse("x")
+        ));
+        assert!(crate::equivalent(
+            &parse(&format!("{} * x", huge)),
+            &parse(&format!("x * {}", huge))
+        ));
+        assert!(crate::equivalent(
+            &parse("x^2000000000 * y"),
+            &parse("y * x^2000000000")
+        ));
+    }
+}
@@ -1,17 +1,37 @@
 #![allow(dead_code)]
 #![allow(unused_macros)]
 use core::fmt;
-use std::collections::HashMap;
-
-#[derive(Clone, PartialEq, Eq, Hash)]
+use num::{One, Zero};
+
+pub mod differentiate;
+pub mod evaluate;
+pub mod expand;
+pub mod parser;
+pub mod polynomial;
+pub mod printer;
+pub mod rational;
+pub mod rewrite;
+pub mod saturation;
+pub mod substitute;
+
+use rational::Rational;
+
+#[derive(Clone, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
 pub struct Symbol {
     name: String,
 }
 
 impl Symbol {
-    fn new(name: String) -> Symbol {
+    pub fn new(name: String) -> Symbol {
         Symbol { name }
     }
+
+    // Euler's number, log(_e, x) is the natural logarithm
+    // names with a leading underscore are reserved, so e stays free for variables and component
+    // names, which start with the letter of their kind
+    pub fn euler() -> Symbol {
+        Symbol::new("_e".to_string())
+    }
 }
 
 impl fmt::Display for Symbol {
@@ -20,7 +40,8 @@ impl fmt::Display for Symbol {
     }
 }
 
-#[derive(Clone, Eq, Hash, PartialEq)]
+// the order of the variants is the canonical order of operator expressions
+#[derive(Clone, Debug, Eq, Hash, PartialEq, PartialOrd, Ord)]
 pub enum Operator {
     Negation,
     Reciprocal,
@@ -94,8 +115,8 @@ impl Operator {
 
     fn neutral_element(&self) -> Option<Expression> {
         match self {
-            Operator::Addition => Some(Expression::Constant(0)),
-            Operator::Multiplication => Some(Expression::Constant(1)),
+            Operator::Addition => Some(Expression::integer(0)),
+            Operator::Multiplication => Some(Expression::integer(1)),
             _ => None,
         }
     }
@@ -126,7 +147,8 @@ impl fmt::Display for Operator {
     }
 }
 
-#[derive(Clone, Eq, Hash, PartialEq)]
+// ordered by the operator, then lexicographically by the operands
+#[derive(Clone, Debug, Eq, Hash, PartialEq, PartialOrd, Ord)]
 pub struct OperatorExpression {
     operator: Operator,
     operands: Vec<Expression>,
@@ -170,21 +192,19 @@ impl OperatorExpression {
 
 impl fmt::Display for OperatorExpression {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        write!(f, "{}", self.operator)?;
-        let mut iter = self.operands.iter();
-        if let Some(first) = iter.next() {
-            write!(f, "{}", first)?;
-            for item in iter {
-                write!(f, ", {}", item)?;
-            }
+        write!(f, "({}", self.operator)?;
+        for operand in &self.operands {
+            write!(f, " {}", operand)?;
         }
-        Ok(())
+        write!(f, ")")
     }
 }
 
-#[derive(Clone, Eq, Hash, PartialEq)]
+// total order: constants < symbols < operator expressions, simplify sorts the operands of
+// commutative operators by it
+#[derive(Clone, Debug, Eq, Hash, PartialEq, PartialOrd, Ord)]
 pub enum Expression {
-    Constant(i128),
+    Constant(Rational),
     Symbol(Symbol),
     OperatorExpression(OperatorExpression),
 }
@@ -195,7 +215,13 @@ pub trait ConstructExpression {
 
 impl ConstructExpression for i128 {
     fn construct_expression(&self) -> Result<Expression, String> {
-        Ok(Expression::Constant(*self))
+        Ok(Expression::integer(*self))
+    }
+}
+
+impl ConstructExpression for Rational {
+    fn construct_expression(&self) -> Result<Expression, String> {
+        Ok(Expression::Constant(self.clone()))
     }
 }
 
@@ -211,112 +237,104 @@ impl ConstructExpression for Result<OperatorExpression, String> {
     }
 }
 
-trait InlinePush<T> {
-    fn inline_push(&mut self, item: T) -> &mut Self;
-}
-
-impl<T> InlinePush<T> for Vec<T> {
-    fn inline_push(&mut self, item: T) -> &mut Self {
-        self.push(item);
-        self
+impl Expression {
+    // for operand counts that are known to fit the operator
+    pub(crate) fn operation(operator: Operator, operands: Vec<Expression>) -> Expression {
+        Expression::OperatorExpression(OperatorExpression { operator, operands })
     }
-}
 
-trait SafeAdd<K, V> {
-    fn safe_add(&mut self, key: K, value: V);
-}
-
-impl<K, V> SafeAdd<K, V> for HashMap<K, V>
-where
-    K: Copy + std::cmp::Eq + std::hash::Hash,
-    V: std::ops::Add<Output = V> + std::ops::Sub<Output = V> + Copy + std::ops::AddAssign<V>,
-{
-    fn safe_add(&mut self, key: K, value: V) {
-        if let std::collections::hash_map::Entry::Vacant(e) = self.entry(key) {
-            e.insert(value);
-        } else {
-            *self.get_mut(&key).unwrap() += value;
+    // canonical order of the operands of commutative operators, e.g. y + 2 * x + 1 -> 1 + y + 2 * x
+    fn sort_operands(&mut self) {
+        if let Expression::OperatorExpression(operator_expression) = self {
+            if operator_expression.operator.is_commutative() {
+                operator_expression.operands.sort();
+            }
         }
     }
-}
 
-impl Expression {
-    fn is_equal(&mut self, other: &mut Expression) -> bool {
-        other.simplify();
-        format!("{}", self) == format!("{}", other)
-    }
-
-    pub fn simplify(&mut self) {
+    // constant operands are evaluated exactly, e.g. (/ 6 4) -> 3/2 or (+ x 2 3) -> (+ x 5)
+    // results that are undefined or irrational, like (/ 1 0) or (root 2 2), are left untouched
+    fn fold_constants(&mut self) {
         if let Expression::OperatorExpression(operator_expression) = self {
-            println!("simplifying {}", operator_expression);
-            for operand in &mut operator_expression.operands {
-                operand.simplify();
+            if let Operator::Addition | Operator::Multiplication = operator_expression.operator {
+                self.fold_constant_operands();
+                return;
             }
-            match operator_expression.operator {
-                Operator::Negation => {
-                    self.double_negation();
-                }
-                Operator::Subtraction => {
-                    self.remove_subtraction();
+            let constants = operator_expression
+                .operands
+                .iter()
+                .map(|operand| match operand {
+                    Expression::Constant(constant) => Some(constant),
+                    _ => None,
+                })
+                .collect::<Option<Vec<&Rational>>>();
+            let Some(constants) = constants else {
+                return;
+            };
+            let folded = match (&operator_expression.operator, &constants[..]) {
+                (Operator::Negation, [value]) => Some(-*value),
+                (Operator::Reciprocal, [value]) => (!value.is_zero()).then(|| value.recip()),
+                (Operator::Factorial, [value]) => rational::factorial(value),
+                (Operator::Subtraction, [first, rest @ ..]) => Some(
+                    rest.iter()
+                        .fold((*first).clone(), |difference, value| difference - *value),
+                ),
+                (Operator::Division, [first, rest @ ..]) => {
+                    (!rest.iter().any(|value| value.is_zero())).then(|| {
+                        rest.iter()
+                            .fold((*first).clone(), |quotient, value| quotient / *value)
+                    })
                 }
-                Operator::Addition | Operator::Multiplication => {
-                    self.remove_neutral_element();
-                    self.merge();
-                    self.factor_out();
+                (Operator::Exponentiation, [base, exponent]) => rational::power(base, exponent),
+                (Operator::Root, [n, value]) if n.is_integer() => {
+                    rational::root(value, &n.to_integer())
                 }
-                _ => {}
+                (Operator::Logarithm, [base, value]) => rational::logarithm(base, value),
+                _ => None,
+            };
+            if let Some(folded) = folded {
+                *self = Expression::Constant(folded);
             }
         }
     }
 
-    fn remove_subtraction(&mut self) {
+    // called on sums and products, all constants are combined into one at the position of the first
+    // an operator that is left with a single operand is replaced by it, e.g. (* 2 3) -> 6
+    fn fold_constant_operands(&mut self) {
         if let Expression::OperatorExpression(operator_expression) = self {
-            if let Result::Ok(expression) = OperatorExpression::new(
-                Operator::Addition,
-                vec![
-                    Ok(operator_expression.operands[0].clone()),
-                    OperatorExpression::new(
-                        Operator::Negation,
-                        vec![OperatorExpression::new(
-                            Operator::Addition,
-                            operator_expression.operands[1..]
-                                .iter()
-                                .cloned()
-                                .map(Ok)
-                                .collect::<Vec<Result<Expression, String>>>()
-                                .inline_push(Ok(Expression::Constant(0)))
-                                .clone(),
-                        )
-                        .construct_expression()],
-                    )
-                    .construct_expression(),
-                ],
-            )
-            .construct_expression()
-            {
-                *self = expression;
+            let addition = operator_expression.operator == Operator::Addition;
+            let mut position = None;
+            let mut folded = if addition {
+                Rational::zero()
+            } else {
+                Rational::one()
+            };
+            let mut operands = Vec::new();
+            for operand in operator_expression.operands.drain(..) {
+                if let Expression::Constant(constant) = operand {
+                    position.get_or_insert(operands.len());
+                    folded = if addition {
+                        folded + constant
+                    } else {
+                        folded * constant
+                    };
+                } else {
+                    operands.push(operand);
+                }
             }
-        }
-    }
-
-    // called on negations, if the child is also a negation, the child moves up and the parent is deleted
-    fn double_negation(&mut self) {
-        if let Expression::OperatorExpression(operator_expression) = self {
-            if let Expression::OperatorExpression(operand) = &mut operator_expression.operands[0] {
-                if operand.operator == Operator::Negation {
-                    *self = operand.operands[0].clone();
+            if let Some(position) = position {
+                // e.g. 0 * x -> 0
+                if !addition && folded.is_zero() {
+                    *self = Expression::Constant(folded);
+                    return;
                 }
+                operands.insert(position, Expression::Constant(folded));
             }
-        }
-    }
-
-    fn remove_neutral_element(&mut self) {
-        if let Expression::OperatorExpression(operator_expression) = self {
-            if let Some(neutral_element) = operator_expression.operator.neutral_element() {
-                operator_expression
-                    .operands
-                    .retain(|operand| *operand != neutral_element);
+            if let [operand] = &mut operands[..] {
+                *self = std::mem::replace(operand, Expression::integer(0));
+                return;
             }
+            operator_expression.operands = operands;
         }
     }
 
@@ -341,94 +359,27 @@ impl Expression {
         }
     }
 
-    fn factor_out(&mut self) {
-        if let Expression::OperatorExpression(operator_expression) = self {
-            if let Some(distributive_operator) =
-                operator_expression.operator.is_distributive_under()
-            {
-                let mut found_expressions = HashMap::<&Expression, i128>::new();
-                for operand in operator_expression.operands.iter() {
-                    if let Expression::OperatorExpression(operand) = operand {
-                        if let Some(inverse_operator) = operand.operator.inverse() {
-                            // first check if the operand is (inverse_operator expression)
-                            if operand.operands.len() != 1 {
-                                continue;
-                            }
-                            if operand.operator != inverse_operator {
-                                continue;
-                            }
-                            // -> HashMap[expression] -= 1
-                            // e.g. x - x -> 0
-                            found_expressions.safe_add(&operand.operands[0], -1);
-                            continue;
-                        }
-
-                        // first check if the operand is (distributive_operator constant expression)
-                        if operand.operator != distributive_operator {
-                            continue;
-                        }
-                        // -> HashMap[expression] += constant
-                        // e.g. x + 3 * x -> 4 * x
-                        if operand.operands.len() != 2 {
-                            continue;
-                        }
-                        if let Expression::Constant(constant) = &operand.operands[0] {
-                            found_expressions.safe_add(&operand.operands[1], *constant);
-                        }
-                        if let Expression::Constant(constant) = &operand.operands[1] {
-                            found_expressions.safe_add(&operand.operands[0], *constant);
-                        }
-                    }
-                    found_expressions.safe_add(operand, 1);
-                }
-                // the Hash map now contains all expressions and their coefficients
-                found_expressions.retain(|_, value| *value != 0);
-                // the new operands are (distributive_operator coefficient expression)
-                // e.g. x + 4 * x + y -> 5 * x + y
-                // or y * y -> y ^ 2
-                let new_operands = found_expressions
-                    .into_iter()
-                    .map(|(expression, coefficient)| {
-                        if coefficient == 1 {
-                            Ok(expression.clone())
-                        } else {
-                            OperatorExpression::new(
-                                distributive_operator.clone(),
-                                vec![Expression::Constant(coefficient), expression.clone()]
-                                    .into_iter()
-                                    .map(Ok)
-                                    .collect::<Vec<Result<Expression, String>>>(),
-                            )
-                            .construct_expression()
-                        }
-                    })
-                    .collect::<Result<Vec<Expression>, String>>();
-                if let Result::Ok(new_operands) = new_operands {
-                    if new_operands.len() == 1 {
-                        // if n = 1 the operator changes to the distributive operator)
-                        *self = new_operands[0].clone();
-                    } else {
-                        operator_expression.operands = new_operands;
-                    }
-                }
-            }
-        }
-    }
-
     // TODO: negation(x * const) -> x * -const <- found by sum_up
 }
 
+// whether both expressions are equal after simplifying, or after expanding if that fails
+// e.g. (x + 1) * (x - 1) and x^2 - 1
+pub fn equivalent(a: &Expression, b: &Expression) -> bool {
+    let canonical = |expression: &Expression| {
+        let mut expression = expression.clone();
+        expression.simplify();
+        expression
+    };
+    canonical(a) == canonical(b) || canonical(&a.expand()) == canonical(&b.expand())
+}
+
 impl fmt::Display for Expression {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         match self {
             Expression::Constant(constant) => write!(f, "{}", constant),
             Expression::Symbol(symbol) => write!(f, "{}", symbol),
             Expression::OperatorExpression(operator_expression) => {
-                write!(f, "({}", operator_expression.operator)?;
-                for operand in &operator_expression.operands {
-                    write!(f, " {}", operand)?;
-                }
-                write!(f, ")")
+                write!(f, "{}", operator_expression)
             }
         }
     }
@@ -503,7 +454,7 @@ mod tests {
 
         if let Result::Ok(expression) = &mut expression {
             expression.simplify();
-            assert_eq!(format!("{}", expression), "(+ 2 (- 3))");
+            assert_eq!(format!("{}", expression), "-1");
         }
     }
 
@@ -519,7 +470,19 @@ mod tests {
 
         if let Result::Ok(expression) = &mut expression {
             expression.simplify();
-            assert_eq!(format!("{}", expression), "2");
+            assert_eq!(format!("{}", expression), "(- (^ x 2))");
+        }
+
+        let mut expression = expr!(
+            Multiplication,
+            expr!(Negation, sym!("x")),
+            sym!("y"),
+            expr!(Negation, sym!("x"))
+        );
+
+        if let Result::Ok(expression) = &mut expression {
+            expression.simplify();
+            assert_eq!(format!("{}", expression), "(* y (^ x 2))");
         }
     }
 
@@ -529,7 +492,163 @@ mod tests {
 
         if let Result::Ok(expression) = &mut expression {
             expression.simplify();
-            assert_eq!(format!("{}", expression), "(+ (* 2 x) y)");
+            assert_eq!(format!("{}", expression), "(+ y (* 2 x))");
+        }
+    }
+
+    #[test]
+    fn test_rational_folding() {
+        let mut expression = expr!(Reciprocal, expr!(3));
+
+        if let Result::Ok(expression) = &mut expression {
+            expression.simplify();
+            assert_eq!(format!("{}", expression), "1/3");
+        }
+
+        let mut expression = expr!(Division, expr!(6), expr!(4));
+
+        if let Result::Ok(expression) = &mut expression {
+            expression.simplify();
+            assert_eq!(format!("{}", expression), "3/2");
+        }
+    }
+
+    #[test]
+    fn test_rational_coefficients() {
+        let mut expression = expr!(
+            Addition,
+            expr!(
+                Multiplication,
+                expr!(Division, expr!(1), expr!(2)),
+                sym!("x")
+            ),
+            expr!(
+                Multiplication,
+                expr!(Division, expr!(1), expr!(3)),
+                sym!("x")
+            )
+        );
+
+        if let Result::Ok(expression) = &mut expression {
+            expression.simplify();
+            assert_eq!(format!("{}", expression), "(* 5/6 x)");
+        }
+
+        let mut expression = expr!(
+            Addition,
+            expr!(Multiplication, expr!(i128::MAX), sym!("y")),
+            expr!(Multiplication, expr!(i128::MAX), sym!("y"))
+        );
+
+        if let Result::Ok(expression) = &mut expression {
+            expression.simplify();
+            assert_eq!(
+                format!("{}", expression),
+                format!("(* {} y)", 2 * (i128::MAX as u128))
+            );
+        }
+    }
+
+    #[test]
+    fn test_constant_folding() {
+        let mut expression = expr!(
+            Addition,
+            expr!(Exponentiation, expr!(2), expr!(-3)),
+            expr!(Factorial, expr!(4)),
+            expr!(Negation, expr!(Root, expr!(3), expr!(-27))),
+            expr!(Logarithm, expr!(4), expr!(8))
+        );
+
+        if let Result::Ok(expression) = &mut expression {
+            expression.simplify();
+            assert_eq!(format!("{}", expression), "229/8");
+        }
+
+        let mut expression = expr!(
+            Multiplication,
+            expr!(2),
+            expr!(Root, expr!(2), expr!(2)),
+            expr!(Division, expr!(1), expr!(2))
+        );
+
+        if let Result::Ok(expression) = &mut expression {
+            expression.simplify();
+            assert_eq!(format!("{}", expression), "(root 2 2)");
+        }
+
+        let mut expression = expr!(
+            Multiplication,
+            expr!(2),
+            sym!("x"),
+            expr!(Root, expr!(2), expr!(2)),
+            expr!(Division, expr!(1), expr!(2))
+        );
+
+        if let Result::Ok(expression) = &mut expression {
+            expression.simplify();
+            assert_eq!(format!("{}", expression), "(* x (root 2 2))");
+        }
+    }
+
+    #[test]
+    fn test_canonical_order() {
+        let mut a = Expression::parse("y * 3 + log(2, x) + x + 1").unwrap();
+        let mut b = Expression::parse("1 + x + log(2, x) + 3 * y").unwrap();
+        a.simplify();
+        b.simplify();
+        assert_eq!(a, b);
+        assert_eq!(a.to_infix(), "1 + x + 3 * y + log(2, x)");
+        assert!(Expression::integer(5) < Expression::parse("a").unwrap());
+        assert!(Expression::parse("z").unwrap() < Expression::parse("-a").unwrap());
+    }
+
+    #[test]
+    fn test_equivalent() {
+        let parse = |input: &str| Expression::parse(input).unwrap();
+        assert!(equivalent(&parse("x + y"), &parse("y + x")));
+        assert!(equivalent(&parse("(x + 1) * (x - 1)"), &parse("x^2 - 1")));
+        assert!(equivalent(
+            &parse("2 * (a + b) / 4"),
+            &parse("a / 2 + b / 2")
+        ));
+        assert!(!equivalent(&parse("x - y"), &parse("y - x")));
+    }
+
+    #[test]
+    fn test_power_merge() {
+        let mut expression = expr!(
+            Multiplication,
+            sym!("x"),
+            expr!(Exponentiation, sym!("x"), expr!(2))
+        );
+
+        if let Result::Ok(expression) = &mut expression {
+            expression.simplify();
+            assert_eq!(format!("{}", expression), "(^ x 3)");
+        }
+
+        // the base of 2 ^ x is not a coefficient of x
+        let mut expression = expr!(
+            Multiplication,
+            expr!(Exponentiation, expr!(2), sym!("x")),
+            expr!(Exponentiation, expr!(2), sym!("x"))
+        );
+
+        if let Result::Ok(expression) = &mut expression {
+            expression.simplify();
+            assert_eq!(format!("{}", expression), "(^ (^ 2 x) 2)");
+        }
+
+        let mut expression = expr!(
+            Multiplication,
+            sym!("x"),
+            expr!(Exponentiation, sym!("x"), expr!(2)),
+            expr!(Exponentiation, expr!(2), sym!("x"))
+        );
+
+        if let Result::Ok(expression) = &mut expression {
+            expression.simplify();
+            assert_eq!(format!("{}", expression), "(* (^ 2 x) (^ x 3))");
         }
     }
 }
@@ -0,0 +1,438 @@
+use core::fmt;
+use std::ops::Range;
+
+use crate::rational::{decimal_length, parse_decimal, Rational};
+use crate::{ConstructExpression, Expression, Operator, OperatorExpression, Symbol};
+
+// error with the byte range of the input it refers to
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ParseError {
+    pub message: String,
+    pub span: Range<usize>,
+}
+
+impl ParseError {
+    pub(crate) fn new(message: String, span: Range<usize>) -> Self {
+        Self { message, span }
+    }
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "{} (at {}..{})",
+            self.message, self.span.start, self.span.end
+        )
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+enum TokenKind {
+    Number(Rational),
+    Identifier(String),
+    Plus,
+    Minus,
+    Star,
+    Slash,
+    Caret,
+    Bang,
+    LeftParenthesis,
+    RightParenthesis,
+    Comma,
+}
+
+impl fmt::Display for TokenKind {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            TokenKind::Number(number) => write!(f, "{}", number),
+            TokenKind::Identifier(name) => write!(f, "{}", name),
+            TokenKind::Plus => write!(f, "+"),
+            TokenKind::Minus => write!(f, "-"),
+            TokenKind::Star => write!(f, "*"),
+            TokenKind::Slash => write!(f, "/"),
+            TokenKind::Caret => write!(f, "^"),
+            TokenKind::Bang => write!(f, "!"),
+            TokenKind::LeftParenthesis => write!(f, "("),
+            TokenKind::RightParenthesis => write!(f, ")"),
+            TokenKind::Comma => write!(f, ","),
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+struct Token {
+    kind: TokenKind,
+    span: Range<usize>,
+}
+
+fn tokenize(input: &str) -> Result<Vec<Token>, ParseError> {
+    let mut tokens = Vec::new();
+    let mut chars = input.char_indices().peekable();
+    while let Some((start, c)) = chars.next() {
+        let single = match c {
+            '+' => Some(TokenKind::Plus),
+            '-' => Some(TokenKind::Minus),
+            '*' => Some(TokenKind::Star),
+            '/' => Some(TokenKind::Slash),
+            '^' => Some(TokenKind::Caret),
+            '!' => Some(TokenKind::Bang),
+            '(' => Some(TokenKind::LeftParenthesis),
+            ')' => Some(TokenKind::RightParenthesis),
+            ',' => Some(TokenKind::Comma),
+            _ => None,
+        };
+        if let Some(kind) = single {
+            tokens.push(Token {
+                kind,
+                span: start..start + 1,
+            });
+            continue;
+        }
+        if c.is_whitespace() {
+            continue;
+        }
+        // numbers are decimals with an optional SPICE suffix like 4.7k
+        if c.is_ascii_digit() {
+            let length = decimal_length(&input[start..]);
+            let suffix = input[start + length..]
+                .chars()
+                .take_while(|c| c.is_alphanumeric() || *c == '_')
+                .map(char::len_utf8)
+                .sum::<usize>();
+            let end = start + length + suffix;
+            let text = &input[start..end];
+            let number = parse_decimal(text)
+                .ok_or_else(|| ParseError::new(format!("Invalid number {}", text), start..end))?;
+            while chars.peek().is_some_and(|(index, _)| *index < end) {
+                chars.next();
+            }
+            tokens.push(Token {
+                kind: TokenKind::Number(number),
+                span: start..end,
+            });
+            continue;
+        }
+        // identifiers run until the first character that does not belong to them
+        // a leading ? marks the wildcards of rewrite rules, e.g. ?x, a leading # the wildcards
+        // that only match constants, e.g. #a
+        if !(c.is_alphabetic() || c == '_' || c == '?' || c == '#') {
+            return Err(ParseError::new(
+                format!("Unexpected character '{}'", c),
+                start..start + c.len_utf8(),
+            ));
+        }
+        let mut end = start + c.len_utf8();
+        while let Some((index, next)) = chars.peek() {
+            if !(next.is_alphanumeric() || *next == '_') {
+                break;
+            }
+            end = index + next.len_utf8();
+            chars.next();
+        }
+        tokens.push(Token {
+            kind: TokenKind::Identifier(input[start..end].to_string()),
+            span: start..end,
+        });
+    }
+    Ok(tokens)
+}
+
+struct Parser {
+    tokens: Vec<Token>,
+    position: usize,
+    length: usize,
+}
+
+impl Parser {
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.position)
+    }
+
+    fn next(&mut self) -> Option<Token> {
+        let token = self.tokens.get(self.position).cloned();
+        self.position += 1;
+        token
+    }
+
+    fn end_span(&self) -> Range<usize> {
+        self.length..self.length
+    }
+
+    fn expect(&mut self, kind: TokenKind, context: &str) -> Result<Token, ParseError> {
+        match self.next() {
+            Some(token) if token.kind == kind => Ok(token),
+            Some(token) => Err(ParseError::new(
+                format!("Expected '{}' {}, found '{}'", kind, context, token.kind),
+                token.span,
+            )),
+            None => Err(ParseError::new(
+                format!("Expected '{}' {}, found the end", kind, context),
+                self.end_span(),
+            )),
+        }
+    }
+
+    fn binary_operator(&self) -> Option<Operator> {
+        match self.peek().map(|token| &token.kind) {
+            Some(TokenKind::Plus) => Some(Operator::Addition),
+            Some(TokenKind::Minus) => Some(Operator::Subtraction),
+            Some(TokenKind::Star) => Some(Operator::Multiplication),
+            Some(TokenKind::Slash) => Some(Operator::Division),
+            Some(TokenKind::Caret) => Some(Operator::Exponentiation),
+            _ => None,
+        }
+    }
+
+    // precedence climbing over Operator::precedence and Operator::is_left_associative
+    fn expression(&mut self, min_precedence: u8) -> Result<(Expression, Range<usize>), ParseError> {
+        let (mut left, mut span) = self.prefix()?;
+        while let Some(operator) = self.binary_operator() {
+            let precedence = operator.precedence();
+            if precedence < min_precedence {
+                break;
+            }
+            self.next();
+            let next_precedence = if operator.is_left_associative() {
+                precedence + 1
+            } else {
+                precedence
+            };
+            let (right, right_span) = self.expression(next_precedence)?;
+            span = span.start..right_span.end;
+            left = combine(operator, left, right, &span)?;
+        }
+        Ok((left, span))
+    }
+
+    // prefix negation, binds with the precedence of Operator::Negation
+    fn prefix(&mut self) -> Result<(Expression, Range<usize>), ParseError> {
+        match self
+            .peek()
+            .map(|token| (token.kind.clone(), token.span.clone()))
+        {
+            Some((TokenKind::Minus, span)) => {
+                self.next();
+                let (operand, operand_span) = self.expression(Operator::Negation.precedence())?;
+                let span = span.start..operand_span.end;
+                let expression = build(Operator::Negation, vec![operand], &span)?;
+                Ok((expression, span))
+            }
+            Some((TokenKind::Plus, span)) => {
+                self.next();
+                let (operand, operand_span) = self.expression(Operator::Negation.precedence())?;
+                Ok((operand, span.start..operand_span.end))
+            }
+            _ => self.postfix(),
+        }
+    }
+
+    fn postfix(&mut self) -> Result<(Expression, Range<usize>), ParseError> {
+        let (mut expression, mut span) = self.primary()?;
+        while let Some(TokenKind::Bang) = self.peek().map(|token| &token.kind) {
+            let token = self.next().unwrap();
+            span = span.start..token.span.end;
+            expression = build(Operator::Factorial, vec![expression], &span)?;
+        }
+        Ok((expression, span))
+    }
+
+    fn primary(&mut self) -> Result<(Expression, Range<usize>), ParseError> {
+        let token = self.next().ok_or_else(|| {
+            ParseError::new(
+                "Expected an expression, found the end".to_string(),
+                self.end_span(),
+            )
+        })?;
+        match token.kind {
+            TokenKind::Number(number) => Ok((Expression::Constant(number), token.span)),
+            TokenKind::Identifier(name) => {
+                if let Some(TokenKind::LeftParenthesis) = self.peek().map(|token| &token.kind) {
+                    self.function(name, token.span)
+                } else {
+                    Ok((Expression::Symbol(Symbol::new(name)), token.span))
+                }
+            }
+            TokenKind::LeftParenthesis => {
+                let (expression, _) = self.expression(0)?;
+                let closing = self.expect(
+                    TokenKind::RightParenthesis,
+                    &format!("to close the parenthesis at {}", token.span.start),
+                )?;
+                Ok((expression, token.span.start..closing.span.end))
+            }
+            kind => Err(ParseError::new(
+                format!("Expected an expression, found '{}'", kind),
+                token.span,
+            )),
+        }
+    }
+
+    // log(base, x), root(n, x) and sqrt(x)
+    fn function(
+        &mut self,
+        name: String,
+        name_span: Range<usize>,
+    ) -> Result<(Expression, Range<usize>), ParseError> {
+        self.expect(TokenKind::LeftParenthesis, "after the function name")?;
+        let mut arguments = Vec::new();
+        loop {
+            arguments.push(self.expression(0)?.0);
+            match self.next() {
+                Some(Token {
+                    kind: TokenKind::Comma,
+                    ..
+                }) => continue,
+                Some(Token {
+                    kind: TokenKind::RightParenthesis,
+                    span,
+                }) => {
+                    let span = name_span.start..span.end;
+                    let (operator, arguments) = match name.as_str() {
+                        "log" => (Operator::Logarithm, arguments),
+                        "root" => (Operator::Root, arguments),
+                        "sqrt" if arguments.len() == 1 => (
+                            Operator::Root,
+                            vec![Expression::integer(2), arguments[0].clone()],
+                        ),
+                        "sqrt" => {
+                            return Err(ParseError::new(
+                                format!(
+                                    "sqrt expects 1 argument, but {} were provided",
+                                    arguments.len()
+                                ),
+                                span,
+                            ))
+                        }
+                        _ => {
+                            return Err(ParseError::new(
+                                format!("Unknown function {}", name),
+                                name_span,
+                            ))
+                        }
+                    };
+                    return Ok((build(operator, arguments, &span)?, span));
+                }
+                Some(token) => {
+                    return Err(ParseError::new(
+                        format!(
+                            "Expected ',' or ')' in the call of {}, found '{}'",
+                            name, token.kind
+                        ),
+                        token.span,
+                    ))
+                }
+                None => {
+                    return Err(ParseError::new(
+                        format!("Expected ',' or ')' in the call of {}, found the end", name),
+                        self.end_span(),
+                    ))
+                }
+            }
+        }
+    }
+}
+
+fn build(
+    operator: Operator,
+    operands: Vec<Expression>,
+    span: &Range<usize>,
+) -> Result<Expression, ParseError> {
+    OperatorExpression::new(operator, operands.into_iter().map(Ok).collect())
+        .construct_expression()
+        .map_err(|message| ParseError::new(message, span.clone()))
+}
+
+// chains of the same left associative operator become one n-ary expression: a - b - c
+fn combine(
+    operator: Operator,
+    left: Expression,
+    right: Expression,
+    span: &Range<usize>,
+) -> Result<Expression, ParseError> {
+    if let Expression::OperatorExpression(mut left) = left {
+        if left.operator == operator && operator.is_left_associative() {
+            left.operands.push(right);
+            return Ok(Expression::OperatorExpression(left));
+        }
+        return build(
+            operator,
+            vec![Expression::OperatorExpression(left), right],
+            span,
+        );
+    }
+    build(operator, vec![left, right], span)
+}
+
+impl Expression {
+    // infix notation with + - * / ^ !, prefix negation, log(base, x), root(n, x) and sqrt(x)
+    // the binding strength follows Operator::precedence, so -x^2 is (-x)^2
+    pub fn parse(input: &str) -> Result<Expression, ParseError> {
+        let mut parser = Parser {
+            tokens: tokenize(input)?,
+            position: 0,
+            length: input.len(),
+        };
+        let (expression, _) = parser.expression(0)?;
+        if let Some(token) = parser.next() {
+            return Err(ParseError::new(
+                format!("Unexpected '{}'", token.kind),
+                token.span,
+            ));
+        }
+        Ok(expression)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parsed(input: &str) -> String {
+        format!("{}", Expression::parse(input).unwrap())
+    }
+
+    #[test]
+    fn test_precedence() {
+        assert_eq!(
+            parsed("2*x + y^2 - log(10, z)"),
+            "(- (+ (* 2 x) (^ y 2)) (log 10 z))"
+        );
+        assert_eq!(parsed("(a + b) * c"), "(* (+ a b) c)");
+        assert_eq!(parsed("-x^2"), "(^ (- x) 2)");
+        assert_eq!(parsed("n! / sqrt(x)"), "(/ (! n) (root 2 x))");
+    }
+
+    #[test]
+    fn test_associativity() {
+        assert_eq!(parsed("a - b - c"), "(- a b c)");
+        assert_eq!(parsed("a - (b - c)"), "(- a (- b c))");
+        assert_eq!(parsed("a ^ b ^ c"), "(^ a (^ b c))");
+    }
+
+    #[test]
+    fn test_numbers() {
+        assert_eq!(parsed("4.7k * x"), "(* 4700 x)");
+        assert_eq!(parsed("1.5e-3"), "3/2000");
+        assert_eq!(
+            parsed("999999999999999999999999999999999999999999"),
+            "999999999999999999999999999999999999999999"
+        );
+    }
+
+    #[test]
+    fn test_errors() {
+        let error = Expression::parse("2 * (x + 1").err().unwrap();
+        assert_eq!(error.span, 10..10);
+        let error = Expression::parse("x + foo(1)").err().unwrap();
+        assert_eq!(error.span, 4..7);
+        let error = Expression::parse("log(x)").err().unwrap();
+        assert_eq!(error.span, 0..6);
+        let error = Expression::parse("x y").err().unwrap();
+        assert_eq!(error.span, 2..3);
+        let error = Expression::parse("1 + 2x").err().unwrap();
+        assert_eq!(error.span, 4..6);
+        let error = Expression::parse("x $ 2").err().unwrap();
+        assert_eq!(error.span, 2..3);
+    }
+}
@@ -0,0 +1,939 @@
+use core::fmt;
+use num::{BigInt, Integer, One, Signed, Zero};
+use std::cmp::Ordering;
+use std::collections::btree_map::Entry;
+use std::collections::BTreeMap;
+use std::ops::{Add, Neg, Sub};
+
+use crate::rational::{self, Rational};
+use crate::{Expression, Operator, Symbol};
+
+// largest magnitude of an exponent that products and powers of user formulas may reach
+pub const MAX_EXPONENT: i32 = 1 << 20;
+// powers of sums are only multiplied out while they have at most this many terms
+pub const MAX_POWER_TERMS: u128 = 1000;
+
+// product of symbols with integer exponents, negative exponents allow terms like 1/R
+#[derive(Clone, Debug, Default, PartialEq, Eq, Hash)]
+pub struct Monomial {
+    // exponents of 0 are never stored
+    exponents: BTreeMap<Symbol, i32>,
+}
+
+impl Monomial {
+    pub fn one() -> Self {
+        Self::default()
+    }
+
+    pub fn symbol(symbol: Symbol, exponent: i32) -> Self {
+        let mut monomial = Self::one();
+        if exponent != 0 {
+            monomial.exponents.insert(symbol, exponent);
+        }
+        monomial
+    }
+
+    pub fn is_one(&self) -> bool {
+        self.exponents.is_empty()
+    }
+
+    pub fn degree(&self) -> i32 {
+        self.exponents.values().sum()
+    }
+
+    pub fn exponent(&self, symbol: &Symbol) -> i32 {
+        self.exponents.get(symbol).copied().unwrap_or(0)
+    }
+
+    pub fn exponents(&self) -> impl Iterator<Item = (&Symbol, i32)> {
+        self.exponents
+            .iter()
+            .map(|(symbol, exponent)| (symbol, *exponent))
+    }
+
+    // applies the operation to the exponents of every symbol of both monomials, None if it
+    // overflows
+    fn combine(
+        &self,
+        other: &Monomial,
+        operation: impl Fn(i32, i32) -> Option<i32>,
+    ) -> Option<Monomial> {
+        let mut exponents = BTreeMap::new();
+        for symbol in self.exponents.keys().chain(other.exponents.keys()) {
+            let exponent = operation(self.exponent(symbol), other.exponent(symbol))?;
+            if exponent != 0 {
+                exponents.insert(symbol.clone(), exponent);
+            }
+        }
+        Some(Monomial { exponents })
+    }
+
+    fn is_bounded(&self) -> bool {
+        self.exponents
+            .values()
+            .all(|exponent| exponent.unsigned_abs() <= MAX_EXPONENT.unsigned_abs())
+    }
+
+    // None if an exponent of the product exceeds MAX_EXPONENT
+    pub fn multiply(&self, other: &Monomial) -> Option<Monomial> {
+        self.combine(other, i32::checked_add)
+            .filter(Monomial::is_bounded)
+    }
+
+    // None if an exponent of the quotient exceeds MAX_EXPONENT
+    pub fn divide(&self, other: &Monomial) -> Option<Monomial> {
+        self.combine(other, i32::checked_sub)
+            .filter(Monomial::is_bounded)
+    }
+
+    // None if an exponent of the power exceeds MAX_EXPONENT
+    pub fn pow(&self, exponent: u32) -> Option<Monomial> {
+        let exponent = i32::try_from(exponent).ok()?;
+        let mut exponents = BTreeMap::new();
+        for (symbol, value) in self.exponents.iter() {
+            let value = value.checked_mul(exponent)?;
+            if value != 0 {
+                exponents.insert(symbol.clone(), value);
+            }
+        }
+        Some(Monomial { exponents }).filter(Monomial::is_bounded)
+    }
+
+    // the same monomial with the exponent of the symbol replaced
+    fn with_exponent(&self, symbol: &Symbol, exponent: i32) -> Monomial {
+        let mut monomial = self.clone();
+        match exponent {
+            0 => monomial.exponents.remove(symbol),
+            _ => monomial.exponents.insert(symbol.clone(), exponent),
+        };
+        monomial
+    }
+
+    // whether no symbol has a negative exponent
+    pub fn is_polynomial(&self) -> bool {
+        self.exponents.values().all(|exponent| *exponent > 0)
+    }
+
+    // whether other / self is a monomial without negative exponents
+    pub fn divides(&self, other: &Monomial) -> bool {
+        self.exponents
+            .iter()
+            .all(|(symbol, exponent)| *exponent <= other.exponent(symbol))
+    }
+
+    // the common factor with the smallest exponents, e.g. gcd(x^2 y, x / y) = x / y
+    pub fn gcd(&self, other: &Monomial) -> Monomial {
+        self.combine(other, |a, b| Some(a.min(b)))
+            .expect("the smaller exponent exists")
+    }
+
+    pub fn to_expression(&self) -> Expression {
+        let mut factors = self
+            .exponents
+            .iter()
+            .map(|(symbol, exponent)| match exponent {
+                1 => Expression::Symbol(symbol.clone()),
+                _ => Expression::operation(
+                    Operator::Exponentiation,
+                    vec![
+                        Expression::Symbol(symbol.clone()),
+                        Expression::integer(*exponent as i128),
+                    ],
+                ),
+            })
+            .collect::<Vec<Expression>>();
+        match factors.len() {
+            0 => Expression::integer(1),
+            1 => factors.remove(0),
+            _ => Expression::operation(Operator::Multiplication, factors),
+        }
+    }
+}
+
+// graded lexicographic order: higher degrees first, then higher exponents of the first symbols
+impl Ord for Monomial {
+    fn cmp(&self, other: &Self) -> Ordering {
+        other.degree().cmp(&self.degree()).then_with(|| {
+            let symbols = self
+                .exponents
+                .keys()
+                .chain(other.exponents.keys())
+                .collect::<std::collections::BTreeSet<&Symbol>>();
+            symbols
+                .into_iter()
+                .map(|symbol| other.exponent(symbol).cmp(&self.exponent(symbol)))
+                .find(|ordering| ordering.is_ne())
+                .unwrap_or(Ordering::Equal)
+        })
+    }
+}
+
+impl PartialOrd for Monomial {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+// sum of monomials with rational coefficients
+#[derive(Clone, Debug, Default, PartialEq, Eq, Hash)]
+pub struct Polynomial {
+    // coefficients of 0 are never stored
+    terms: BTreeMap<Monomial, Rational>,
+}
+
+impl Polynomial {
+    pub fn zero() -> Self {
+        Self::default()
+    }
+
+    pub fn one() -> Self {
+        Self::constant(Rational::one())
+    }
+
+    pub fn constant(value: Rational) -> Self {
+        Self::term(value, Monomial::one())
+    }
+
+    pub fn symbol(symbol: Symbol) -> Self {
+        Self::term(Rational::one(), Monomial::symbol(symbol, 1))
+    }
+
+    pub fn term(coefficient: Rational, monomial: Monomial) -> Self {
+        let mut polynomial = Self::zero();
+        polynomial.add_term(coefficient, monomial);
+        polynomial
+    }
+
+    fn add_term(&mut self, coefficient: Rational, monomial: Monomial) {
+        if coefficient.is_zero() {
+            return;
+        }
+        match self.terms.entry(monomial) {
+            Entry::Vacant(entry) => {
+                entry.insert(coefficient);
+            }
+            Entry::Occupied(mut entry) => {
+                *entry.get_mut() += coefficient;
+                if entry.get().is_zero() {
+                    entry.remove();
+                }
+            }
+        }
+    }
+
+    pub fn is_zero(&self) -> bool {
+        self.terms.is_empty()
+    }
+
+    // the value if the polynomial does not contain any symbol
+    pub fn as_constant(&self) -> Option<Rational> {
+        match self.terms.len() {
+            0 => Some(Rational::zero()),
+            1 => self.terms.get(&Monomial::one()).cloned(),
+            _ => None,
+        }
+    }
+
+    // in graded lexicographic order, the leading term first
+    pub fn terms(&self) -> impl Iterator<Item = (&Monomial, &Rational)> {
+        self.terms.iter()
+    }
+
+    pub fn leading_coefficient(&self) -> Option<&Rational> {
+        self.terms.values().next()
+    }
+
+    // the monomial that divides every term, None for the zero polynomial
+    pub fn monomial_gcd(&self) -> Option<Monomial> {
+        self.terms
+            .keys()
+            .cloned()
+            .reduce(|gcd, monomial| gcd.gcd(&monomial))
+    }
+
+    // the positive rational that divides every coefficient to an integer without common factors
+    pub fn content(&self) -> Rational {
+        let mut numerators = BigInt::zero();
+        let mut denominators = BigInt::one();
+        for coefficient in self.terms.values() {
+            numerators = numerators.gcd(coefficient.numer());
+            denominators = denominators.lcm(coefficient.denom());
+        }
+        if numerators.is_zero() {
+            return Rational::one();
+        }
+        Rational::new(numerators, denominators)
+    }
+
+    // the highest exponent of the symbol, None for the zero polynomial
+    pub fn degree_in(&self, symbol: &Symbol) -> Option<i32> {
+        self.terms
+            .keys()
+            .map(|monomial| monomial.exponent(symbol))
+            .max()
+    }
+
+    // the factor of symbol^exponent, e.g. the coefficient of s in a * s^2 + b * s + c is b
+    pub fn coefficient(&self, symbol: &Symbol, exponent: i32) -> Polynomial {
+        let mut coefficient = Polynomial::zero();
+        for (monomial, value) in self.terms.iter() {
+            if monomial.exponent(symbol) == exponent {
+                coefficient.add_term(value.clone(), monomial.with_exponent(symbol, 0));
+            }
+        }
+        coefficient
+    }
+
+    pub fn scale(&self, factor: &Rational) -> Polynomial {
+        let mut polynomial = Polynomial::zero();
+        for (monomial, coefficient) in self.terms.iter() {
+            polynomial.add_term(coefficient * factor, monomial.clone());
+        }
+        polynomial
+    }
+
+    // None if an exponent exceeds MAX_EXPONENT
+    pub fn multiply_monomial(&self, monomial: &Monomial) -> Option<Polynomial> {
+        let mut polynomial = Polynomial::zero();
+        for (term, coefficient) in self.terms.iter() {
+            polynomial.add_term(coefficient.clone(), term.multiply(monomial)?);
+        }
+        Some(polynomial)
+    }
+
+    // None if an exponent exceeds MAX_EXPONENT, also products that multiply a term of each
+    pub fn checked_mul(&self, other: &Polynomial) -> Option<Polynomial> {
+        let mut product = Polynomial::zero();
+        for (monomial, coefficient) in self.terms.iter() {
+            for (other_monomial, other_coefficient) in other.terms.iter() {
+                product.add_term(
+                    coefficient * other_coefficient,
+                    monomial.multiply(other_monomial)?,
+                );
+            }
+        }
+        Some(product)
+    }
+
+    // single terms raise their monomial, binomials use the binomial theorem and other sums
+    // square and multiply, None if an exponent exceeds MAX_EXPONENT, a coefficient would
+    // exceed rational::MAX_BITS or the power of a sum would have more than MAX_POWER_TERMS terms
+    pub fn pow(&self, exponent: u32) -> Option<Polynomial> {
+        if exponent > MAX_EXPONENT.unsigned_abs()
+            || power_terms(self.terms.len(), exponent) > MAX_POWER_TERMS
+            || !self
+                .terms
+                .values()
+                .all(|coefficient| power_fits(coefficient, exponent))
+        {
+            return None;
+        }
+        let mut terms = self.terms.iter();
+        match (terms.next(), terms.next(), terms.next()) {
+            (Some((monomial, coefficient)), None, _) => {
+                let monomial = monomial.pow(exponent)?;
+                Some(Polynomial::term(
+                    num::pow(coefficient.clone(), exponent as usize),
+                    monomial,
+                ))
+            }
+            (Some((a, a_coefficient)), Some((b, b_coefficient)), None) => {
+                // (a + b)^n = sum of n! / (k! (n - k)!) a^(n - k) b^k
+                let mut power = Polynomial::zero();
+                let mut binomial = BigInt::one();
+                for k in 0..=exponent {
+                    let coefficient = Rational::from_integer(binomial.clone())
+                        * num::pow(a_coefficient.clone(), (exponent - k) as usize)
+                        * num::pow(b_coefficient.clone(), k as usize);
+                    let monomial = a.pow(exponent - k)?.multiply(&b.pow(k)?)?;
+                    power.add_term(coefficient, monomial);
+                    binomial = binomial * (exponent - k) / (k + 1);
+                }
+                Some(power)
+            }
+            _ => {
+                let (mut power, mut square, mut rest) = (Polynomial::one(), self.clone(), exponent);
+                while rest > 0 {
+                    if rest & 1 == 1 {
+                        power = power.checked_mul(&square)?;
+                    }
+                    rest >>= 1;
+                    if rest > 0 {
+                        square = square.checked_mul(&square)?;
+                    }
+                }
+                Some(power)
+            }
+        }
+    }
+
+    // whether no term has a negative exponent
+    pub fn is_polynomial(&self) -> bool {
+        self.terms.keys().all(Monomial::is_polynomial)
+    }
+
+    fn leading_term(&self) -> Option<(&Monomial, &Rational)> {
+        self.terms.iter().next()
+    }
+
+    // 1 / self if it is a single term
+    pub(crate) fn inverse(&self) -> Option<Polynomial> {
+        match self.terms.len() {
+            1 => self.leading_term().and_then(|(monomial, coefficient)| {
+                Some(Polynomial::term(
+                    coefficient.recip(),
+                    Monomial::one().divide(monomial)?,
+                ))
+            }),
+            _ => None,
+        }
+    }
+
+    // the coefficients of all powers of the symbol, e.g. a * s^2 + b * s + c * s^2 -> {1: b, 2: a + c}
+    pub fn collect(&self, symbol: &Symbol) -> BTreeMap<i32, Polynomial> {
+        let mut powers = BTreeMap::<i32, Polynomial>::new();
+        for (monomial, coefficient) in self.terms.iter() {
+            let exponent = monomial.exponent(symbol);
+            powers
+                .entry(exponent)
+                .or_default()
+                .add_term(coefficient.clone(), monomial.with_exponent(symbol, 0));
+        }
+        powers
+    }
+
+    // sum of the collected powers of the symbol, the highest power first
+    pub fn to_collected_expression(&self, symbol: &Symbol) -> Expression {
+        let mut terms = self
+            .collect(symbol)
+            .into_iter()
+            .rev()
+            .map(|(exponent, coefficient)| match coefficient.leading_term() {
+                _ if exponent == 0 => coefficient.to_expression(),
+                // the coefficient has no power of the symbol, so the exponent can be put in
+                Some((monomial, value)) if coefficient.terms.len() == 1 => {
+                    Polynomial::term(value.clone(), monomial.with_exponent(symbol, exponent))
+                        .to_expression()
+                }
+                _ => Expression::operation(
+                    Operator::Multiplication,
+                    vec![
+                        coefficient.to_expression(),
+                        Monomial::symbol(symbol.clone(), exponent).to_expression(),
+                    ],
+                ),
+            })
+            .collect::<Vec<Expression>>();
+        match terms.len() {
+            0 => Expression::integer(0),
+            1 => terms.remove(0),
+            _ => Expression::operation(Operator::Addition, terms),
+        }
+    }
+
+    // quotient and remainder of the division in graded lexicographic order, the remainder
+    // has no term that is divisible by the leading term of the divisor
+    pub fn div_rem(&self, divisor: &Polynomial) -> Result<(Polynomial, Polynomial), String> {
+        let (divisor_monomial, divisor_coefficient) = divisor
+            .leading_term()
+            .ok_or("Division by the zero polynomial")?;
+        if !(self.is_polynomial() && divisor.is_polynomial()) {
+            return Err("Division of polynomials with negative exponents".to_string());
+        }
+        let mut quotient = Polynomial::zero();
+        let mut remainder = Polynomial::zero();
+        let mut rest = self.clone();
+        while let Some((monomial, coefficient)) = rest.leading_term() {
+            let term = Polynomial::term(coefficient.clone(), monomial.clone());
+            if divisor_monomial.divides(monomial) {
+                let factor = coefficient / divisor_coefficient;
+                let monomial = monomial
+                    .divide(divisor_monomial)
+                    .ok_or_else(exponent_error)?;
+                let subtrahend = divisor
+                    .scale(&factor)
+                    .multiply_monomial(&monomial)
+                    .ok_or_else(exponent_error)?;
+                rest = &rest - &subtrahend;
+                quotient.add_term(factor, monomial);
+            } else {
+                rest = &rest - &term;
+                remainder = &remainder + &term;
+            }
+        }
+        Ok((quotient, remainder))
+    }
+
+    // self / divisor if the division leaves no remainder, negative exponents are allowed
+    pub fn divide_exact(&self, divisor: &Polynomial) -> Option<Polynomial> {
+        if self.is_zero() {
+            return (!divisor.is_zero()).then(Polynomial::zero);
+        }
+        // without their monomial factors both sides have no negative exponents
+        let (monomial, divisor_monomial) = (self.monomial_gcd()?, divisor.monomial_gcd()?);
+        let dividend = self.multiply_monomial(&Monomial::one().divide(&monomial)?)?;
+        let divisor = divisor.multiply_monomial(&Monomial::one().divide(&divisor_monomial)?)?;
+        match dividend.div_rem(&divisor) {
+            Ok((quotient, remainder)) if remainder.is_zero() => {
+                quotient.multiply_monomial(&monomial.divide(&divisor_monomial)?)
+            }
+            _ => None,
+        }
+    }
+
+    // greatest common divisor with integer coefficients and a positive leading coefficient,
+    // the monomial part follows Monomial::gcd so negative exponents are allowed, None if an
+    // exponent on the way exceeds MAX_EXPONENT
+    pub fn gcd(&self, other: &Polynomial) -> Option<Polynomial> {
+        let (Some(monomial), Some(other_monomial)) = (self.monomial_gcd(), other.monomial_gcd())
+        else {
+            return Some(if self.is_zero() { other } else { self }.normalized());
+        };
+        let a = self.multiply_monomial(&Monomial::one().divide(&monomial)?)?;
+        let b = other.multiply_monomial(&Monomial::one().divide(&other_monomial)?)?;
+        let gcd = polynomial_gcd(&a, &b)?.multiply_monomial(&monomial.gcd(&other_monomial))?;
+        Some(gcd.normalized())
+    }
+
+    // divided by its content, with a positive leading coefficient
+    fn normalized(&self) -> Polynomial {
+        let mut factor = self.content().recip();
+        if self
+            .leading_coefficient()
+            .is_some_and(Rational::is_negative)
+        {
+            factor = -factor;
+        }
+        self.scale(&factor)
+    }
+
+    pub fn from_expression(expression: &Expression) -> Result<Polynomial, String> {
+        let operator_expression = match expression {
+            Expression::Constant(constant) => return Ok(Polynomial::constant(constant.clone())),
+            Expression::Symbol(symbol) => return Ok(Polynomial::symbol(symbol.clone())),
+            Expression::OperatorExpression(operator_expression) => operator_expression,
+        };
+        let operands = operator_expression
+            .operands
+            .iter()
+            .map(Polynomial::from_expression)
+            .collect::<Result<Vec<Polynomial>, String>>()?;
+        let not_polynomial = || format!("{} is not a polynomial", expression.to_infix());
+        let mut rest = operands.iter().skip(1);
+        match operator_expression.operator {
+            Operator::Negation => Ok(-&operands[0]),
+            Operator::Reciprocal => operands[0].inverse().ok_or_else(not_polynomial),
+            Operator::Addition => Ok(rest.fold(operands[0].clone(), |sum, operand| &sum + operand)),
+            Operator::Subtraction => {
+                Ok(rest.fold(operands[0].clone(), |sum, operand| &sum - operand))
+            }
+            Operator::Multiplication => rest.try_fold(operands[0].clone(), |product, operand| {
+                product.checked_mul(operand).ok_or_else(not_polynomial)
+            }),
+            // only division by single terms, e.g. x / (2 * y)
+            Operator::Division => rest.try_fold(operands[0].clone(), |quotient, operand| {
+                operand
+                    .inverse()
+                    .and_then(|inverse| quotient.checked_mul(&inverse))
+                    .ok_or_else(not_polynomial)
+            }),
+            Operator::Exponentiation => {
+                let exponent = operands[1]
+                    .as_constant()
+                    .filter(|exponent| exponent.is_integer())
+                    .and_then(|exponent| exponent.to_integer().try_into().ok())
+                    .ok_or_else(not_polynomial)?;
+                let base = match exponent {
+                    0.. => operands[0].clone(),
+                    _ => operands[0].inverse().ok_or_else(not_polynomial)?,
+                };
+                base.pow(i32::unsigned_abs(exponent))
+                    .ok_or_else(not_polynomial)
+            }
+            Operator::Factorial | Operator::Root | Operator::Logarithm => Err(not_polynomial()),
+        }
+    }
+
+    // negative terms after the first become negations, so the infix form reads x - y
+    pub fn to_expression(&self) -> Expression {
+        let mut terms = self
+            .terms
+            .iter()
+            .enumerate()
+            .map(|(index, (monomial, coefficient))| {
+                // the sign of the first term stays in its coefficient, e.g. -2 * x + y
+                let signed = index == 0 && !(monomial.is_one() || coefficient.abs().is_one());
+                let magnitude = if signed {
+                    coefficient.clone()
+                } else {
+                    coefficient.abs()
+                };
+                let term = if monomial.is_one() {
+                    Expression::Constant(magnitude)
+                } else if magnitude.is_one() {
+                    monomial.to_expression()
+                } else {
+                    let mut factors = vec![Expression::Constant(magnitude)];
+                    match monomial.to_expression() {
+                        Expression::OperatorExpression(product)
+                            if product.operator == Operator::Multiplication =>
+                        {
+                            factors.extend(product.operands)
+                        }
+                        factor => factors.push(factor),
+                    }
+                    Expression::operation(Operator::Multiplication, factors)
+                };
+                if coefficient.is_negative() && !signed {
+                    Expression::operation(Operator::Negation, vec![term])
+                } else {
+                    term
+                }
+            })
+            .collect::<Vec<Expression>>();
+        match terms.len() {
+            0 => Expression::integer(0),
+            1 => terms.remove(0),
+            _ => Expression::operation(Operator::Addition, terms),
+        }
+    }
+}
+
+// the highest exponent of the symbol and its coefficient
+fn leading_coefficient_in(polynomial: &Polynomial, symbol: &Symbol) -> (i32, Polynomial) {
+    let degree = polynomial.degree_in(symbol).unwrap_or(0);
+    (degree, polynomial.coefficient(symbol, degree))
+}
+
+// number of monomials of the degree in as many symbols as the sum has terms, the most terms
+// its power can have, counting stops above MAX_POWER_TERMS
+fn power_terms(terms: usize, exponent: u32) -> u128 {
+    // (exponent + terms - 1)! / (exponent! (terms - 1)!)
+    let mut count: u128 = 1;
+    for k in 1..terms as u128 {
+        count = count * (exponent as u128 + k) / k;
+        if count > MAX_POWER_TERMS {
+            break;
+        }
+    }
+    count
+}
+
+// whether coefficient^exponent stays within rational::MAX_BITS, checked before computing it
+fn power_fits(coefficient: &Rational, exponent: u32) -> bool {
+    coefficient.abs().is_one()
+        || rational::bits(coefficient).saturating_mul(exponent as u64) <= rational::MAX_BITS
+}
+
+// gcd of the coefficients of the powers of the symbol
+fn content_in(polynomial: &Polynomial, symbol: &Symbol) -> Option<Polynomial> {
+    polynomial
+        .collect(symbol)
+        .values()
+        .try_fold(Polynomial::zero(), |gcd, coefficient| gcd.gcd(coefficient))
+}
+
+// the content divides the polynomial, None only if an exponent exceeds MAX_EXPONENT
+fn primitive_part_in(polynomial: &Polynomial, symbol: &Symbol) -> Option<Polynomial> {
+    if polynomial.is_zero() {
+        return Some(Polynomial::zero());
+    }
+    polynomial.divide_exact(&content_in(polynomial, symbol)?)
+}
+
+// a * lc(b)^k - q * b with a degree in the symbol below the one of b
+fn pseudo_remainder(a: &Polynomial, b: &Polynomial, symbol: &Symbol) -> Option<Polynomial> {
+    let (degree, leading) = leading_coefficient_in(b, symbol);
+    let mut remainder = a.clone();
+    while !remainder.is_zero() && remainder.degree_in(symbol).unwrap_or(0) >= degree {
+        let (remainder_degree, remainder_leading) = leading_coefficient_in(&remainder, symbol);
+        let shift = Monomial::symbol(symbol.clone(), remainder_degree - degree);
+        let subtrahend = remainder_leading
+            .multiply_monomial(&shift)?
+            .checked_mul(b)?;
+        remainder = &remainder.checked_mul(&leading)? - &subtrahend;
+    }
+    Some(remainder)
+}
+
+// gcd of two nonzero polynomials without negative exponents by recursion over the symbols,
+// the coefficients of the main symbol use the primitive remainder sequence
+fn polynomial_gcd(a: &Polynomial, b: &Polynomial) -> Option<Polynomial> {
+    let Some(symbol) = a
+        .terms
+        .keys()
+        .chain(b.terms.keys())
+        .flat_map(|monomial| monomial.exponents.keys())
+        .next()
+        .cloned()
+    else {
+        return Some(Polynomial::one());
+    };
+    let (a_content, b_content) = (content_in(a, &symbol)?, content_in(b, &symbol)?);
+    let content = a_content.gcd(&b_content)?;
+    let mut a = primitive_part_in(a, &symbol)?;
+    let mut b = primitive_part_in(b, &symbol)?;
+    if a.degree_in(&symbol) < b.degree_in(&symbol) {
+        std::mem::swap(&mut a, &mut b);
+    }
+    while !b.is_zero() {
+        let remainder = pseudo_remainder(&a, &b, &symbol)?;
+        a = b;
+        b = primitive_part_in(&remainder, &symbol)?;
+    }
+    content.checked_mul(&primitive_part_in(&a, &symbol)?)
+}
+
+fn exponent_error() -> String {
+    format!("Exponents beyond {} are not supported", MAX_EXPONENT)
+}
+
+impl Expression {
+    // sum of the powers of the symbol with their coefficients, e.g. a*s + b*s + 1 -> (a + b) * s + 1
+    pub fn collect(&self, symbol: &Symbol) -> Result<Expression, String> {
+        Ok(Polynomial::from_expression(self)?.to_collected_expression(symbol))
+    }
+}
+
+impl fmt::Display for Polynomial {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.to_expression().to_infix())
+    }
+}
+
+impl Add for &Polynomial {
+    type Output = Polynomial;
+
+    fn add(self, other: &Polynomial) -> Polynomial {
+        let mut sum = self.clone();
+        for (monomial, coefficient) in other.terms.iter() {
+            sum.add_term(coefficient.clone(), monomial.clone());
+        }
+        sum
+    }
+}
+
+impl Sub for &Polynomial {
+    type Output = Polynomial;
+
+    fn sub(self, other: &Polynomial) -> Polynomial {
+        self + &-other
+    }
+}
+
+impl Neg for &Polynomial {
+    type Output = Polynomial;
+
+    fn neg(self) -> Polynomial {
+        Polynomial {
+            terms: self
+                .terms
+                .iter()
+                .map(|(monomial, coefficient)| (monomial.clone(), -coefficient))
+                .collect(),
+        }
+    }
+}
+
+impl Add for Polynomial {
+    type Output = Polynomial;
+
+    fn add(self, other: Polynomial) -> Polynomial {
+        &self + &other
+    }
+}
+
+impl Sub for Polynomial {
+    type Output = Polynomial;
+
+    fn sub(self, other: Polynomial) -> Polynomial {
+        &self - &other
+    }
+}
+
+impl Neg for Polynomial {
+    type Output = Polynomial;
+
+    fn neg(self) -> Polynomial {
+        -&self
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn symbol(name: &str) -> Polynomial {
+        Polynomial::symbol(Symbol::new(name.to_string()))
+    }
+
+    fn integer(value: i64) -> Polynomial {
+        Polynomial::constant(Rational::from_integer(value.into()))
+    }
+
+    fn product(a: &Polynomial, b: &Polynomial) -> Polynomial {
+        a.checked_mul(b).unwrap()
+    }
+
+    #[test]
+    fn test_arithmetic() {
+        let x = symbol("x");
+        let y = symbol("y");
+        let square = (&x + &y).pow(2).unwrap();
+        assert_eq!(square.to_string(), "x^2 + 2 * x * y + y^2");
+        let difference = &square - &product(&x, &x);
+        assert_eq!(difference.to_string(), "2 * x * y + y^2");
+        assert!((&(&x - &y) + &(&y - &x)).is_zero());
+        assert_eq!(
+            (&integer(3) - &product(&x, &integer(2))).to_string(),
+            "-2 * x + 3"
+        );
+    }
+
+    #[test]
+    fn test_binomial_power() {
+        let binomial = &product(&symbol("x"), &integer(2)) - &symbol("y");
+        let fifth = (0..5).fold(Polynomial::one(), |power, _| product(&power, &binomial));
+        assert_eq!(binomial.pow(5).unwrap(), fifth);
+        assert_eq!(binomial.pow(0).unwrap(), Polynomial::one());
+        let trinomial = &binomial + &integer(1);
+        assert_eq!(trinomial.pow(2).unwrap(), product(&trinomial, &trinomial));
+    }
+
+    #[test]
+    fn test_monomial_gcd() {
+        let x = Symbol::new("x".to_string());
+        let y = Symbol::new("y".to_string());
+        let polynomial = &Polynomial::term(
+            Rational::from_integer(4.into()),
+            Monomial::symbol(x.clone(), 2)
+                .multiply(&Monomial::symbol(y.clone(), 1))
+                .unwrap(),
+        ) + &Polynomial::term(
+            Rational::new(2.into(), 3.into()),
+            Monomial::symbol(x.clone(), 1)
+                .multiply(&Monomial::symbol(y, -1))
+                .unwrap(),
+        );
+        let gcd = polynomial.monomial_gcd().unwrap();
+        assert_eq!(gcd.to_expression().to_infix(), "x * y^-1");
+        assert_eq!(polynomial.content(), Rational::new(2.into(), 3.into()));
+        assert_eq!(Polynomial::zero().monomial_gcd(), None);
+    }
+
+    fn parse(input: &str) -> Polynomial {
+        Polynomial::from_expression(&Expression::parse(input).unwrap()).unwrap()
+    }
+
+    #[test]
+    fn test_from_expression() {
+        assert_eq!(parse("(x - 1) * (x + 1)").to_string(), "x^2 - 1");
+        assert_eq!(
+            parse("-((a + b)^2) / (2 * c)").to_string(),
+            "-1/2 * a^2 * c^-1 - a * b * c^-1 - 1/2 * b^2 * c^-1"
+        );
+        assert_eq!(parse("x^-2 * x^3").to_string(), "x");
+        assert!(Polynomial::from_expression(&Expression::parse("1 / (x + 1)").unwrap()).is_err());
+        assert!(Polynomial::from_expression(&Expression::parse("x^y").unwrap()).is_err());
+    }
+
+    #[test]
+    fn test_large_powers() {
+        assert_eq!(parse("x^3000 * x^-1000").to_string(), "x^2000");
+        assert_eq!(parse("(2 * x)^1000 / 2^1000").to_string(), "x^1000");
+        assert_eq!(parse("(2 * x - 3)^100").terms().count(), 101);
+        for input in [
+            "x^2000000000",
+            "x^1000000 * x^1000000",
+            "(x + y + z)^1000",
+            "(2 * x)^1000000",
+            "(3^1000)^100000",
+            "(3^1000 * x + 1)^100",
+        ] {
+            assert!(Polynomial::from_expression(&Expression::parse(input).unwrap()).is_err());
+        }
+        let x = Monomial::symbol(Symbol::new("x".to_string()), MAX_EXPONENT);
+        assert_eq!(x.multiply(&x), None);
+        assert_eq!(x.pow(3), None);
+        assert_eq!(Monomial::one().divide(&x).unwrap().divide(&x), None);
+        let y = Monomial::symbol(Symbol::new("y".to_string()), i32::MAX);
+        assert_eq!(y.multiply(&y), None);
+        assert_eq!(
+            y.divide(&Monomial::symbol(Symbol::new("y".to_string()), -1)),
+            None
+        );
+        let x = Polynomial::term(Rational::one(), x);
+        assert_eq!(x.checked_mul(&x), None);
+        assert!(parse("a + 1").gcd(&x).is_some());
+        assert!(x.div_rem(&parse("a")).is_ok());
+    }
+
+    #[test]
+    fn test_division() {
+        let (quotient, remainder) = parse("x^3 - 2*x + 5").div_rem(&parse("x - 1")).unwrap();
+        assert_eq!(quotient.to_string(), "x^2 + x - 1");
+        assert_eq!(remainder.to_string(), "4");
+        let (quotient, remainder) = parse("x^2 * y + x * y^2 + y^2")
+            .div_rem(&parse("x * y - 1"))
+            .unwrap();
+        assert_eq!(
+            &product(&quotient, &parse("x * y - 1")) + &remainder,
+            parse("x^2 * y + x * y^2 + y^2")
+        );
+        assert_eq!(
+            parse("R1 * V1 + R2 * V1").divide_exact(&parse("R1 + R2")),
+            Some(parse("V1"))
+        );
+        assert_eq!(parse("x + 1").divide_exact(&parse("x - 1")), None);
+        assert_eq!(
+            parse("1 / R1 + 1 / R2").divide_exact(&parse("R1 + R2")),
+            Some(parse("1 / (R1 * R2)"))
+        );
+        assert!(parse("x").div_rem(&Polynomial::zero()).is_err());
+    }
+
+    #[test]
+    fn test_gcd() {
+        let gcd = |a: &str, b: &str| parse(a).gcd(&parse(b)).unwrap().to_string();
+        assert_eq!(gcd("x^2 - 1", "x^2 + 2*x + 1"), "x + 1");
+        assert_eq!(gcd("R1 * V1 + R2 * V1", "2 * R1 + 2 * R2"), "R1 + R2");
+        assert_eq!(
+            gcd("(a + b) * (a - b) * c", "(b - a) * c^2 * d"),
+            "a * c - b * c"
+        );
+        assert_eq!(gcd("x^2 * y + y", "x * z + z"), "1");
+        assert_eq!(gcd("6 * x", "4 * x^2"), "x");
+        assert_eq!(gcd("0", "-3 * x - 6"), "x + 2");
+    }
+
+    #[test]
+    fn test_collect() {
+        let s = Symbol::new("s".to_string());
+        let expression = Expression::parse("a*s^2 + b*s + c*s^2 + s + 1").unwrap();
+        assert_eq!(
+            expression.collect(&s).unwrap().to_infix(),
+            "(a + c) * s^2 + (b + 1) * s + 1"
+        );
+        assert_eq!(
+            Expression::parse("2*s*R")
+                .unwrap()
+                .collect(&s)
+                .unwrap()
+                .to_infix(),
+            "2 * R * s"
+        );
+    }
+
+    #[test]
+    fn test_coefficient() {
+        let s = Symbol::new("s".to_string());
+        let polynomial = &product(&symbol("a"), &symbol("s").pow(2).unwrap())
+            + &product(&symbol("b"), &symbol("s"));
+        let polynomial = &polynomial + &(&symbol("s") + &integer(1));
+        assert_eq!(polynomial.degree_in(&s), Some(2));
+        assert_eq!(polynomial.coefficient(&s, 1).to_string(), "b + 1");
+        assert_eq!(polynomial.coefficient(&s, 0).to_string(), "1");
+        assert!(polynomial.coefficient(&s, 3).is_zero());
+        assert_eq!(Polynomial::zero().degree_in(&s), None);
+    }
+}
@@ -0,0 +1,382 @@
+use num::Signed;
+
+use crate::rational::Rational;
+use crate::{Expression, Operator, Symbol};
+
+// binding strength of expressions that never need parentheses
+const ATOM: u8 = u8::MAX;
+
+fn parenthesize(text: String, needed: bool) -> String {
+    if needed {
+        format!("({})", text)
+    } else {
+        text
+    }
+}
+
+fn left_right(text: String, needed: bool) -> String {
+    if needed {
+        format!("\\left({}\\right)", text)
+    } else {
+        text
+    }
+}
+
+// -x and negative constants
+fn negated(expression: &Expression) -> Option<Result<&Expression, Rational>> {
+    match expression {
+        Expression::Constant(constant) if constant.is_negative() => Some(Err(-constant)),
+        Expression::OperatorExpression(operator_expression)
+            if operator_expression.operator == Operator::Negation =>
+        {
+            Some(Ok(&operator_expression.operands[0]))
+        }
+        _ => None,
+    }
+}
+
+// how strongly an expression holds together when printed in infix notation
+fn binding(expression: &Expression) -> u8 {
+    match expression {
+        // printed as 3/2
+        Expression::Constant(constant) if !constant.is_integer() => Operator::Division.precedence(),
+        Expression::Constant(constant) if constant.is_negative() => Operator::Negation.precedence(),
+        Expression::Constant(_) | Expression::Symbol(_) => ATOM,
+        Expression::OperatorExpression(operator_expression) => {
+            match operator_expression.operator {
+                // printed as function calls
+                Operator::Logarithm | Operator::Root => ATOM,
+                // printed as 1/x
+                Operator::Reciprocal => Operator::Division.precedence(),
+                _ => operator_expression.operator.precedence(),
+            }
+        }
+    }
+}
+
+// how strongly an expression holds together when rendered in LaTeX
+fn latex_binding(expression: &Expression) -> u8 {
+    match expression {
+        Expression::OperatorExpression(operator_expression) => {
+            match operator_expression.operator {
+                Operator::Root => ATOM,
+                // fractions group themselves, but not as the base of a power
+                Operator::Division | Operator::Reciprocal => Operator::Multiplication.precedence(),
+                _ => operator_expression.operator.precedence(),
+            }
+        }
+        Expression::Constant(constant) if !constant.is_integer() => {
+            Operator::Multiplication.precedence()
+        }
+        _ => binding(expression),
+    }
+}
+
+fn latex_constant(constant: &Rational) -> String {
+    if constant.is_integer() {
+        constant.to_string()
+    } else if constant.is_negative() {
+        format!("-{}", latex_constant(&-constant))
+    } else {
+        format!("\\frac{{{}}}{{{}}}", constant.numer(), constant.denom())
+    }
+}
+
+// R1 -> R_{1}, V_out -> V_{out}
+fn latex_symbol(symbol: &Symbol) -> String {
+    if *symbol == Symbol::euler() {
+        return "e".to_string();
+    }
+    let name = &symbol.name;
+    if let Some((base, subscript)) = name.split_once('_') {
+        return format!("{}_{{{}}}", base, subscript);
+    }
+    let base = name.trim_end_matches(|c: char| c.is_ascii_digit());
+    if base.is_empty() || base.len() == name.len() {
+        name.clone()
+    } else {
+        format!("{}_{{{}}}", base, &name[base.len()..])
+    }
+}
+
+impl Expression {
+    // infix notation that Expression::parse reads back into an equal value, with only the
+    // parentheses that Operator::precedence requires; the tree can differ where a negated
+    // summand prints as a subtraction or a negative or fractional constant as a negation or
+    // division
+    pub fn to_infix(&self) -> String {
+        let operator_expression = match self {
+            Expression::Constant(constant) => return constant.to_string(),
+            Expression::Symbol(symbol) => return symbol.name.clone(),
+            Expression::OperatorExpression(operator_expression) => operator_expression,
+        };
+        let operator = &operator_expression.operator;
+        let operands = &operator_expression.operands;
+        let precedence = operator.precedence();
+        match operator {
+            Operator::Negation => format!(
+                "-{}",
+                parenthesize(operands[0].to_infix(), binding(&operands[0]) < precedence)
+            ),
+            Operator::Reciprocal => format!(
+                "1/{}",
+                parenthesize(
+                    operands[0].to_infix(),
+                    binding(&operands[0]) <= Operator::Division.precedence()
+                )
+            ),
+            // postfix binds tighter than prefix, so -x needs parentheses as well
+            Operator::Factorial => format!(
+                "{}!",
+                parenthesize(
+                    operands[0].to_infix(),
+                    binding(&operands[0]) < Operator::Factorial.precedence()
+                        || negated(&operands[0]).is_some()
+                )
+            ),
+            Operator::Addition
+            | Operator::Subtraction
+            | Operator::Multiplication
+            | Operator::Division => {
+                let mut text =
+                    parenthesize(operands[0].to_infix(), binding(&operands[0]) < precedence);
+                for operand in &operands[1..] {
+                    // x + (-y) reads better as x - y
+                    if let (Operator::Addition, Some(negated)) = (operator, negated(operand)) {
+                        let negated = match negated {
+                            Ok(expression) => parenthesize(
+                                expression.to_infix(),
+                                binding(expression) <= precedence,
+                            ),
+                            Err(constant) => constant.to_string(),
+                        };
+                        text += &format!(" - {}", negated);
+                        continue;
+                    }
+                    text += &format!(
+                        " {} {}",
+                        operator,
+                        parenthesize(operand.to_infix(), binding(operand) <= precedence)
+                    );
+                }
+                text
+            }
+            // -x^2 would read as (-x)^2 anyway, but that is easy to misread
+            Operator::Exponentiation => format!(
+                "{}^{}",
+                parenthesize(
+                    operands[0].to_infix(),
+                    binding(&operands[0]) <= precedence || negated(&operands[0]).is_some()
+                ),
+                parenthesize(operands[1].to_infix(), binding(&operands[1]) < precedence)
+            ),
+            Operator::Logarithm => {
+                format!(
+                    "log({}, {})",
+                    operands[0].to_infix(),
+                    operands[1].to_infix()
+                )
+            }
+            Operator::Root => {
+                if operands[0] == Expression::integer(2) {
+                    format!("sqrt({})", operands[1].to_infix())
+                } else {
+                    format!(
+                        "root({}, {})",
+                        operands[0].to_infix(),
+                        operands[1].to_infix()
+                    )
+                }
+            }
+        }
+    }
+
+    pub fn to_latex(&self) -> String {
+        let operator_expression = match self {
+            Expression::Constant(constant) => return latex_constant(constant),
+            Expression::Symbol(symbol) => return latex_symbol(symbol),
+            Expression::OperatorExpression(operator_expression) => operator_expression,
+        };
+        let operands = &operator_expression.operands;
+        let additive = Operator::Addition.precedence();
+        match operator_expression.operator {
+            Operator::Negation => format!(
+                "-{}",
+                left_right(
+                    operands[0].to_latex(),
+                    latex_binding(&operands[0]) <= additive
+                )
+            ),
+            Operator::Reciprocal => format!("\\frac{{1}}{{{}}}", operands[0].to_latex()),
+            Operator::Factorial => format!(
+                "{}!",
+                left_right(operands[0].to_latex(), latex_binding(&operands[0]) != ATOM)
+            ),
+            Operator::Addition | Operator::Subtraction => {
+                let mut text = operands[0].to_latex();
+                for operand in &operands[1..] {
+                    let subtraction = operator_expression.operator == Operator::Subtraction;
+                    let (sign, operand) = match negated(operand) {
+                        Some(Ok(expression)) if !subtraction => ("-", expression.clone()),
+                        Some(Err(constant)) if !subtraction => {
+                            ("-", Expression::Constant(constant))
+                        }
+                        _ if subtraction => ("-", operand.clone()),
+                        _ => ("+", operand.clone()),
+                    };
+                    text += &format!(
+                        " {} {}",
+                        sign,
+                        left_right(
+                            operand.to_latex(),
+                            sign == "-" && latex_binding(&operand) <= additive
+                        )
+                    );
+                }
+                text
+            }
+            Operator::Multiplication => operands
+                .iter()
+                .enumerate()
+                .map(|(index, operand)| {
+                    left_right(
+                        operand.to_latex(),
+                        latex_binding(operand) <= additive
+                            || (index > 0 && negated(operand).is_some()),
+                    )
+                })
+                .collect::<Vec<String>>()
+                .join(" \\cdot "),
+            // a / b / c -> \frac{a}{b \cdot c}
+            Operator::Division => {
+                let denominator = operands[1..]
+                    .iter()
+                    .map(|operand| {
+                        left_right(
+                            operand.to_latex(),
+                            operands.len() > 2 && latex_binding(operand) <= additive,
+                        )
+                    })
+                    .collect::<Vec<String>>()
+                    .join(" \\cdot ");
+                format!("\\frac{{{}}}{{{}}}", operands[0].to_latex(), denominator)
+            }
+            Operator::Exponentiation => format!(
+                "{}^{{{}}}",
+                left_right(operands[0].to_latex(), latex_binding(&operands[0]) != ATOM),
+                operands[1].to_latex()
+            ),
+            Operator::Logarithm => format!(
+                "\\log_{{{}}}\\left({}\\right)",
+                operands[0].to_latex(),
+                operands[1].to_latex()
+            ),
+            Operator::Root => {
+                if operands[0] == Expression::integer(2) {
+                    format!("\\sqrt{{{}}}", operands[1].to_latex())
+                } else {
+                    format!(
+                        "\\sqrt[{}]{{{}}}",
+                        operands[0].to_latex(),
+                        operands[1].to_latex()
+                    )
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{Expression, Operator, OperatorExpression, Symbol};
+    use num::complex::Complex64;
+    use std::collections::HashMap;
+
+    fn infix(input: &str) -> String {
+        Expression::parse(input).unwrap().to_infix()
+    }
+
+    fn latex(input: &str) -> String {
+        Expression::parse(input).unwrap().to_latex()
+    }
+
+    #[test]
+    fn test_infix_parentheses() {
+        assert_eq!(infix("((2 * x)) + (y ^ 2)"), "2 * x + y^2");
+        assert_eq!(infix("(a + b) * c"), "(a + b) * c");
+        assert_eq!(infix("a - (b - c)"), "a - (b - c)");
+        assert_eq!(infix("(a ^ b) ^ c"), "(a^b)^c");
+        assert_eq!(infix("-(x ^ 2)"), "-(x^2)");
+        assert_eq!(infix("(-x) ^ 2"), "(-x)^2");
+        assert_eq!(infix("(-n)!"), "(-n)!");
+        assert_eq!(infix("x + -y"), "x - y");
+        assert_eq!(
+            infix("log(10, z) * root(3, x) + sqrt(y)"),
+            "log(10, z) * root(3, x) + sqrt(y)"
+        );
+    }
+
+    #[test]
+    fn test_infix_round_trip() {
+        for input in [
+            "2*x + y^2 - log(10, z)",
+            "a - (b - c) - d",
+            "a / (b * c) / d",
+            "a ^ (b ^ c) ^ d",
+            "-(a + b) * -c",
+            "(x!)! + (-x)!",
+            "a * (b / c)",
+        ] {
+            let expression = Expression::parse(input).unwrap();
+            assert!(Expression::parse(&expression.to_infix()).unwrap() == expression);
+        }
+    }
+
+    #[test]
+    fn test_infix_equal_value() {
+        let x = Expression::Symbol(Symbol::new("x".to_string()));
+        let product = |constant: Expression| {
+            Expression::OperatorExpression(
+                OperatorExpression::new(
+                    Operator::Multiplication,
+                    vec![Ok(constant), Ok(x.clone())],
+                )
+                .unwrap(),
+            )
+        };
+        let values = [("x", 2.0), ("y", 5.0)]
+            .into_iter()
+            .map(|(name, value)| (Symbol::new(name.to_string()), Complex64::from(value)))
+            .collect::<HashMap<Symbol, Complex64>>();
+        for (expression, printed) in [
+            (Expression::parse("x + -y").unwrap(), "x - y"),
+            (product(Expression::integer(-3)), "-3 * x"),
+            (product(Expression::rational(3, 2).unwrap()), "3/2 * x"),
+        ] {
+            assert_eq!(expression.to_infix(), printed);
+            // read back as a subtraction, a negation and a division
+            let reparsed = Expression::parse(printed).unwrap();
+            assert!(reparsed != expression);
+            let (value, reparsed_value) = (
+                expression.evaluate(&values).unwrap(),
+                reparsed.evaluate(&values).unwrap(),
+            );
+            assert!((value - reparsed_value).norm() < 1e-12, "{}", printed);
+        }
+    }
+
+    #[test]
+    fn test_latex() {
+        assert_eq!(latex("R2 / (R1 + R2)"), "\\frac{R_{2}}{R_{1} + R_{2}}");
+        assert_eq!(latex("(a + b) * c"), "\\left(a + b\\right) \\cdot c");
+        assert_eq!(
+            latex("(x / y) ^ (n + 1)"),
+            "\\left(\\frac{x}{y}\\right)^{n + 1}"
+        );
+        assert_eq!(latex("root(3, x) + sqrt(y)"), "\\sqrt[3]{x} + \\sqrt{y}");
+        assert_eq!(latex("log(10, V_out)"), "\\log_{10}\\left(V_{out}\\right)");
+        assert_eq!(latex("n! - (a - b)"), "n! - \\left(a - b\\right)");
+        assert_eq!(latex("x ^ 1.5 - 0.25"), "x^{\\frac{3}{2}} - \\frac{1}{4}");
+        assert_eq!(latex("_e ^ x"), "e^{x}");
+    }
+}
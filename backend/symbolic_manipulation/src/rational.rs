@@ -0,0 +1,241 @@
+use num::{BigInt, BigRational, One, Signed, ToPrimitive, Zero};
+
+use crate::Expression;
+
+// arbitrary precision, always gcd-reduced with the sign in the numerator
+pub type Rational = BigRational;
+
+// decimal exponents beyond this are typos rather than values
+const MAX_EXPONENT: u64 = 1000;
+
+fn power_of_ten(exponent: i64) -> Option<Rational> {
+    if exponent.unsigned_abs() > MAX_EXPONENT {
+        return None;
+    }
+    let power =
+        Rational::from_integer(num::pow(BigInt::from(10), exponent.unsigned_abs() as usize));
+    if exponent < 0 {
+        Some(power.recip())
+    } else {
+        Some(power)
+    }
+}
+
+// the scale of SPICE suffixes like 4.7k or 10meg
+fn suffix_exponent(suffix: &str) -> Option<i64> {
+    match suffix.to_lowercase().as_str() {
+        "" => Some(0),
+        "t" => Some(12),
+        "g" => Some(9),
+        "meg" => Some(6),
+        "k" => Some(3),
+        "m" => Some(-3),
+        "u" => Some(-6),
+        "n" => Some(-9),
+        "p" => Some(-12),
+        "f" => Some(-15),
+        _ => None,
+    }
+}
+
+// length of the number at the start of the text: digits, a fraction and an exponent
+pub(crate) fn decimal_length(text: &str) -> usize {
+    let bytes = text.as_bytes();
+    let digits = |from: usize| {
+        bytes[from..]
+            .iter()
+            .take_while(|byte| byte.is_ascii_digit())
+            .count()
+    };
+    let mut end = digits(0);
+    if bytes.get(end) == Some(&b'.') && digits(end + 1) > 0 {
+        end += 1 + digits(end + 1);
+    }
+    if matches!(bytes.get(end), Some(b'e' | b'E')) {
+        let sign = usize::from(matches!(bytes.get(end + 1), Some(b'+' | b'-')));
+        let exponent = digits(end + 1 + sign);
+        if exponent > 0 {
+            end += 1 + sign + exponent;
+        }
+    }
+    end
+}
+
+// exact value of a decimal number like 4.7, 1e-3 or 4.7k
+pub fn parse_decimal(text: &str) -> Option<Rational> {
+    let length = decimal_length(text);
+    if length == 0 {
+        return None;
+    }
+    let (number, suffix) = text.split_at(length);
+    let (mantissa, exponent) = match number.find(['e', 'E']) {
+        Some(index) => (&number[..index], number[index + 1..].parse::<i64>().ok()?),
+        None => (number, 0),
+    };
+    let (integer, fraction) = mantissa.split_once('.').unwrap_or((mantissa, ""));
+    let digits = format!("{}{}", integer, fraction).parse::<BigInt>().ok()?;
+    let exponent = exponent
+        .checked_add(suffix_exponent(suffix)?)?
+        .checked_sub(fraction.len() as i64)?;
+    Some(Rational::from_integer(digits) * power_of_ten(exponent)?)
+}
+
+// folding stops at results with more bits than this, they are kept symbolic
+pub(crate) const MAX_BITS: u64 = 1 << 16;
+
+pub(crate) fn bits(value: &Rational) -> u64 {
+    value.numer().bits().max(value.denom().bits())
+}
+
+pub fn factorial(value: &Rational) -> Option<Rational> {
+    if !value.is_integer() || value.is_negative() {
+        return None;
+    }
+    let n = value.to_integer().to_u64()?;
+    // log2(n!) < n log2(n)
+    if n.saturating_mul(64 - n.leading_zeros() as u64) > MAX_BITS {
+        return None;
+    }
+    Some(Rational::from_integer(
+        (1..=n).fold(BigInt::one(), |product, factor| product * factor),
+    ))
+}
+
+// base ^ exponent for rational exponents whose denominator root of the base is exact
+pub fn power(base: &Rational, exponent: &Rational) -> Option<Rational> {
+    // 0^0 is 1, like the rule ?x ^ 0 -> 1
+    if base.is_zero() && exponent.is_negative() {
+        return None;
+    }
+    let root = root(base, exponent.denom())?;
+    let magnitude = exponent.numer().abs().to_u64()?;
+    if bits(&root).saturating_mul(magnitude) > MAX_BITS {
+        return None;
+    }
+    let result = num::pow(root, magnitude as usize);
+    if exponent.is_negative() {
+        Some(result.recip())
+    } else {
+        Some(result)
+    }
+}
+
+// the exact nth root, odd roots of negative values are negative
+pub fn root(value: &Rational, n: &BigInt) -> Option<Rational> {
+    let n = n.to_u32().filter(|n| *n > 0)?;
+    if value.is_negative() && n % 2 == 0 {
+        return None;
+    }
+    let exact = |integer: &BigInt| {
+        let root = integer.nth_root(n);
+        (num::pow(root.clone(), n as usize) == *integer).then_some(root)
+    };
+    Some(Rational::new(exact(value.numer())?, exact(value.denom())?))
+}
+
+// the smallest base with value = base ^ exponent, e.g. 8 -> (2, 3)
+fn perfect_power(value: &Rational) -> (Rational, u32) {
+    let highest = bits(value).min(u32::MAX as u64) as u32;
+    (2..=highest)
+        .rev()
+        .find_map(|n| root(value, &n.into()).map(|root| (root, n)))
+        .unwrap_or((value.clone(), 1))
+}
+
+// log_base(value) where the result is rational, e.g. log_4(8) = 3/2
+pub fn logarithm(base: &Rational, value: &Rational) -> Option<Rational> {
+    if !base.is_positive() || base.is_one() || !value.is_positive() {
+        return None;
+    }
+    if value.is_one() {
+        return Some(Rational::zero());
+    }
+    let (base_root, base_exponent) = perfect_power(base);
+    let (value_root, value_exponent) = perfect_power(value);
+    let ratio = Rational::new(value_exponent.into(), base_exponent.into());
+    if base_root == value_root {
+        Some(ratio)
+    } else if base_root == value_root.recip() {
+        Some(-ratio)
+    } else {
+        None
+    }
+}
+
+impl Expression {
+    pub fn integer(value: i128) -> Expression {
+        Expression::Constant(Rational::from_integer(value.into()))
+    }
+
+    pub fn rational(numerator: i128, denominator: i128) -> Result<Expression, String> {
+        if denominator == 0 {
+            return Err(format!("Rational {}/0 has no value", numerator));
+        }
+        Ok(Expression::Constant(Rational::new(
+            numerator.into(),
+            denominator.into(),
+        )))
+    }
+
+    pub(crate) fn is_zero(&self) -> bool {
+        matches!(self, Expression::Constant(constant) if constant.is_zero())
+    }
+
+    pub(crate) fn is_one(&self) -> bool {
+        matches!(self, Expression::Constant(constant) if constant.is_one())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rational(numerator: i64, denominator: i64) -> Rational {
+        Rational::new(numerator.into(), denominator.into())
+    }
+
+    #[test]
+    fn test_parse_decimal() {
+        assert_eq!(parse_decimal("4.7k"), Some(rational(4700, 1)));
+        assert_eq!(parse_decimal("4.7"), Some(rational(47, 10)));
+        assert_eq!(parse_decimal("100n"), Some(rational(1, 10_000_000)));
+        assert_eq!(parse_decimal("2.5e-3"), Some(rational(1, 400)));
+        assert_eq!(parse_decimal("1MEG"), Some(rational(1_000_000, 1)));
+        assert_eq!(parse_decimal("3x"), None);
+        assert_eq!(parse_decimal("1e99999999999"), None);
+    }
+
+    #[test]
+    fn test_exact_arithmetic() {
+        assert_eq!(factorial(&rational(5, 1)), Some(rational(120, 1)));
+        assert_eq!(factorial(&rational(1, 2)), None);
+        assert_eq!(
+            power(&rational(4, 9), &rational(-3, 2)),
+            Some(rational(27, 8))
+        );
+        assert_eq!(power(&rational(2, 1), &rational(1, 2)), None);
+        assert_eq!(power(&rational(0, 1), &rational(-1, 1)), None);
+        assert_eq!(
+            power(&rational(0, 1), &rational(0, 1)),
+            Some(rational(1, 1))
+        );
+        assert_eq!(root(&rational(-8, 27), &3.into()), Some(rational(-2, 3)));
+        assert_eq!(root(&rational(-4, 1), &2.into()), None);
+        assert_eq!(
+            logarithm(&rational(4, 1), &rational(8, 1)),
+            Some(rational(3, 2))
+        );
+        assert_eq!(
+            logarithm(&rational(10, 1), &rational(1, 1000)),
+            Some(rational(-3, 1))
+        );
+        assert_eq!(logarithm(&rational(2, 1), &rational(3, 1)), None);
+    }
+
+    #[test]
+    fn test_normalization() {
+        let constant = Expression::rational(6, -4).unwrap();
+        assert_eq!(format!("{}", constant), "-3/2");
+        assert!(Expression::rational(1, 0).is_err());
+    }
+}
@@ -0,0 +1,693 @@
+use std::collections::HashMap;
+use std::sync::OnceLock;
+
+use crate::parser::ParseError;
+use crate::{Expression, Operator, OperatorExpression, Symbol};
+
+// rewrites before the rule set is considered to not terminate
+const MAX_REWRITES: usize = 10000;
+// rewrites inside the results of rewrites, rules that keep nesting the expression would
+// overflow the stack long before they reach max_rewrites
+const MAX_DEPTH: usize = 200;
+
+// what the wildcards of a pattern stand for
+pub type Bindings = HashMap<Symbol, Expression>;
+
+// symbols with a leading ? match any expression, a wildcard that occurs twice has to match
+// equal expressions, e.g. ?x - ?x
+fn is_wildcard(symbol: &Symbol) -> bool {
+    symbol.name.starts_with('?') || is_constant_wildcard(symbol)
+}
+
+// symbols with a leading # only match constants, e.g. #a * ?x + ?x -> (#a + 1) * ?x
+fn is_constant_wildcard(symbol: &Symbol) -> bool {
+    symbol.name.starts_with('#')
+}
+
+#[derive(Clone)]
+enum Transformation {
+    Pattern {
+        pattern: Expression,
+        replacement: Expression,
+    },
+    Builtin(Builtin),
+}
+
+// the steps of simplify that no pattern can express
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Builtin {
+    // (a + b) + c -> a + b + c, a pattern has a fixed number of operands, so it cannot take
+    // over the operands of a nested sum of any length
+    Merge,
+    // (+ x 2 3) -> (+ x 5), (/ 6 4) -> 3/2, a replacement cannot compute with the values its
+    // wildcards stand for
+    FoldConstants,
+    // y + 2 * x + 1 -> 1 + y + 2 * x, a pattern cannot compare two expressions
+    SortOperands,
+}
+
+impl Builtin {
+    fn name(&self) -> &'static str {
+        match self {
+            Builtin::Merge => "merge",
+            Builtin::FoldConstants => "fold constants",
+            Builtin::SortOperands => "sort operands",
+        }
+    }
+
+    fn applies_to(&self, operator: &Operator) -> bool {
+        match self {
+            Builtin::Merge => operator.is_associative(),
+            Builtin::FoldConstants => true,
+            Builtin::SortOperands => operator.is_commutative(),
+        }
+    }
+
+    fn apply(&self, expression: &Expression) -> Option<Expression> {
+        let Expression::OperatorExpression(operator_expression) = expression else {
+            return None;
+        };
+        if !self.applies_to(&operator_expression.operator) {
+            return None;
+        }
+        let mut rewritten = expression.clone();
+        match self {
+            Builtin::Merge => rewritten.merge(),
+            Builtin::FoldConstants => rewritten.fold_constants(),
+            Builtin::SortOperands => rewritten.sort_operands(),
+        }
+        (rewritten != *expression).then_some(rewritten)
+    }
+}
+
+#[derive(Clone)]
+pub struct Rule {
+    name: String,
+    transformation: Transformation,
+}
+
+impl Rule {
+    // every wildcard of the replacement has to occur in the pattern
+    pub fn new(
+        name: &str,
+        mut pattern: Expression,
+        replacement: Expression,
+    ) -> Result<Rule, String> {
+        fold_pattern(&mut pattern);
+        let bound = pattern.free_symbols();
+        if let Some(unbound) = replacement
+            .free_symbols()
+            .into_iter()
+            .find(|symbol| is_wildcard(symbol) && !bound.contains(symbol))
+        {
+            return Err(format!(
+                "Wildcard {} of rule {} does not occur in the pattern",
+                unbound, name
+            ));
+        }
+        Ok(Rule {
+            name: name.to_string(),
+            transformation: Transformation::Pattern {
+                pattern,
+                replacement,
+            },
+        })
+    }
+
+    fn builtin(builtin: Builtin) -> Rule {
+        Rule {
+            name: builtin.name().to_string(),
+            transformation: Transformation::Builtin(builtin),
+        }
+    }
+
+    // "pattern -> replacement", e.g. "log(?b, ?b^?x) -> ?x", the rule is named after the text
+    pub fn parse(rule: &str) -> Result<Rule, ParseError> {
+        let arrow = rule.find("->").ok_or_else(|| {
+            ParseError::new("Expected '->' in the rule".to_string(), 0..rule.len())
+        })?;
+        let pattern = Expression::parse(&rule[..arrow])?;
+        let offset = arrow + 2;
+        let replacement = Expression::parse(&rule[offset..]).map_err(|error| {
+            ParseError::new(
+                error.message,
+                error.span.start + offset..error.span.end + offset,
+            )
+        })?;
+        Rule::new(rule.trim(), pattern, replacement)
+            .map_err(|message| ParseError::new(message, offset..rule.len()))
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    // the rewritten expression if the rule applies to the root of the expression
+    pub fn apply(&self, expression: &Expression) -> Option<Expression> {
+        match &self.transformation {
+            Transformation::Builtin(builtin) => builtin.apply(expression),
+            Transformation::Pattern {
+                pattern,
+                replacement,
+            } => match_with(pattern, expression, Bindings::new(), &mut |bindings| {
+                Some(instantiate(replacement, &bindings))
+            })
+            .or_else(|| apply_to_part(pattern, replacement, expression)),
+        }
+    }
+}
+
+// constant parts of a pattern are folded like the expressions it is matched against, e.g. the
+// -1 of ?x ^ -1 is the constant -1 and not (- 1)
+fn fold_pattern(pattern: &mut Expression) {
+    if let Expression::OperatorExpression(operator_expression) = pattern {
+        operator_expression
+            .operands
+            .iter_mut()
+            .for_each(fold_pattern);
+        if operator_expression
+            .operands
+            .iter()
+            .all(|operand| matches!(operand, Expression::Constant(_)))
+        {
+            pattern.fold_constants();
+        }
+    }
+}
+
+// a commutative pattern also matches a part of the operands, the others stay next to the
+// replacement, e.g. ?x + -?x -> 0 turns a + b + -a into 0 + b
+fn apply_to_part(
+    pattern: &Expression,
+    replacement: &Expression,
+    expression: &Expression,
+) -> Option<Expression> {
+    let (
+        Expression::OperatorExpression(pattern_expression),
+        Expression::OperatorExpression(operator_expression),
+    ) = (pattern, expression)
+    else {
+        return None;
+    };
+    if pattern_expression.operator != operator_expression.operator
+        || !pattern_expression.operator.is_commutative()
+        || pattern_expression.operands.len() >= operator_expression.operands.len()
+    {
+        return None;
+    }
+    let (bindings, used) = match_unordered(
+        &pattern_expression.operands,
+        &operator_expression.operands,
+        &mut vec![false; operator_expression.operands.len()],
+        Bindings::new(),
+        &mut |bindings, used| Some((bindings, used.to_vec())),
+    )?;
+    let mut operands = match instantiate(replacement, &bindings) {
+        Expression::OperatorExpression(replaced)
+            if replaced.operator == operator_expression.operator =>
+        {
+            replaced.operands
+        }
+        replaced => vec![replaced],
+    };
+    operands.extend(
+        operator_expression
+            .operands
+            .iter()
+            .zip(used)
+            .filter(|(_, used)| !used)
+            .map(|(operand, _)| operand.clone()),
+    );
+    Some(Expression::operation(
+        operator_expression.operator.clone(),
+        operands,
+    ))
+}
+
+// the first way the pattern matches the expression that found accepts, the matching
+// backtracks into the next way only if found returns None
+fn match_with<R>(
+    pattern: &Expression,
+    expression: &Expression,
+    bindings: Bindings,
+    found: &mut dyn FnMut(Bindings) -> Option<R>,
+) -> Option<R> {
+    match pattern {
+        Expression::Symbol(symbol)
+            if is_constant_wildcard(symbol) && !matches!(expression, Expression::Constant(_)) =>
+        {
+            None
+        }
+        Expression::Symbol(symbol) if is_wildcard(symbol) => match bindings.get(symbol) {
+            Some(bound) if bound != expression => None,
+            Some(_) => found(bindings),
+            None => {
+                let mut bindings = bindings;
+                bindings.insert(symbol.clone(), expression.clone());
+                found(bindings)
+            }
+        },
+        Expression::Constant(_) | Expression::Symbol(_) => {
+            if pattern == expression {
+                found(bindings)
+            } else {
+                None
+            }
+        }
+        Expression::OperatorExpression(pattern_expression) => {
+            let Expression::OperatorExpression(operator_expression) = expression else {
+                return None;
+            };
+            if pattern_expression.operator != operator_expression.operator {
+                return None;
+            }
+            let patterns = &pattern_expression.operands;
+            let operands = &operator_expression.operands;
+            if pattern_expression.operator.is_commutative() {
+                if patterns.len() != operands.len() {
+                    return None;
+                }
+                return match_unordered(
+                    patterns,
+                    operands,
+                    &mut vec![false; operands.len()],
+                    bindings,
+                    &mut |bindings, _| found(bindings),
+                );
+            }
+            // a - b - c is (a - b) - c for a pattern with two operands
+            if patterns.len() == 2
+                && operands.len() > 2
+                && pattern_expression.operator.is_left_associative()
+            {
+                let (last, first) = operands.split_last().expect("more than two operands");
+                let nested = vec![
+                    Expression::operation(operator_expression.operator.clone(), first.to_vec()),
+                    last.clone(),
+                ];
+                return match_ordered(patterns, &nested, bindings, found);
+            }
+            if patterns.len() != operands.len() {
+                return None;
+            }
+            match_ordered(patterns, operands, bindings, found)
+        }
+    }
+}
+
+fn match_ordered<R>(
+    patterns: &[Expression],
+    operands: &[Expression],
+    bindings: Bindings,
+    found: &mut dyn FnMut(Bindings) -> Option<R>,
+) -> Option<R> {
+    let Some((pattern, rest)) = patterns.split_first() else {
+        return found(bindings);
+    };
+    match_with(pattern, &operands[0], bindings, &mut |bindings| {
+        match_ordered(rest, &operands[1..], bindings, found)
+    })
+}
+
+// assigns every pattern to a different operand, found gets the bindings and the used operands
+fn match_unordered<R>(
+    patterns: &[Expression],
+    operands: &[Expression],
+    used: &mut Vec<bool>,
+    bindings: Bindings,
+    found: &mut dyn FnMut(Bindings, &[bool]) -> Option<R>,
+) -> Option<R> {
+    let Some((pattern, rest)) = patterns.split_first() else {
+        return found(bindings, used);
+    };
+    for (index, operand) in operands.iter().enumerate() {
+        if used[index] {
+            continue;
+        }
+        used[index] = true;
+        let result = match_with(pattern, operand, bindings.clone(), &mut |bindings| {
+            match_unordered(rest, operands, used, bindings, found)
+        });
+        used[index] = false;
+        if result.is_some() {
+            return result;
+        }
+    }
+    None
+}
+
+fn instantiate(replacement: &Expression, bindings: &Bindings) -> Expression {
+    let mut expression = replacement.clone();
+    expression.substitute_all(bindings);
+    expression
+}
+
+// rules are tried in order, the first one that applies wins
+#[derive(Clone)]
+pub struct RuleSet {
+    rules: Vec<Rule>,
+    max_rewrites: usize,
+}
+
+impl Default for RuleSet {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl RuleSet {
+    pub fn new() -> RuleSet {
+        RuleSet {
+            rules: Vec::new(),
+            max_rewrites: MAX_REWRITES,
+        }
+    }
+
+    // the rules simplify applies, domain rules can be added on top
+    // everything but merge, fold constants and sort operands is a pattern, see Builtin
+    pub fn builtin() -> RuleSet {
+        let mut rules = RuleSet::new();
+        let patterns = |rules: &mut RuleSet, patterns: &[&str]| {
+            for rule in patterns {
+                rules.add(Rule::parse(rule).expect("valid builtin rule"));
+            }
+        };
+        patterns(
+            &mut rules,
+            &[
+                "-(-?x) -> ?x",
+                "?a * -?b -> -(?a * ?b)",
+                "?a - ?b -> ?a + -?b",
+                "?x + 0 -> ?x",
+                "?x * 1 -> ?x",
+            ],
+        );
+        rules.add(Rule::builtin(Builtin::Merge));
+        rules.add(Rule::builtin(Builtin::FoldConstants));
+        patterns(
+            &mut rules,
+            &[
+                "?x ^ 1 -> ?x",
+                "?x ^ 0 -> 1",
+                "1 ^ ?x -> 1",
+                "log(?x, ?x) -> 1",
+                // factoring out of sums
+                "?x + ?x -> 2 * ?x",
+                "#a * ?x + ?x -> (#a + 1) * ?x",
+                "#a * ?x + #b * ?x -> (#a + #b) * ?x",
+                "?x + -?x -> 0",
+                "#a * ?x + -?x -> (#a - 1) * ?x",
+                "-?x + -?x -> -2 * ?x",
+                "-(#a * ?x) -> -#a * ?x",
+                "-1 * ?x -> -?x",
+                // factoring out of products
+                "?x * ?x -> ?x ^ 2",
+                "?x ^ #a * ?x -> ?x ^ (#a + 1)",
+                "?x ^ #a * ?x ^ #b -> ?x ^ (#a + #b)",
+            ],
+        );
+        // 1/x is the division 1 / x for the parser, the reciprocal has to be built directly
+        let parse = |text: &str| Expression::parse(text).expect("valid builtin pattern");
+        let reciprocal = || Expression::operation(Operator::Reciprocal, vec![parse("?x")]);
+        let product = |factor: &str| {
+            Expression::operation(Operator::Multiplication, vec![parse(factor), reciprocal()])
+        };
+        for (name, pattern, replacement) in [
+            ("?x * 1/?x -> 1", product("?x"), parse("1")),
+            (
+                "?x ^ #a * 1/?x -> ?x ^ (#a - 1)",
+                product("?x ^ #a"),
+                parse("?x ^ (#a - 1)"),
+            ),
+            (
+                "1/?x * 1/?x -> ?x ^ -2",
+                Expression::operation(Operator::Multiplication, vec![reciprocal(), reciprocal()]),
+                parse("?x ^ -2"),
+            ),
+            ("?x ^ -1 -> 1/?x", parse("?x ^ -1"), reciprocal()),
+        ] {
+            rules.add(Rule::new(name, pattern, replacement).expect("valid builtin rule"));
+        }
+        rules.add(Rule::builtin(Builtin::SortOperands));
+        rules
+    }
+
+    pub fn add(&mut self, rule: Rule) -> &mut Self {
+        self.rules.push(rule);
+        self
+    }
+
+    pub fn with_max_rewrites(mut self, max_rewrites: usize) -> Self {
+        self.max_rewrites = max_rewrites;
+        self
+    }
+
+    pub fn rules(&self) -> &[Rule] {
+        &self.rules
+    }
+
+    // applies the rules innermost first until none applies anymore, an error if that takes
+    // more than max_rewrites, nests rewrites too deep or an expression comes back
+    pub fn rewrite(&self, expression: &Expression) -> Result<Expression, String> {
+        match self.rewrite_partially(expression) {
+            (rewritten, None) => Ok(rewritten),
+            (_, Some(error)) => Err(error),
+        }
+    }
+
+    // the expression reached when the rules stop, every rewrite before an error is kept
+    fn rewrite_partially(&self, expression: &Expression) -> (Expression, Option<String>) {
+        let mut state = State {
+            rewrites: 0,
+            error: None,
+        };
+        let rewritten = self.normalize(expression.clone(), 0, &mut state);
+        (rewritten, state.error)
+    }
+
+    // the operands are normalized first, so a rule only ever sees normalized operands, after
+    // a rewrite the operands of the result are normalized again
+    // after an error the expression is returned as it is
+    fn normalize(&self, expression: Expression, depth: usize, state: &mut State) -> Expression {
+        if state.error.is_some() {
+            return expression;
+        }
+        if depth > MAX_DEPTH {
+            state.error = Some(format!(
+                "The rules nest rewrites deeper than {} levels",
+                MAX_DEPTH
+            ));
+            return expression;
+        }
+        let mut expression = self.normalize_operands(expression, depth, state);
+        let mut seen = Vec::new();
+        while state.error.is_none() {
+            let Some(rewritten) = self.rules.iter().find_map(|rule| rule.apply(&expression)) else {
+                break;
+            };
+            state.rewrites += 1;
+            if state.rewrites > self.max_rewrites {
+                state.error = Some(format!(
+                    "The rules did not reach a fixpoint after {} rewrites",
+                    self.max_rewrites
+                ));
+                break;
+            }
+            seen.push(expression);
+            expression = self.normalize_operands(rewritten, depth + 1, state);
+            if seen.contains(&expression) {
+                state.error = Some(format!(
+                    "The rules rewrite {} in a cycle",
+                    expression.to_infix()
+                ));
+            }
+        }
+        expression
+    }
+
+    fn normalize_operands(
+        &self,
+        expression: Expression,
+        depth: usize,
+        state: &mut State,
+    ) -> Expression {
+        let Expression::OperatorExpression(operator_expression) = expression else {
+            return expression;
+        };
+        let operands = operator_expression
+            .operands
+            .into_iter()
+            .map(|operand| self.normalize(operand, depth, state))
+            .collect();
+        Expression::OperatorExpression(OperatorExpression {
+            operator: operator_expression.operator,
+            operands,
+        })
+    }
+}
+
+// shared by all normalize calls of one rewrite
+struct State {
+    rewrites: usize,
+    error: Option<String>,
+}
+
+impl Expression {
+    pub fn rewrite(&self, rules: &RuleSet) -> Result<Expression, String> {
+        rules.rewrite(self)
+    }
+
+    // the builtin rules applied until none applies anymore, if they ever run into a guard the
+    // expression they reached until then is kept, every rewrite on the way is an equivalence
+    pub fn simplify(&mut self) {
+        static BUILTIN: OnceLock<RuleSet> = OnceLock::new();
+        let rules = BUILTIN.get_or_init(|| RuleSet::builtin().with_max_rewrites(usize::MAX));
+        *self = rules.rewrite_partially(self).0;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(input: &str) -> Expression {
+        Expression::parse(input).unwrap()
+    }
+
+    fn rules(rules: &[&str]) -> RuleSet {
+        let mut set = RuleSet::new();
+        for rule in rules {
+            set.add(Rule::parse(rule).unwrap());
+        }
+        set
+    }
+
+    #[test]
+    fn test_wildcards() {
+        let set = rules(&["log(?b, ?b^?x) -> ?x", "root(?n, ?x^?n) -> ?x"]);
+        assert_eq!(
+            set.rewrite(&parse("log(2, 2^(y + 1)) * root(3, z^3)"))
+                .unwrap()
+                .to_infix(),
+            "(y + 1) * z"
+        );
+        // ?b has to match the same expression twice
+        assert_eq!(
+            set.rewrite(&parse("log(2, 3^y)")).unwrap(),
+            parse("log(2, 3^y)")
+        );
+        assert!(Rule::parse("?x -> ?y").is_err());
+        assert!(Rule::parse("?x + 1").is_err());
+    }
+
+    #[test]
+    fn test_constant_wildcards() {
+        let set = rules(&["#a * ?x + ?x -> (#a + 1) * ?x", "?x ^ -1 -> 1 / ?x"]);
+        assert_eq!(
+            set.rewrite(&parse("2 * y + y")).unwrap(),
+            parse("(2 + 1) * y")
+        );
+        assert_eq!(
+            set.rewrite(&parse("z * y + y")).unwrap(),
+            parse("z * y + y")
+        );
+        // -1 in a pattern matches the constant -1
+        assert_eq!(
+            set.rewrite(&Expression::operation(
+                Operator::Exponentiation,
+                vec![parse("x"), Expression::integer(-1)]
+            ))
+            .unwrap(),
+            parse("1 / x")
+        );
+    }
+
+    #[test]
+    fn test_commutative_part() {
+        let set = rules(&["?x + -?x -> 0", "?x + 0 -> ?x"]);
+        assert_eq!(set.rewrite(&parse("a + b + -a")).unwrap().to_infix(), "b");
+        // a - b - c matches as (a - b) - c
+        let set = rules(&["?a - ?b -> ?a + -?b"]);
+        assert_eq!(
+            set.rewrite(&parse("a - b - c")).unwrap().to_string(),
+            "(+ (+ a (- b)) (- c))"
+        );
+    }
+
+    #[test]
+    fn test_domain_rule() {
+        // two resistors in parallel
+        let mut set = RuleSet::builtin();
+        set.add(Rule::parse("1 / (1 / ?a + 1 / ?b) -> ?a * ?b / (?a + ?b)").unwrap());
+        assert_eq!(
+            set.rewrite(&parse("1 / (1 / R1 + 1 / R2)"))
+                .unwrap()
+                .to_infix(),
+            "R1 * R2 / (R1 + R2)"
+        );
+    }
+
+    #[test]
+    fn test_builtin() {
+        let set = RuleSet::builtin();
+        for (input, expected) in [
+            ("-(-(x * y))", "x * y"),
+            ("x - y - z", "x - y - z"),
+            ("x - (y - x)", "x - (y - x)"),
+            ("(x + 0) * 1 * y", "x * y"),
+            ("(a + b) + (c + d)", "a + b + c + d"),
+            ("2 * x * 3 + 6 / 4", "3/2 + 6 * x"),
+            ("x^1 * y^0 + 1^z + log(z, z)", "2 + x"),
+            ("x * x^2 + y + 3 * y", "4 * y + x^3"),
+            ("z + y * 2 + 1", "1 + z + 2 * y"),
+            ("-(-(x * x)) + 2 * 3", "6 + x^2"),
+            ("x - 3 * x - x", "-3 * x"),
+            ("x * x^2 * x^-1", "x^2"),
+        ] {
+            let rewritten = set.rewrite(&parse(input)).unwrap();
+            assert_eq!(rewritten.to_infix(), expected, "{}", input);
+            let mut simplified = parse(input);
+            simplified.simplify();
+            assert_eq!(simplified, rewritten, "{}", input);
+        }
+        let names = set.rules().iter().map(Rule::name).collect::<Vec<&str>>();
+        for name in [
+            "merge",
+            "fold constants",
+            "sort operands",
+            "?x + ?x -> 2 * ?x",
+        ] {
+            assert!(names.contains(&name), "{}", name);
+        }
+    }
+
+    #[test]
+    fn test_first_match() {
+        // the first assignment of the operands that matches is taken, not every permutation
+        let set = rules(&["?a + ?b + ?c + ?d + ?e + ?f + ?g + ?h + ?i + ?j -> 0"]);
+        let sum = (0..12)
+            .map(|index| format!("x{}", index))
+            .collect::<Vec<String>>()
+            .join(" + ");
+        assert_eq!(
+            set.rewrite(&parse(&sum)).unwrap().to_infix(),
+            "0 + x10 + x11"
+        );
+    }
+
+    #[test]
+    fn test_termination_guard() {
+        let set = rules(&["?a + ?b -> ?b + ?a"]);
+        assert!(set.rewrite(&parse("x + y")).is_err());
+        assert!(rules(&["?x -> ?x + 1"]).rewrite(&parse("x")).is_err());
+        let set = rules(&["?x -> ?x + 1"]).with_max_rewrites(10);
+        assert!(set
+            .rewrite(&parse("x"))
+            .unwrap_err()
+            .contains("after 10 rewrites"));
+        // the rewrites before the guard are kept
+        let (partial, error) = set.rewrite_partially(&parse("x"));
+        assert!(error.is_some());
+        assert!(partial.to_infix().starts_with("x + 1 + 1"));
+    }
+}
@@ -0,0 +1,623 @@
+use num::{One, Zero};
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::time::{Duration, Instant};
+
+use crate::rational::Rational;
+use crate::{Expression, Operator, Symbol};
+
+// index of an equivalence class, only the representative of the union-find is meaningful
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct ClassId(usize);
+
+// an expression whose operands are equivalence classes, sums and products are binary
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub enum Node {
+    Constant(Rational),
+    Symbol(Symbol),
+    Operation(Operator, Vec<ClassId>),
+}
+
+// cost of a node given the costs of the cheapest expressions of its operands, it has to be
+// larger than the cost of every operand
+pub trait CostFunction {
+    fn cost(&self, node: &Node, operands: &[f64]) -> f64;
+}
+
+pub struct NodeCount;
+
+impl CostFunction for NodeCount {
+    fn cost(&self, _: &Node, operands: &[f64]) -> f64 {
+        1.0 + operands.iter().sum::<f64>()
+    }
+}
+
+pub struct Depth;
+
+impl CostFunction for Depth {
+    fn cost(&self, _: &Node, operands: &[f64]) -> f64 {
+        1.0 + operands.iter().copied().fold(0.0, f64::max)
+    }
+}
+
+// a term to add to the graph, built from existing classes
+#[derive(Clone)]
+enum Term {
+    Class(ClassId),
+    Constant(Rational),
+    Operation(Operator, Vec<Term>),
+}
+
+fn operation(operator: Operator, operands: Vec<Term>) -> Term {
+    Term::Operation(operator, operands)
+}
+
+// equivalence classes of expressions that share their common subexpressions
+#[derive(Default)]
+pub struct EGraph {
+    // union-find over the class ids
+    parents: Vec<usize>,
+    classes: BTreeMap<ClassId, Vec<Node>>,
+    memo: HashMap<Node, ClassId>,
+    // the value of classes that contain a constant
+    constants: BTreeMap<ClassId, Rational>,
+}
+
+impl EGraph {
+    pub fn find(&self, id: ClassId) -> ClassId {
+        let mut index = id.0;
+        while self.parents[index] != index {
+            index = self.parents[index];
+        }
+        ClassId(index)
+    }
+
+    pub fn node_count(&self) -> usize {
+        self.memo.len()
+    }
+
+    pub fn class_count(&self) -> usize {
+        self.classes.len()
+    }
+
+    fn canonical(&self, node: &Node) -> Node {
+        match node {
+            Node::Operation(operator, operands) => Node::Operation(
+                operator.clone(),
+                operands.iter().map(|operand| self.find(*operand)).collect(),
+            ),
+            _ => node.clone(),
+        }
+    }
+
+    pub fn add(&mut self, node: Node) -> ClassId {
+        let node = self.canonical(&node);
+        if let Some(id) = self.memo.get(&node) {
+            return self.find(*id);
+        }
+        let id = ClassId(self.parents.len());
+        self.parents.push(id.0);
+        if let Node::Constant(constant) = &node {
+            self.constants.insert(id, constant.clone());
+        }
+        self.classes.insert(id, vec![node.clone()]);
+        self.memo.insert(node, id);
+        id
+    }
+
+    // subtractions and divisions become sums and products of inverses, sums and products
+    // become binary
+    pub fn add_expression(&mut self, expression: &Expression) -> ClassId {
+        let operator_expression = match expression {
+            Expression::Constant(constant) => return self.add(Node::Constant(constant.clone())),
+            Expression::Symbol(symbol) => return self.add(Node::Symbol(symbol.clone())),
+            Expression::OperatorExpression(operator_expression) => operator_expression,
+        };
+        let operands = operator_expression
+            .operands
+            .iter()
+            .map(|operand| self.add_expression(operand))
+            .collect::<Vec<ClassId>>();
+        let (operator, inverse) = match operator_expression.operator {
+            Operator::Subtraction => (Operator::Addition, Some(Operator::Negation)),
+            Operator::Division => (Operator::Multiplication, Some(Operator::Reciprocal)),
+            ref operator => (operator.clone(), None),
+        };
+        if operands.len() < 2 || !operator.is_associative() {
+            return self.add(Node::Operation(operator, operands));
+        }
+        operands[1..].iter().fold(operands[0], |left, right| {
+            let right = match &inverse {
+                Some(inverse) => self.add(Node::Operation(inverse.clone(), vec![*right])),
+                None => *right,
+            };
+            self.add(Node::Operation(operator.clone(), vec![left, right]))
+        })
+    }
+
+    fn add_term(&mut self, term: &Term) -> ClassId {
+        match term {
+            Term::Class(id) => self.find(*id),
+            Term::Constant(constant) => self.add(Node::Constant(constant.clone())),
+            Term::Operation(operator, operands) => {
+                let operands = operands
+                    .iter()
+                    .map(|operand| self.add_term(operand))
+                    .collect();
+                self.add(Node::Operation(operator.clone(), operands))
+            }
+        }
+    }
+
+    pub fn union(&mut self, a: ClassId, b: ClassId) -> bool {
+        let (a, b) = (self.find(a), self.find(b));
+        if a == b {
+            return false;
+        }
+        self.parents[b.0] = a.0;
+        let nodes = self.classes.remove(&b).unwrap_or_default();
+        self.classes.entry(a).or_default().extend(nodes);
+        if let Some(constant) = self.constants.remove(&b) {
+            self.constants.entry(a).or_insert(constant);
+        }
+        true
+    }
+
+    // restores the invariant that equal nodes are in the same class, merging the classes of
+    // nodes that became equal through a union of their operands
+    fn rebuild(&mut self) {
+        loop {
+            let mut memo = HashMap::<Node, ClassId>::new();
+            let mut unions = Vec::new();
+            for (id, nodes) in self.classes.iter() {
+                for node in nodes {
+                    let node = self.canonical(node);
+                    match memo.get(&node) {
+                        Some(other) if other != id => unions.push((*id, *other)),
+                        Some(_) => {}
+                        None => {
+                            memo.insert(node, *id);
+                        }
+                    }
+                }
+            }
+            let mut changed = false;
+            for (a, b) in unions {
+                changed |= self.union(a, b);
+            }
+            if !changed {
+                for id in self.classes.keys().copied().collect::<Vec<ClassId>>() {
+                    let mut seen = HashSet::new();
+                    let nodes = self.classes[&id]
+                        .iter()
+                        .map(|node| self.canonical(node))
+                        .filter(|node| seen.insert(node.clone()))
+                        .collect::<Vec<Node>>();
+                    self.classes.insert(id, nodes);
+                }
+                self.memo = memo
+                    .into_iter()
+                    .map(|(node, id)| (node, self.find(id)))
+                    .collect();
+                return;
+            }
+        }
+    }
+
+    fn nodes(&self, id: ClassId) -> &[Node] {
+        &self.classes[&self.find(id)]
+    }
+
+    fn constant(&self, id: ClassId) -> Option<&Rational> {
+        self.constants.get(&self.find(id))
+    }
+
+    // the operands of all nodes with the operator in the class
+    fn operations<'a>(
+        &'a self,
+        id: ClassId,
+        operator: &'a Operator,
+    ) -> impl Iterator<Item = &'a [ClassId]> + 'a {
+        self.nodes(id).iter().filter_map(move |node| match node {
+            Node::Operation(other, operands) if other == operator => Some(&operands[..]),
+            _ => None,
+        })
+    }
+
+    // the terms that are equal to the node, derived from the properties of its operator
+    fn rewrites(&self, node: &Node, terms: &mut Vec<Term>) {
+        let Node::Operation(operator, operands) = node else {
+            return;
+        };
+        let class = Term::Class;
+        if let Some(value) = self.fold(operator, operands) {
+            terms.push(Term::Constant(value));
+        }
+        match (operator, &operands[..]) {
+            // -(-x) = x, 1/(1/x) = x and -x = -1 * x
+            (Operator::Negation | Operator::Reciprocal, [operand]) => {
+                for inner in self.operations(*operand, operator) {
+                    terms.push(class(inner[0]));
+                }
+                if *operator == Operator::Negation {
+                    terms.push(operation(
+                        Operator::Multiplication,
+                        vec![Term::Constant(-Rational::one()), class(*operand)],
+                    ));
+                }
+            }
+            (Operator::Exponentiation, [base, exponent]) => {
+                match self.constant(*exponent) {
+                    Some(exponent) if exponent.is_one() => terms.push(class(*base)),
+                    Some(exponent) if exponent.is_zero() => {
+                        terms.push(Term::Constant(Rational::one()))
+                    }
+                    _ => {}
+                }
+                self.distribute(operator, *base, *exponent, terms);
+            }
+            (_, [a, b]) => {
+                let (a, b) = (*a, *b);
+                if operator.is_commutative() {
+                    terms.push(operation(operator.clone(), vec![class(b), class(a)]));
+                }
+                // (x op y) op b = x op (y op b)
+                if operator.is_associative() {
+                    for inner in self.operations(a, operator) {
+                        terms.push(operation(
+                            operator.clone(),
+                            vec![
+                                class(inner[0]),
+                                operation(operator.clone(), vec![class(inner[1]), class(b)]),
+                            ],
+                        ));
+                    }
+                }
+                if let Some(neutral) = operator.neutral_element() {
+                    let Expression::Constant(neutral) = neutral else {
+                        unreachable!("neutral elements are constants")
+                    };
+                    // a op e = a
+                    if self.constant(b) == Some(&neutral) {
+                        terms.push(class(a));
+                    }
+                    // a op inverse(a) = e
+                    if let Some(inverse) = operator.inverse() {
+                        if self
+                            .operations(b, &inverse)
+                            .any(|inner| self.find(inner[0]) == self.find(a))
+                        {
+                            terms.push(Term::Constant(neutral));
+                        }
+                    }
+                }
+                // s * x + s * y = s * (x + y) and s^x * s^y = s^(x + y), the coefficients add up
+                // like in factor_out and a plain operand has the coefficient 1
+                if let Some(distributive) = operator.is_distributive_under() {
+                    let forms = |id: ClassId| {
+                        self.operations(id, &distributive)
+                            .map(|inner| (self.find(inner[0]), class(inner[1])))
+                            .chain([(self.find(id), Term::Constant(Rational::one()))])
+                            .collect::<Vec<(ClassId, Term)>>()
+                    };
+                    let right_forms = forms(b);
+                    for (shared, left) in forms(a) {
+                        for (other, right) in right_forms.iter() {
+                            if shared != *other {
+                                continue;
+                            }
+                            let sum =
+                                operation(Operator::Addition, vec![left.clone(), right.clone()]);
+                            terms.push(operation(distributive.clone(), vec![class(shared), sum]));
+                        }
+                    }
+                }
+                self.distribute(operator, a, b, terms);
+            }
+            _ => {}
+        }
+    }
+
+    // s * (x + y) = s * x + s * y and s^(x + y) = s^x * s^y
+    fn distribute(&self, operator: &Operator, a: ClassId, b: ClassId, terms: &mut Vec<Term>) {
+        for outer_operator in [Operator::Addition, Operator::Multiplication] {
+            if outer_operator.is_distributive_under().as_ref() != Some(operator) {
+                continue;
+            }
+            for inner in self.operations(b, &Operator::Addition) {
+                terms.push(operation(
+                    outer_operator.clone(),
+                    vec![
+                        operation(
+                            operator.clone(),
+                            vec![Term::Class(a), Term::Class(inner[0])],
+                        ),
+                        operation(
+                            operator.clone(),
+                            vec![Term::Class(a), Term::Class(inner[1])],
+                        ),
+                    ],
+                ));
+            }
+        }
+    }
+
+    // the exact value if all operands are constants, like Expression::simplify folds them
+    fn fold(&self, operator: &Operator, operands: &[ClassId]) -> Option<Rational> {
+        let operands = operands
+            .iter()
+            .map(|operand| self.constant(*operand).cloned().map(Expression::Constant))
+            .collect::<Option<Vec<Expression>>>()?;
+        let mut expression = Expression::operation(operator.clone(), operands);
+        expression.fold_constants();
+        match expression {
+            Expression::Constant(value) => Some(value),
+            // sums and products keep the folded constant as their only operand
+            Expression::OperatorExpression(mut operator_expression)
+                if operator_expression.operands.len() == 1 =>
+            {
+                match operator_expression.operands.pop() {
+                    Some(Expression::Constant(value)) => Some(value),
+                    _ => None,
+                }
+            }
+            _ => None,
+        }
+    }
+
+    // the cheapest expression of every class, found by relaxing the costs until they are stable
+    fn extract(&self, root: ClassId, cost_function: &dyn CostFunction) -> Expression {
+        let mut best = HashMap::<ClassId, (f64, Node)>::new();
+        loop {
+            let mut changed = false;
+            for (id, nodes) in self.classes.iter() {
+                for node in nodes {
+                    let operands = match node {
+                        Node::Operation(_, operands) => operands
+                            .iter()
+                            .map(|operand| best.get(&self.find(*operand)).map(|(cost, _)| *cost))
+                            .collect::<Option<Vec<f64>>>(),
+                        _ => Some(vec![]),
+                    };
+                    let Some(operands) = operands else {
+                        continue;
+                    };
+                    let cost = cost_function.cost(node, &operands);
+                    if best.get(id).is_none_or(|(best_cost, _)| cost < *best_cost) {
+                        best.insert(*id, (cost, node.clone()));
+                        changed = true;
+                    }
+                }
+            }
+            if !changed {
+                break;
+            }
+        }
+        self.build(root, &best)
+    }
+
+    fn build(&self, id: ClassId, best: &HashMap<ClassId, (f64, Node)>) -> Expression {
+        match &best[&self.find(id)].1 {
+            Node::Constant(constant) => Expression::Constant(constant.clone()),
+            Node::Symbol(symbol) => Expression::Symbol(symbol.clone()),
+            Node::Operation(operator, operands) => {
+                let mut expression = Expression::operation(
+                    operator.clone(),
+                    operands
+                        .iter()
+                        .map(|operand| self.build(*operand, best))
+                        .collect(),
+                );
+                // binary sums and products become n-ary again, in canonical order
+                if operator.is_associative() {
+                    expression.merge();
+                    expression.sort_operands();
+                }
+                expression
+            }
+        }
+    }
+}
+
+// why the saturation stopped
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum StopReason {
+    // no rule adds anything new, the graph contains every equivalent expression the rules find
+    Saturated,
+    NodeLimit,
+    IterationLimit,
+    TimeLimit,
+}
+
+pub struct Saturation {
+    pub expression: Expression,
+    pub stop_reason: StopReason,
+    pub iterations: usize,
+    pub node_count: usize,
+}
+
+// simplifies by equality saturation: the rules only ever add equivalent expressions to an
+// e-graph, the cheapest expression is extracted at the end, so no order of rewrites gets stuck
+pub struct SaturationSimplifier {
+    cost_function: Box<dyn CostFunction>,
+    node_limit: usize,
+    iteration_limit: usize,
+    time_limit: Duration,
+}
+
+impl Default for SaturationSimplifier {
+    fn default() -> Self {
+        Self {
+            cost_function: Box::new(NodeCount),
+            node_limit: 10000,
+            iteration_limit: 30,
+            time_limit: Duration::from_secs(1),
+        }
+    }
+}
+
+impl SaturationSimplifier {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_cost_function(mut self, cost_function: impl CostFunction + 'static) -> Self {
+        self.cost_function = Box::new(cost_function);
+        self
+    }
+
+    pub fn with_node_limit(mut self, node_limit: usize) -> Self {
+        self.node_limit = node_limit;
+        self
+    }
+
+    pub fn with_iteration_limit(mut self, iteration_limit: usize) -> Self {
+        self.iteration_limit = iteration_limit;
+        self
+    }
+
+    pub fn with_time_limit(mut self, time_limit: Duration) -> Self {
+        self.time_limit = time_limit;
+        self
+    }
+
+    pub fn simplify(&self, expression: &Expression) -> Saturation {
+        let start = Instant::now();
+        let mut graph = EGraph::default();
+        let root = graph.add_expression(expression);
+        let mut iterations = 0;
+        let stop_reason = loop {
+            if iterations == self.iteration_limit {
+                break StopReason::IterationLimit;
+            }
+            iterations += 1;
+            let mut rewrites = Vec::new();
+            for (id, nodes) in graph.classes.iter() {
+                for node in nodes {
+                    let mut terms = Vec::new();
+                    graph.rewrites(node, &mut terms);
+                    rewrites.extend(terms.into_iter().map(|term| (*id, term)));
+                }
+            }
+            let (nodes, classes) = (graph.node_count(), graph.class_count());
+            let mut stop = None;
+            for (id, term) in rewrites {
+                let added = graph.add_term(&term);
+                graph.union(id, added);
+                if graph.node_count() >= self.node_limit {
+                    stop = Some(StopReason::NodeLimit);
+                    break;
+                }
+                if start.elapsed() >= self.time_limit {
+                    stop = Some(StopReason::TimeLimit);
+                    break;
+                }
+            }
+            graph.rebuild();
+            if let Some(stop) = stop {
+                break stop;
+            }
+            if graph.node_count() == nodes && graph.class_count() == classes {
+                break StopReason::Saturated;
+            }
+        };
+        Saturation {
+            expression: graph.extract(root, self.cost_function.as_ref()),
+            stop_reason,
+            iterations,
+            node_count: graph.node_count(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn simplified(input: &str) -> String {
+        SaturationSimplifier::new()
+            .simplify(&Expression::parse(input).unwrap())
+            .expression
+            .to_infix()
+    }
+
+    #[test]
+    fn test_identities() {
+        assert_eq!(simplified("2 - 3"), "-1");
+        assert_eq!(simplified("x - x"), "0");
+        assert_eq!(simplified("(x * 1 + 0) / x"), "1");
+        assert_eq!(simplified("-(-y)"), "y");
+        assert_eq!(simplified("x + x + x"), "3 * x");
+    }
+
+    #[test]
+    fn test_factoring() {
+        assert_eq!(simplified("a * b + a * c"), "a * (b + c)");
+        assert_eq!(simplified("x^2 * x^3"), "x^5");
+    }
+
+    #[test]
+    fn test_local_minimum() {
+        // simplify has to expand first, which makes the expression larger
+        let input = "(x + 1) * (x + 1) - x * x - 2 * x";
+        let mut greedy = Expression::parse(input).unwrap();
+        greedy.simplify();
+        assert_ne!(greedy.to_infix(), "1");
+        let result = SaturationSimplifier::new().simplify(&Expression::parse(input).unwrap());
+        assert_eq!(result.expression.to_infix(), "1");
+    }
+
+    #[test]
+    fn test_cost_function() {
+        // powers cost ten times as much as other nodes
+        struct NoPowers;
+        impl CostFunction for NoPowers {
+            fn cost(&self, node: &Node, operands: &[f64]) -> f64 {
+                let own = match node {
+                    Node::Operation(Operator::Exponentiation, _) => 10.0,
+                    _ => 1.0,
+                };
+                own + operands.iter().sum::<f64>()
+            }
+        }
+        let expression = Expression::parse("x * x * x").unwrap();
+        let simplifier = SaturationSimplifier::new();
+        assert_eq!(
+            simplifier.simplify(&expression).expression.to_infix(),
+            "x^3"
+        );
+        let simplifier = simplifier.with_cost_function(NoPowers);
+        assert_eq!(
+            simplifier.simplify(&expression).expression.to_infix(),
+            "x * x * x"
+        );
+        let depth = SaturationSimplifier::new().with_cost_function(Depth);
+        assert_eq!(depth.simplify(&expression).expression.to_infix(), "x^3");
+    }
+
+    #[test]
+    fn test_limits() {
+        let expression = Expression::parse("(a + b) * (c + d) * (e + f) - a * c * e").unwrap();
+        let result = SaturationSimplifier::new()
+            .with_node_limit(200)
+            .simplify(&expression);
+        assert_eq!(result.stop_reason, StopReason::NodeLimit);
+        // the result is still equal to the input
+        let values = ["a", "b", "c", "d", "e", "f"]
+            .iter()
+            .enumerate()
+            .map(|(index, name)| (Symbol::new(name.to_string()), (index as f64 + 1.5).into()))
+            .collect();
+        let expected = expression.evaluate(&values).unwrap();
+        assert!((result.expression.evaluate(&values).unwrap() - expected).norm() < 1e-9);
+
+        let result = SaturationSimplifier::new()
+            .with_iteration_limit(1)
+            .simplify(&expression);
+        assert_eq!(result.stop_reason, StopReason::IterationLimit);
+        assert_eq!(result.iterations, 1);
+        let result = SaturationSimplifier::new()
+            .with_time_limit(Duration::ZERO)
+            .simplify(&expression);
+        assert_eq!(result.stop_reason, StopReason::TimeLimit);
+    }
+}
@@ -0,0 +1,100 @@
+use std::collections::{HashMap, HashSet};
+
+use crate::{Expression, Symbol};
+
+impl Expression {
+    // replaces every occurrence of the symbol in place, only the replacement is cloned
+    // the result is not simplified, e.g. plugging in numbers needs a simplify to fold them
+    pub fn substitute(&mut self, symbol: &Symbol, replacement: &Expression) {
+        match self {
+            Expression::Symbol(other) if other == symbol => *self = replacement.clone(),
+            Expression::OperatorExpression(operator_expression) => {
+                for operand in &mut operator_expression.operands {
+                    operand.substitute(symbol, replacement);
+                }
+            }
+            _ => {}
+        }
+    }
+
+    // all symbols are replaced at once, so a -> b and b -> a swaps them
+    pub fn substitute_all(&mut self, replacements: &HashMap<Symbol, Expression>) {
+        match self {
+            Expression::Symbol(symbol) => {
+                if let Some(replacement) = replacements.get(symbol) {
+                    *self = replacement.clone();
+                }
+            }
+            Expression::OperatorExpression(operator_expression) => {
+                for operand in &mut operator_expression.operands {
+                    operand.substitute_all(replacements);
+                }
+            }
+            Expression::Constant(_) => {}
+        }
+    }
+
+    pub fn free_symbols(&self) -> HashSet<Symbol> {
+        let mut symbols = HashSet::new();
+        self.collect_symbols(&mut symbols);
+        symbols
+    }
+
+    fn collect_symbols(&self, symbols: &mut HashSet<Symbol>) {
+        match self {
+            Expression::Symbol(symbol) => {
+                if !symbols.contains(symbol) {
+                    symbols.insert(symbol.clone());
+                }
+            }
+            Expression::OperatorExpression(operator_expression) => {
+                for operand in &operator_expression.operands {
+                    operand.collect_symbols(symbols);
+                }
+            }
+            Expression::Constant(_) => {}
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn symbol(name: &str) -> Symbol {
+        Symbol::new(name.to_string())
+    }
+
+    #[test]
+    fn test_substitute() {
+        let mut expression = Expression::parse("Vin * R2 / (R1 + R2)").unwrap();
+        expression.substitute(&symbol("R2"), &Expression::parse("4.7k").unwrap());
+        assert_eq!(expression.to_infix(), "Vin * 4700 / (R1 + 4700)");
+        expression.substitute(&symbol("R1"), &Expression::parse("300").unwrap());
+        expression.substitute(&symbol("Vin"), &Expression::integer(5));
+        expression.simplify();
+        assert_eq!(expression.to_infix(), "47/10");
+    }
+
+    #[test]
+    fn test_substitute_all() {
+        let mut expression = Expression::parse("a - b / c").unwrap();
+        let replacements = HashMap::from([
+            (symbol("a"), Expression::parse("b").unwrap()),
+            (symbol("b"), Expression::parse("a").unwrap()),
+            (symbol("c"), Expression::parse("x1 + x2").unwrap()),
+        ]);
+        expression.substitute_all(&replacements);
+        assert_eq!(expression.to_infix(), "b - a / (x1 + x2)");
+    }
+
+    #[test]
+    fn test_free_symbols() {
+        let expression = Expression::parse("x * y + log(10, x) - 3").unwrap();
+        assert_eq!(
+            expression.free_symbols(),
+            HashSet::from([symbol("x"), symbol("y")])
+        );
+        assert!(Expression::integer(3).free_symbols().is_empty());
+    }
+}